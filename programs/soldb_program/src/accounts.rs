@@ -1,11 +1,1257 @@
 use borsh::{BorshDeserialize, BorshSerialize};
+use bytemuck::{Pod, Zeroable};
+use solana_program::{
+    hash::hashv,
+    pubkey::{MAX_SEED_LEN, Pubkey},
+};
+use solana_program_error::ToStr;
+
+use crate::{
+    error::{Result, SolDbError},
+    merkle::{self, EMPTY},
+};
+
+/// Maximum length of a table name, which is used as a PDA seed.
+pub const MAX_NAME_LEN: usize = MAX_SEED_LEN;
+
+/// Maximum length of a key, which is used as a PDA seed.
+pub const MAX_KEY_LEN: usize = MAX_SEED_LEN;
+
+/// Seed prefix of the staging buffer PDA, derived as `[UPLOAD_SEED, value]`.
+pub const UPLOAD_SEED: &[u8] = b"upload";
+
+/// Seed prefix of the slab PDAs of a packed table, derived as
+/// `[SLAB_SEED, table, index]`.
+pub const SLAB_SEED: &[u8] = b"slab";
+
+/// Seed prefix of the directory pages of a table, derived as
+/// `[DIRECTORY_SEED, table, page]` with `page` in little-endian.
+pub const DIRECTORY_SEED: &[u8] = b"dir";
+
+/// Seed prefix of the Merkle tree of a compressed table, derived as
+/// `[TREE_SEED, table]`.
+pub const TREE_SEED: &[u8] = b"tree";
+
+/// Seed prefix of the Bloom filter of a table, derived as
+/// `[BLOOM_SEED, table]`.
+pub const BLOOM_SEED: &[u8] = b"bloom";
+
+/// Seed prefix of the relay nonce PDA of a signer, derived as
+/// `[NONCE_SEED, signer]`.
+pub const NONCE_SEED: &[u8] = b"nonce";
+
+/// Seed prefix of a multisig authority PDA, derived as
+/// `[MULTISIG_SEED, creator, name]`.
+pub const MULTISIG_SEED: &[u8] = b"multisig";
+
+/// Seed prefix of the delegation PDA granting a session key writes to a
+/// table, derived as `[DELEGATION_SEED, table, delegate]`.
+pub const DELEGATION_SEED: &[u8] = b"delegation";
+
+/// Seed prefix of the ACL entry PDA granting roles on a table to a
+/// principal, derived as `[ACL_SEED, table, principal]`.
+pub const ACL_SEED: &[u8] = b"acl";
+
+/// Seed prefix of the PDA naming the authority of a value, derived as
+/// `[VALUE_AUTHORITY_SEED, value]`.
+pub const VALUE_AUTHORITY_SEED: &[u8] = b"authority";
+
+/// Largest value stored inline in a slab entry. Larger values must be
+/// migrated to a dedicated PDA.
+pub const MAX_ENTRY_LEN: usize = 60;
+
+/// First byte of every account owned by the program, telling the account
+/// types apart so that one can never be passed where another is expected.
+#[repr(u8)]
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AccountKind {
+    Uninitialized,
+    Table,
+    Value,
+    Upload,
+    Slab,
+    Directory,
+    Tree,
+    Bloom,
+    Nonce,
+    Multisig,
+    Delegation,
+    Acl,
+    ValueAuthority,
+}
+
+/// How a table stores its values.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TableMode {
+    /// Every value lives in its own PDA.
+    Dedicated,
+    /// Values live in `slabs` fixed-size slab accounts, each key hashing to
+    /// one slab. Values migrated out of their slab live in their own PDA.
+    Packed { slabs: u8 },
+    /// Values only live in the transaction logs of the writes, and a tree
+    /// account holds the Merkle root over `2^depth` leaves committing to
+    /// them.
+    Compressed { depth: u8 },
+    /// Like `Dedicated`, with value PDAs derived from `[key, table]` alone
+    /// instead of `[key, table, owner]`: the table holds a single key space,
+    /// where each key is created by the first writer allowed to insert it.
+    /// Any writer allowed to put may then overwrite it.
+    Global,
+}
+
+impl TableMode {
+    /// Whether every value lives in its own PDA, listed in the directory of
+    /// the table.
+    pub fn is_dedicated(self) -> bool {
+        matches!(self, Self::Dedicated | Self::Global)
+    }
+}
+
+/// Byte range of every payload of a dedicated table holding the key of a
+/// value in another dedicated table, its parent.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ForeignKey {
+    /// Parent table.
+    pub parent: Pubkey,
+    /// Owner of the parent table, from which its value PDAs are derived.
+    pub parent_owner: Pubkey,
+    /// Start of the key in the payload.
+    pub offset: u32,
+    /// Length of the key.
+    pub len: u8,
+    /// Whether a parent value cannot be deleted while values point at it.
+    pub restrict: bool,
+}
+
+impl ForeignKey {
+    /// Parent key held by `payload`, `None` when the payload is too short.
+    pub fn reference<'a>(&self, payload: &'a [u8]) -> Option<&'a [u8]> {
+        let start = self.offset as usize;
+        payload.get(start..start + self.len as usize)
+    }
+}
+
+/// Token whose holders may insert and put values of a dedicated table,
+/// through any SPL Token or Token-2022 account of theirs.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TokenGate {
+    pub mint: Pubkey,
+    /// Balance a token account must hold, in base units of the mint.
+    pub min_amount: u64,
+}
 
 #[derive(BorshDeserialize, BorshSerialize, Debug)]
 pub struct SolTable {
+    pub discriminator: AccountKind,
+    /// Canonical bump of the table PDA.
+    pub bump: u8,
+    pub mode: TableMode,
+    /// Number of keys listed in the directory of a dedicated table. Packed
+    /// tables are enumerated through their slabs and leave it at zero.
+    pub keys: u32,
+    /// Merkle root over the keys and values of a dedicated table, see
+    /// [`crate::merkle`]. Packed tables have no directory and leave it
+    /// empty, so their values cannot be proven.
+    pub root: [u8; 32],
+    /// Whether a `SolBloom` filter tracks the keys of the table, in which
+    /// case every instruction creating a key takes it.
+    pub bloom: bool,
+    /// Foreign key checked on every write, declared by `SetForeignKey`.
+    pub foreign_key: Option<ForeignKey>,
+    /// Program invoked after every mutation of a key, see [`crate::hook`].
+    pub hook: Option<Pubkey>,
+    /// Token letting its holders write, declared by `SetTokenGate`.
+    pub token_gate: Option<TokenGate>,
+    /// Receives the lamports of the values closed by `Moderate`, the owner
+    /// when `None`. Set by `SetModerationDestination`.
+    pub moderation_destination: Option<Pubkey>,
     pub name: String,
+    /// Root of the subtree of every directory page.
+    pub page_roots: Vec<[u8; 32]>,
 }
 
-#[derive(BorshDeserialize, BorshSerialize, Debug)]
+impl SolTable {
+    pub fn new(bump: u8, mode: TableMode, name: String) -> Self {
+        Self {
+            discriminator: AccountKind::Table,
+            bump,
+            mode,
+            keys: 0,
+            root: EMPTY,
+            bloom: false,
+            foreign_key: None,
+            hook: None,
+            token_gate: None,
+            moderation_destination: None,
+            name,
+            page_roots: Vec::new(),
+        }
+    }
+
+    /// Reads a table account, failing if it holds another account type.
+    pub fn load(data: &[u8]) -> Result<Self> {
+        let table = Self::deserialize(&mut &data[..]).map_err(|_| SolDbError::NotTable)?;
+        require!(
+            table.discriminator == AccountKind::Table,
+            SolDbError::NotTable
+        );
+        Ok(table)
+    }
+}
+
+/// Fixed header at the start of every value account, followed by `len`
+/// payload bytes.
+///
+/// The header is plain old data so that processors can read and write the
+/// payload in place instead of (de)serializing it through the heap. The
+/// account may be larger than `LEN + len`; bytes past the payload are unused.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
 pub struct SolValue {
-    pub val: Vec<u8>,
+    /// Always `AccountKind::Value`, stored as a byte to keep the header `Pod`.
+    pub discriminator: u8,
+    /// Canonical bump of the value PDA.
+    pub bump: u8,
+    /// Set when a `SolValueAuthority` controls the value, which the owner of
+    /// the table can then no longer write.
+    pub has_authority: u8,
+    pub padding: [u8; 1],
+    /// Length of the payload.
+    pub len: u32,
+    /// Payload size the account is kept large enough for, even when the
+    /// payload is shorter, so that writes up to it never reallocate.
+    pub capacity: u32,
+    /// Number of values pointing at this one through a restricting foreign
+    /// key. The value cannot be deleted until it drops to zero.
+    pub refs: u32,
+}
+
+impl SolValue {
+    pub const LEN: usize = std::mem::size_of::<Self>();
+
+    pub fn new(len: usize, capacity: usize, bump: u8) -> Self {
+        Self {
+            discriminator: AccountKind::Value as u8,
+            bump,
+            has_authority: 0,
+            padding: [0; 1],
+            len: len as u32,
+            capacity: capacity as u32,
+            refs: 0,
+        }
+    }
+
+    /// Account size needed to store a value of `len` bytes.
+    pub fn space(len: usize) -> usize {
+        Self::LEN + len
+    }
+
+    /// Account size needed to store a payload of `len` bytes while keeping
+    /// the reserved capacity.
+    pub fn reserved_space(&self, len: usize) -> usize {
+        Self::space(len.max(self.capacity as usize))
+    }
+
+    /// Reads the header of a value account.
+    pub fn load(data: &[u8]) -> Result<Self> {
+        let header = data.get(..Self::LEN).ok_or(SolDbError::NotValue)?;
+        let value: Self = bytemuck::pod_read_unaligned(header);
+        require!(
+            value.discriminator == AccountKind::Value as u8
+                && Self::space(value.len as usize) <= data.len(),
+            SolDbError::NotValue
+        );
+        Ok(value)
+    }
+
+    /// Borrows the payload of a value account.
+    pub fn payload(data: &[u8]) -> Result<&[u8]> {
+        let value = Self::load(data)?;
+        Ok(&data[Self::LEN..Self::space(value.len as usize)])
+    }
+
+    /// Writes the header into a value account.
+    pub fn store(&self, data: &mut [u8]) {
+        data[..Self::LEN].copy_from_slice(bytemuck::bytes_of(self));
+    }
+}
+
+/// Header of a staging buffer used to upload a value across several
+/// transactions. The uploaded bytes follow the header in the account data.
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct SolUpload {
+    pub discriminator: AccountKind,
+    /// Canonical bump of the buffer PDA.
+    pub bump: u8,
+    /// Final length of the value being uploaded.
+    pub len: u32,
+    /// Running digest of every chunk written so far.
+    pub digest: [u8; 32],
+    /// Digest the client committed to when opening the upload.
+    pub expected: [u8; 32],
+}
+
+impl SolUpload {
+    pub const LEN: usize = 1 + 1 + 4 + 32 + 32;
+
+    pub fn new(bump: u8, len: u32, expected: [u8; 32]) -> Self {
+        Self {
+            discriminator: AccountKind::Upload,
+            bump,
+            len,
+            digest: [0; 32],
+            expected,
+        }
+    }
+
+    /// Reads the header of a staging buffer, failing if the account holds
+    /// another account type.
+    pub fn load(data: &[u8]) -> Result<Self> {
+        let header = data.get(..Self::LEN).ok_or(SolDbError::NotUpload)?;
+        let upload = Self::try_from_slice(header).map_err(|_| SolDbError::NotUpload)?;
+        require!(
+            upload.discriminator == AccountKind::Upload,
+            SolDbError::NotUpload
+        );
+        Ok(upload)
+    }
+
+    /// Folds a chunk written at `offset` into the running `digest`.
+    ///
+    /// The digest of an upload starts at `[0; 32]` and chains every
+    /// `WriteChunk` in the order they are sent.
+    pub fn chain(digest: &[u8; 32], offset: u32, data: &[u8]) -> [u8; 32] {
+        hashv(&[digest, &offset.to_le_bytes(), data]).to_bytes()
+    }
+}
+
+/// Header of a slab account of a packed table, followed by `SLOTS` entries.
+///
+/// Keys are placed by open addressing: each key hashes to a slab and to a
+/// starting slot within it, and is stored in the first free slot found by
+/// linear probing from there. Deleted entries leave a tombstone so that the
+/// keys probed past them remain reachable.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct SolSlab {
+    /// Always `AccountKind::Slab`, stored as a byte to keep the header `Pod`.
+    pub discriminator: u8,
+    /// Canonical bump of the slab PDA.
+    pub bump: u8,
+    /// Index of the slab within its table.
+    pub index: u8,
+    pub padding: u8,
+    /// Number of occupied entries.
+    pub count: u32,
+}
+
+impl SolSlab {
+    pub const LEN: usize = std::mem::size_of::<Self>();
+    pub const SLOTS: usize = 64;
+    pub const SPACE: usize = Self::LEN + Self::SLOTS * SlabEntry::LEN;
+
+    pub fn new(index: u8, bump: u8) -> Self {
+        Self {
+            discriminator: AccountKind::Slab as u8,
+            bump,
+            index,
+            padding: 0,
+            count: 0,
+        }
+    }
+
+    /// Reads the header of a slab account.
+    pub fn load(data: &[u8]) -> Result<Self> {
+        require!(data.len() == Self::SPACE, SolDbError::NotSlab);
+        let slab: Self = bytemuck::pod_read_unaligned(&data[..Self::LEN]);
+        require!(
+            slab.discriminator == AccountKind::Slab as u8,
+            SolDbError::NotSlab
+        );
+        Ok(slab)
+    }
+
+    /// Writes the header into a slab account.
+    pub fn store(&self, data: &mut [u8]) {
+        data[..Self::LEN].copy_from_slice(bytemuck::bytes_of(self));
+    }
+
+    /// Index of the slab holding `key` in a table of `slabs` slabs.
+    pub fn index_of(key: &[u8], slabs: u8) -> u8 {
+        (Self::key_hash(key, 0) % slabs as u64) as u8
+    }
+
+    /// Reads the entry in `slot`.
+    pub fn entry(data: &[u8], slot: usize) -> SlabEntry {
+        let start = Self::LEN + slot * SlabEntry::LEN;
+        bytemuck::pod_read_unaligned(&data[start..start + SlabEntry::LEN])
+    }
+
+    /// Writes `entry` into `slot`.
+    pub fn set_entry(data: &mut [u8], slot: usize, entry: &SlabEntry) {
+        let start = Self::LEN + slot * SlabEntry::LEN;
+        data[start..start + SlabEntry::LEN].copy_from_slice(bytemuck::bytes_of(entry));
+    }
+
+    /// Finds the slot of `key`, whether its value is inline or migrated.
+    pub fn find(data: &[u8], key: &[u8]) -> Option<usize> {
+        for slot in Self::probe(key) {
+            let entry = Self::entry(data, slot);
+            match entry.state() {
+                EntryState::Empty => return None,
+                EntryState::Tombstone => {}
+                EntryState::Occupied | EntryState::Migrated => {
+                    if entry.key() == key {
+                        return Some(slot);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Keys of every entry, inline or migrated.
+    pub fn keys(data: &[u8]) -> Result<Vec<Vec<u8>>> {
+        Self::load(data)?;
+        Ok((0..Self::SLOTS)
+            .map(|slot| Self::entry(data, slot))
+            .filter(|entry| matches!(entry.state(), EntryState::Occupied | EntryState::Migrated))
+            .map(|entry| entry.key().to_vec())
+            .collect())
+    }
+
+    /// Finds a free slot for `key`, failing if the key is already present.
+    pub fn vacant(data: &[u8], key: &[u8]) -> Result<usize> {
+        let mut free = None;
+        for slot in Self::probe(key) {
+            let entry = Self::entry(data, slot);
+            match entry.state() {
+                EntryState::Empty => return Ok(free.unwrap_or(slot)),
+                EntryState::Tombstone => {
+                    free.get_or_insert(slot);
+                }
+                EntryState::Occupied | EntryState::Migrated => {
+                    require!(entry.key() != key, SolDbError::AccountAlreadyExists);
+                }
+            }
+        }
+        free.ok_or_else(|| SolDbError::SlabFull.into())
+    }
+
+    /// Slots in the order they are probed for `key`.
+    fn probe(key: &[u8]) -> impl Iterator<Item = usize> {
+        let start = (Self::key_hash(key, 1) % Self::SLOTS as u64) as usize;
+        (0..Self::SLOTS).map(move |i| (start + i) % Self::SLOTS)
+    }
+
+    /// Reads the `word`-th 8 bytes of the hash of `key`.
+    fn key_hash(key: &[u8], word: usize) -> u64 {
+        let hash = hashv(&[key]).to_bytes();
+        u64::from_le_bytes(hash[word * 8..word * 8 + 8].try_into().unwrap())
+    }
+}
+
+/// State of a slab entry.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EntryState {
+    Empty,
+    Occupied,
+    Tombstone,
+    /// The value moved to its dedicated PDA. The key is kept so that it stays
+    /// unique and readers know where to look.
+    Migrated,
+}
+
+/// Entry of a slab, holding a key and a value of up to `MAX_ENTRY_LEN` bytes.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct SlabEntry {
+    pub state: u8,
+    pub key_len: u8,
+    pub len: u8,
+    pub padding: u8,
+    pub key: [u8; MAX_KEY_LEN],
+    pub value: [u8; MAX_ENTRY_LEN],
+}
+
+impl SlabEntry {
+    pub const LEN: usize = std::mem::size_of::<Self>();
+
+    /// Builds an occupied entry. `key` and `value` must fit their limits.
+    pub fn new(key: &[u8], value: &[u8]) -> Self {
+        let mut entry = Self::zeroed();
+        entry.state = EntryState::Occupied as u8;
+        entry.key_len = key.len() as u8;
+        entry.key[..key.len()].copy_from_slice(key);
+        entry.set_value(value);
+        entry
+    }
+
+    pub fn state(&self) -> EntryState {
+        match self.state {
+            1 => EntryState::Occupied,
+            2 => EntryState::Tombstone,
+            3 => EntryState::Migrated,
+            _ => EntryState::Empty,
+        }
+    }
+
+    pub fn key(&self) -> &[u8] {
+        &self.key[..self.key_len as usize]
+    }
+
+    pub fn value(&self) -> &[u8] {
+        &self.value[..self.len as usize]
+    }
+
+    /// Replaces the value, clearing the bytes past it.
+    pub fn set_value(&mut self, value: &[u8]) {
+        self.len = value.len() as u8;
+        self.value = [0; MAX_ENTRY_LEN];
+        self.value[..value.len()].copy_from_slice(value);
+    }
+}
+
+/// Header of a directory page, followed by `PAGE_KEYS` entries.
+///
+/// The pages of a dedicated table list its keys densely: `Insert` appends to
+/// the last page and `Delete` moves the last key into the entry it frees, so
+/// that the table's `keys` keys always fill pages `0..=page_of(keys - 1)`.
+/// Clients can then enumerate a table by reading those PDAs.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct SolDirectory {
+    /// Always `AccountKind::Directory`, stored as a byte to keep the header
+    /// `Pod`.
+    pub discriminator: u8,
+    /// Canonical bump of the page PDA.
+    pub bump: u8,
+    pub padding: [u8; 2],
+    /// Index of the page within its table.
+    pub page: u32,
+    /// Number of keys in the page, stored in its first entries.
+    pub count: u32,
+}
+
+impl SolDirectory {
+    pub const LEN: usize = std::mem::size_of::<Self>();
+    pub const PAGE_KEYS: usize = 64;
+    pub const SPACE: usize = Self::LEN + Self::PAGE_KEYS * DirectoryEntry::LEN;
+
+    pub fn new(page: u32, bump: u8) -> Self {
+        Self {
+            discriminator: AccountKind::Directory as u8,
+            bump,
+            padding: [0; 2],
+            page,
+            count: 0,
+        }
+    }
+
+    /// Reads the header of a directory page.
+    pub fn load(data: &[u8]) -> Result<Self> {
+        require!(data.len() == Self::SPACE, SolDbError::NotDirectory);
+        let directory: Self = bytemuck::pod_read_unaligned(&data[..Self::LEN]);
+        require!(
+            directory.discriminator == AccountKind::Directory as u8
+                && directory.count as usize <= Self::PAGE_KEYS,
+            SolDbError::NotDirectory
+        );
+        Ok(directory)
+    }
+
+    /// Writes the header into a directory page.
+    pub fn store(&self, data: &mut [u8]) {
+        data[..Self::LEN].copy_from_slice(bytemuck::bytes_of(self));
+    }
+
+    /// Page listing the key at `position` of the directory.
+    pub fn page_of(position: u32) -> u32 {
+        position / Self::PAGE_KEYS as u32
+    }
+
+    /// Reads the entry in `slot`.
+    pub fn entry(data: &[u8], slot: usize) -> DirectoryEntry {
+        let start = Self::LEN + slot * DirectoryEntry::LEN;
+        bytemuck::pod_read_unaligned(&data[start..start + DirectoryEntry::LEN])
+    }
+
+    /// Writes `entry` into `slot`.
+    pub fn set_entry(data: &mut [u8], slot: usize, entry: &DirectoryEntry) {
+        let start = Self::LEN + slot * DirectoryEntry::LEN;
+        data[start..start + DirectoryEntry::LEN].copy_from_slice(bytemuck::bytes_of(entry));
+    }
+
+    /// Keys listed in a page, in directory order.
+    pub fn keys(data: &[u8]) -> Result<Vec<Vec<u8>>> {
+        let directory = Self::load(data)?;
+        Ok((0..directory.count as usize)
+            .map(|slot| Self::entry(data, slot).key().to_vec())
+            .collect())
+    }
+
+    /// Merkle leaves of every entry of a page, `EMPTY` for the free ones.
+    pub fn leaves(data: &[u8]) -> Vec<[u8; 32]> {
+        (0..Self::PAGE_KEYS)
+            .map(|slot| Self::entry(data, slot).leaf)
+            .collect()
+    }
+
+    /// Finds the slot of `key` of `writer` in a page.
+    pub fn find(data: &[u8], writer: &Pubkey, key: &[u8]) -> Result<Option<usize>> {
+        let directory = Self::load(data)?;
+        Ok((0..directory.count as usize).find(|&slot| {
+            let entry = Self::entry(data, slot);
+            &entry.writer == writer && entry.key() == key
+        }))
+    }
+}
+
+/// Entry of a directory page, holding one key of the table and its Merkle
+/// leaf.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct DirectoryEntry {
+    /// Signer the value PDA is derived from along with the key. Global
+    /// tables derive theirs from the key alone and record their owner.
+    pub writer: Pubkey,
+    pub key_len: u8,
+    pub key: [u8; MAX_KEY_LEN],
+    pub leaf: [u8; 32],
+}
+
+impl DirectoryEntry {
+    pub const LEN: usize = std::mem::size_of::<Self>();
+
+    /// Builds an entry for `key` of `writer`, which must fit `MAX_KEY_LEN`.
+    pub fn new(writer: &Pubkey, key: &[u8], leaf: [u8; 32]) -> Self {
+        let mut entry = Self::zeroed();
+        entry.writer = *writer;
+        entry.key_len = key.len() as u8;
+        entry.key[..key.len()].copy_from_slice(key);
+        entry.leaf = leaf;
+        entry
+    }
+
+    pub fn key(&self) -> &[u8] {
+        &self.key[..self.key_len as usize]
+    }
+}
+
+/// Header of the Merkle tree of a compressed table, followed by a change log
+/// of its `MAX_BUFFER` latest changes.
+///
+/// Each change records the root it produced and the path from the changed
+/// leaf up to it. A write may then prove its leaf against any root still in
+/// the log: the proof is fast-forwarded over the later changes, so that
+/// concurrent writes to different leaves do not invalidate each other.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct SolTree {
+    /// Always `AccountKind::Tree`, stored as a byte to keep the header `Pod`.
+    pub discriminator: u8,
+    /// Canonical bump of the tree PDA.
+    pub bump: u8,
+    /// Number of levels below the root.
+    pub depth: u8,
+    pub padding: [u8; 5],
+    /// Number of changes since the tree was created.
+    pub sequence: u64,
+    /// Number of change log entries in use.
+    pub buffer_len: u32,
+    /// Change log entry of the latest change, holding the current root.
+    pub active: u32,
+}
+
+impl SolTree {
+    pub const LEN: usize = std::mem::size_of::<Self>();
+    pub const MAX_DEPTH: usize = 20;
+    pub const MAX_BUFFER: usize = 8;
+    pub const SPACE: usize = Self::LEN + Self::MAX_BUFFER * ChangeLog::LEN;
+
+    /// Writes an empty tree of `depth` levels into `data`.
+    pub fn init(data: &mut [u8], depth: u8, bump: u8) {
+        let mut change = ChangeLog::zeroed();
+        for level in 0..depth as usize {
+            change.path[level] = merkle::empty_subtree(level);
+        }
+        change.root = merkle::empty_subtree(depth as usize);
+        Self::set_change(data, 0, &change);
+
+        let tree = Self {
+            discriminator: AccountKind::Tree as u8,
+            bump,
+            depth,
+            padding: [0; 5],
+            sequence: 0,
+            buffer_len: 1,
+            active: 0,
+        };
+        tree.store(data);
+    }
+
+    /// Reads the header of a tree account.
+    pub fn load(data: &[u8]) -> Result<Self> {
+        require!(data.len() == Self::SPACE, SolDbError::NotTree);
+        let tree: Self = bytemuck::pod_read_unaligned(&data[..Self::LEN]);
+        require!(
+            tree.discriminator == AccountKind::Tree as u8
+                && tree.depth as usize <= Self::MAX_DEPTH
+                && tree.active < tree.buffer_len
+                && tree.buffer_len as usize <= Self::MAX_BUFFER,
+            SolDbError::NotTree
+        );
+        Ok(tree)
+    }
+
+    /// Writes the header into a tree account.
+    pub fn store(&self, data: &mut [u8]) {
+        data[..Self::LEN].copy_from_slice(bytemuck::bytes_of(self));
+    }
+
+    /// Reads the change log entry in `slot`.
+    pub fn change(data: &[u8], slot: usize) -> ChangeLog {
+        let start = Self::LEN + slot * ChangeLog::LEN;
+        bytemuck::pod_read_unaligned(&data[start..start + ChangeLog::LEN])
+    }
+
+    fn set_change(data: &mut [u8], slot: usize, change: &ChangeLog) {
+        let start = Self::LEN + slot * ChangeLog::LEN;
+        data[start..start + ChangeLog::LEN].copy_from_slice(bytemuck::bytes_of(change));
+    }
+
+    /// Change log entries from the latest change back to the oldest one.
+    fn history(&self, data: &[u8]) -> impl Iterator<Item = ChangeLog> {
+        (0..self.buffer_len as usize).map(move |age| {
+            let slot = (self.active as usize + Self::MAX_BUFFER - age) % Self::MAX_BUFFER;
+            Self::change(data, slot)
+        })
+    }
+
+    /// Current root of the tree.
+    pub fn root(data: &[u8]) -> Result<[u8; 32]> {
+        let tree = Self::load(data)?;
+        Ok(Self::change(data, tree.active as usize).root)
+    }
+
+    /// Replaces `previous_leaf` at `index` with `new_leaf`, given its `proof`
+    /// against `root`, which may be any root still in the change log.
+    ///
+    /// Fails with `InvalidProof` when the root is too old, when the leaf
+    /// changed since, or when the proof does not match. Returns the new
+    /// root.
+    pub fn replace_leaf(
+        data: &mut [u8],
+        root: [u8; 32],
+        previous_leaf: [u8; 32],
+        new_leaf: [u8; 32],
+        index: u32,
+        proof: &[[u8; 32]],
+    ) -> Result<[u8; 32]> {
+        let mut tree = Self::load(data)?;
+        let depth = tree.depth as usize;
+        require!(
+            (index as u64) < 1 << depth && proof.len() == depth,
+            SolDbError::InvalidProof
+        );
+
+        let history: Vec<ChangeLog> = tree.history(data).collect();
+        let age = history
+            .iter()
+            .position(|change| change.root == root)
+            .ok_or(SolDbError::InvalidProof)?;
+
+        // Every later change replaced the sibling of the proven path at the
+        // level where both paths meet.
+        let mut proof = proof.to_vec();
+        for change in history[..age].iter().rev() {
+            require!(change.index != index, SolDbError::InvalidProof);
+            let level = (u32::BITS - 1 - (change.index ^ index).leading_zeros()) as usize;
+            proof[level] = change.path[level];
+        }
+        require!(
+            merkle::fold(previous_leaf, index, &proof) == history[0].root,
+            SolDbError::InvalidProof
+        );
+
+        let mut change = ChangeLog::zeroed();
+        let mut node = new_leaf;
+        for (level, sibling) in proof.iter().enumerate() {
+            change.path[level] = node;
+            node = if (index >> level) & 1 == 0 {
+                merkle::node(&node, sibling)
+            } else {
+                merkle::node(sibling, &node)
+            };
+        }
+        change.root = node;
+        change.index = index;
+
+        tree.active = ((tree.active as usize + 1) % Self::MAX_BUFFER) as u32;
+        tree.buffer_len = (tree.buffer_len + 1).min(Self::MAX_BUFFER as u32);
+        tree.sequence += 1;
+        Self::set_change(data, tree.active as usize, &change);
+        tree.store(data);
+
+        Ok(change.root)
+    }
+}
+
+/// Change log entry of a tree: the root after a change and the nodes on the
+/// path from the changed leaf, at `path[0]`, up to it.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct ChangeLog {
+    pub root: [u8; 32],
+    pub path: [[u8; 32]; SolTree::MAX_DEPTH],
+    pub index: u32,
+    pub padding: [u8; 4],
+}
+
+impl ChangeLog {
+    pub const LEN: usize = std::mem::size_of::<Self>();
+}
+
+/// Header of the Bloom filter of a table, followed by `bits / 8` bytes of
+/// filter.
+///
+/// Every key created in the table sets `hashes` bits of the filter, so that
+/// a clear bit proves the key was never inserted. Deleting a key clears
+/// nothing: `RebuildBloom` recomputes the filter from the keys the table
+/// still holds once deletes made it too dense.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct SolBloom {
+    /// Always `AccountKind::Bloom`, stored as a byte to keep the header `Pod`.
+    pub discriminator: u8,
+    /// Canonical bump of the filter PDA.
+    pub bump: u8,
+    /// Number of bits set per key.
+    pub hashes: u8,
+    pub padding: u8,
+    /// Size of the filter in bits, a multiple of 8.
+    pub bits: u32,
+    /// Number of keys added since the filter was last cleared.
+    pub count: u32,
+}
+
+impl SolBloom {
+    pub const LEN: usize = std::mem::size_of::<Self>();
+    pub const MAX_HASHES: u8 = 16;
+    /// Largest filter, keeping the account within the size a program may
+    /// create.
+    pub const MAX_BITS: u32 = 8 * 8192;
+
+    pub fn new(bits: u32, hashes: u8, bump: u8) -> Self {
+        Self {
+            discriminator: AccountKind::Bloom as u8,
+            bump,
+            hashes,
+            padding: 0,
+            bits,
+            count: 0,
+        }
+    }
+
+    /// Account size of a filter of `bits` bits.
+    pub fn space(bits: u32) -> usize {
+        Self::LEN + bits as usize / 8
+    }
+
+    /// Reads the header of a filter account.
+    pub fn load(data: &[u8]) -> Result<Self> {
+        require!(data.len() >= Self::LEN, SolDbError::NotBloom);
+        let bloom: Self = bytemuck::pod_read_unaligned(&data[..Self::LEN]);
+        require!(
+            bloom.discriminator == AccountKind::Bloom as u8
+                && data.len() == Self::space(bloom.bits),
+            SolDbError::NotBloom
+        );
+        Ok(bloom)
+    }
+
+    /// Writes the header into a filter account.
+    pub fn store(&self, data: &mut [u8]) {
+        data[..Self::LEN].copy_from_slice(bytemuck::bytes_of(self));
+    }
+
+    /// Bits set for `key`, derived from its hash by double hashing.
+    fn positions(&self, key: &[u8]) -> impl Iterator<Item = usize> {
+        let hash = hashv(&[key]).to_bytes();
+        let first = u64::from_le_bytes(hash[..8].try_into().unwrap());
+        let step = u64::from_le_bytes(hash[8..16].try_into().unwrap()) | 1;
+        let bits = self.bits as u64;
+
+        (0..self.hashes as u64)
+            .map(move |i| (first.wrapping_add(i.wrapping_mul(step)) % bits) as usize)
+    }
+
+    /// Adds `key` to the filter.
+    pub fn insert(data: &mut [u8], key: &[u8]) -> Result<()> {
+        let mut bloom = Self::load(data)?;
+        for bit in bloom.positions(key) {
+            data[Self::LEN + bit / 8] |= 1 << (bit % 8);
+        }
+        bloom.count += 1;
+        bloom.store(data);
+        Ok(())
+    }
+
+    /// Whether `key` may have been added. `false` is always exact.
+    pub fn contains(data: &[u8], key: &[u8]) -> Result<bool> {
+        let bloom = Self::load(data)?;
+        Ok(bloom
+            .positions(key)
+            .all(|bit| data[Self::LEN + bit / 8] & (1 << (bit % 8)) != 0))
+    }
+
+    /// Removes every key from the filter.
+    pub fn clear(data: &mut [u8]) -> Result<()> {
+        let mut bloom = Self::load(data)?;
+        data[Self::LEN..].fill(0);
+        bloom.count = 0;
+        bloom.store(data);
+        Ok(())
+    }
+}
+
+/// Relay nonce of a signer, counting the relayed writes it authorized.
+///
+/// A relayed write carries the nonce it was signed with, which must be the
+/// stored one, and increments it, so that every signature is used at most
+/// once.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct SolNonce {
+    /// Always `AccountKind::Nonce`, stored as a byte to keep the account
+    /// `Pod`.
+    pub discriminator: u8,
+    /// Canonical bump of the nonce PDA.
+    pub bump: u8,
+    pub padding: [u8; 6],
+    /// Nonce the next relayed write must be signed with.
+    pub nonce: u64,
+}
+
+impl SolNonce {
+    pub const LEN: usize = std::mem::size_of::<Self>();
+
+    pub fn new(bump: u8) -> Self {
+        Self {
+            discriminator: AccountKind::Nonce as u8,
+            bump,
+            padding: [0; 6],
+            nonce: 0,
+        }
+    }
+
+    /// Reads a nonce account.
+    pub fn load(data: &[u8]) -> Result<Self> {
+        require!(data.len() == Self::LEN, SolDbError::NotNonce);
+        let nonce: Self = bytemuck::pod_read_unaligned(data);
+        require!(
+            nonce.discriminator == AccountKind::Nonce as u8,
+            SolDbError::NotNonce
+        );
+        Ok(nonce)
+    }
+
+    /// Writes the nonce into its account.
+    pub fn store(&self, data: &mut [u8]) {
+        data.copy_from_slice(bytemuck::bytes_of(self));
+    }
+}
+
+/// M-of-N authority that can own tables in place of a single key.
+///
+/// A multisig passed as the owner of an instruction counts as signing it
+/// once `threshold` of its signers sign the transaction. Being a program
+/// account, it pays for the accounts of its tables out of its own lamports,
+/// which must be funded beforehand.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct SolMultisig {
+    /// Always `AccountKind::Multisig`, stored as a byte to keep the account
+    /// `Pod`.
+    pub discriminator: u8,
+    /// Canonical bump of the multisig PDA.
+    pub bump: u8,
+    /// Number of signers that must sign.
+    pub threshold: u8,
+    /// Number of listed signers, the rest of `signers` being unused.
+    pub count: u8,
+    pub padding: [u8; 4],
+    pub signers: [Pubkey; SolMultisig::MAX_SIGNERS],
+}
+
+impl SolMultisig {
+    pub const LEN: usize = std::mem::size_of::<Self>();
+    pub const MAX_SIGNERS: usize = 10;
+
+    /// Multisig requiring `threshold` of the distinct `signers`, of which
+    /// there may be at most `MAX_SIGNERS`.
+    pub fn new(bump: u8, threshold: u8, signers: &[Pubkey]) -> Result<Self> {
+        let mut multisig = Self {
+            discriminator: AccountKind::Multisig as u8,
+            bump,
+            threshold: 0,
+            count: 0,
+            padding: [0; 4],
+            signers: [Pubkey::default(); Self::MAX_SIGNERS],
+        };
+        multisig.set_signers(threshold, signers)?;
+        Ok(multisig)
+    }
+
+    /// Replaces the signers and the threshold.
+    pub fn set_signers(&mut self, threshold: u8, signers: &[Pubkey]) -> Result<()> {
+        require!(
+            threshold > 0
+                && threshold as usize <= signers.len()
+                && signers.len() <= Self::MAX_SIGNERS
+                && signers
+                    .iter()
+                    .enumerate()
+                    .all(|(i, signer)| !signers[..i].contains(signer)),
+            SolDbError::InvalidMultisig
+        );
+
+        self.threshold = threshold;
+        self.count = signers.len() as u8;
+        self.signers = [Pubkey::default(); Self::MAX_SIGNERS];
+        self.signers[..signers.len()].copy_from_slice(signers);
+        Ok(())
+    }
+
+    /// Listed signers.
+    pub fn signers(&self) -> &[Pubkey] {
+        &self.signers[..self.count as usize]
+    }
+
+    /// Reads a multisig account.
+    pub fn load(data: &[u8]) -> Result<Self> {
+        require!(data.len() == Self::LEN, SolDbError::NotMultisig);
+        let multisig: Self = bytemuck::pod_read_unaligned(data);
+        require!(
+            multisig.discriminator == AccountKind::Multisig as u8,
+            SolDbError::NotMultisig
+        );
+        Ok(multisig)
+    }
+
+    /// Writes the multisig into its account.
+    pub fn store(&self, data: &mut [u8]) {
+        data.copy_from_slice(bytemuck::bytes_of(self));
+    }
+}
+
+/// Grant letting a session key `Insert`, `Put` and `Delete` keys starting
+/// with `prefix` in a table, in place of its owner.
+///
+/// Every delegated write consumes one of the `remaining` writes, and the
+/// grant is rejected once the slot passes `expiry_slot`. The owner revokes
+/// it early by closing the account with `Revoke`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct SolDelegation {
+    /// Always `AccountKind::Delegation`, stored as a byte to keep the account
+    /// `Pod`.
+    pub discriminator: u8,
+    /// Canonical bump of the delegation PDA.
+    pub bump: u8,
+    /// Length of `prefix`, the rest of it being unused.
+    pub prefix_len: u8,
+    pub padding: [u8; 5],
+    /// Session key allowed to write.
+    pub delegate: Pubkey,
+    /// Table the grant applies to.
+    pub table: Pubkey,
+    /// Number of writes the delegate may still make.
+    pub remaining: u64,
+    /// Last slot at which the delegate may write.
+    pub expiry_slot: u64,
+    pub prefix: [u8; MAX_KEY_LEN],
+}
+
+impl SolDelegation {
+    pub const LEN: usize = std::mem::size_of::<Self>();
+
+    /// Grant of `max_writes` writes to keys starting with `prefix`, which
+    /// must fit `MAX_KEY_LEN`, until `expiry_slot`.
+    pub fn new(
+        bump: u8,
+        delegate: Pubkey,
+        table: Pubkey,
+        prefix: &[u8],
+        max_writes: u64,
+        expiry_slot: u64,
+    ) -> Result<Self> {
+        require!(prefix.len() <= MAX_KEY_LEN, SolDbError::KeyTooLong);
+
+        let mut delegation = Self {
+            discriminator: AccountKind::Delegation as u8,
+            bump,
+            prefix_len: prefix.len() as u8,
+            padding: [0; 5],
+            delegate,
+            table,
+            remaining: max_writes,
+            expiry_slot,
+            prefix: [0; MAX_KEY_LEN],
+        };
+        delegation.prefix[..prefix.len()].copy_from_slice(prefix);
+        Ok(delegation)
+    }
+
+    /// Prefix every delegated key must start with.
+    pub fn prefix(&self) -> &[u8] {
+        &self.prefix[..self.prefix_len as usize]
+    }
+
+    /// Reads a delegation account.
+    pub fn load(data: &[u8]) -> Result<Self> {
+        require!(data.len() == Self::LEN, SolDbError::NotDelegation);
+        let delegation: Self = bytemuck::pod_read_unaligned(data);
+        require!(
+            delegation.discriminator == AccountKind::Delegation as u8
+                && delegation.prefix_len as usize <= MAX_KEY_LEN,
+            SolDbError::NotDelegation
+        );
+        Ok(delegation)
+    }
+
+    /// Writes the delegation into its account.
+    pub fn store(&self, data: &mut [u8]) {
+        data.copy_from_slice(bytemuck::bytes_of(self));
+    }
+}
+
+/// Roles a principal holds on a table, letting several wallets write to the
+/// table of its owner, in its namespace.
+///
+/// The principal signs `Insert`, `Put` and `Delete` in place of the owner as
+/// a delegate does, and admins also grant and revoke roles. `READER` is not
+/// checked by the program: it tells clients which principals may be handed
+/// the keys of encrypted payloads.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct SolAcl {
+    /// Always `AccountKind::Acl`, stored as a byte to keep the account `Pod`.
+    pub discriminator: u8,
+    /// Canonical bump of the ACL entry PDA.
+    pub bump: u8,
+    /// Union of the role flags held by the principal.
+    pub roles: u8,
+    pub padding: [u8; 5],
+    /// Wallet holding the roles.
+    pub principal: Pubkey,
+    /// Table the roles apply to.
+    pub table: Pubkey,
+}
+
+impl SolAcl {
+    pub const LEN: usize = std::mem::size_of::<Self>();
+
+    /// Reads encrypted payloads, enforced by clients only.
+    pub const READER: u8 = 1 << 0;
+    /// Inserts new keys.
+    pub const INSERTER: u8 = 1 << 1;
+    /// Overwrites existing values.
+    pub const UPDATER: u8 = 1 << 2;
+    /// Deletes keys.
+    pub const DELETER: u8 = 1 << 3;
+    /// Holds every other role, and grants and revokes roles.
+    pub const ADMIN: u8 = 1 << 4;
+    /// Closes any value, whoever controls it.
+    pub const MODERATOR: u8 = 1 << 5;
+    /// Every known role flag.
+    pub const ALL: u8 = Self::READER
+        | Self::INSERTER
+        | Self::UPDATER
+        | Self::DELETER
+        | Self::ADMIN
+        | Self::MODERATOR;
+
+    pub fn new(bump: u8, principal: Pubkey, table: Pubkey) -> Self {
+        Self {
+            discriminator: AccountKind::Acl as u8,
+            bump,
+            roles: 0,
+            padding: [0; 5],
+            principal,
+            table,
+        }
+    }
+
+    /// Whether the principal holds `role`, directly or as an admin.
+    pub fn has(&self, role: u8) -> bool {
+        self.roles & (role | Self::ADMIN) != 0
+    }
+
+    /// Reads an ACL entry account.
+    pub fn load(data: &[u8]) -> Result<Self> {
+        require!(data.len() == Self::LEN, SolDbError::NotAcl);
+        let acl: Self = bytemuck::pod_read_unaligned(data);
+        require!(
+            acl.discriminator == AccountKind::Acl as u8,
+            SolDbError::NotAcl
+        );
+        Ok(acl)
+    }
+
+    /// Writes the ACL entry into its account.
+    pub fn store(&self, data: &mut [u8]) {
+        data.copy_from_slice(bytemuck::bytes_of(self));
+    }
+}
+
+/// Wallet controlling a value in place of the owner of its table, which
+/// turns the table into a registry of entries belonging to others.
+///
+/// The authority signs `Put` for the value as a delegate does, and hands
+/// the value over or releases it back to the owner with `SetValueAuthority`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct SolValueAuthority {
+    /// Always `AccountKind::ValueAuthority`, stored as a byte to keep the
+    /// account `Pod`.
+    pub discriminator: u8,
+    /// Canonical bump of the value authority PDA.
+    pub bump: u8,
+    pub padding: [u8; 6],
+    /// Value under control.
+    pub value: Pubkey,
+    /// Wallet controlling the value.
+    pub authority: Pubkey,
+}
+
+impl SolValueAuthority {
+    pub const LEN: usize = std::mem::size_of::<Self>();
+
+    pub fn new(bump: u8, value: Pubkey, authority: Pubkey) -> Self {
+        Self {
+            discriminator: AccountKind::ValueAuthority as u8,
+            bump,
+            padding: [0; 6],
+            value,
+            authority,
+        }
+    }
+
+    /// Reads a value authority account.
+    pub fn load(data: &[u8]) -> Result<Self> {
+        require!(data.len() == Self::LEN, SolDbError::NotValueAuthority);
+        let record: Self = bytemuck::pod_read_unaligned(data);
+        require!(
+            record.discriminator == AccountKind::ValueAuthority as u8,
+            SolDbError::NotValueAuthority
+        );
+        Ok(record)
+    }
+
+    /// Writes the value authority into its account.
+    pub fn store(&self, data: &mut [u8]) {
+        data.copy_from_slice(bytemuck::bytes_of(self));
+    }
 }
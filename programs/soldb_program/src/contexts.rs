@@ -0,0 +1,1266 @@
+//! Validated accounts of every instruction.
+//!
+//! Each context lists its accounts in instruction order along with the
+//! constraints they must satisfy, so that every processor starts from the
+//! same guarantees. Constraints depending on instruction data, such as PDA
+//! derivations, are checked by the `check_*` methods.
+
+use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey};
+use solana_program_error::ToStr;
+use spl_generic_token::generic_token;
+
+use crate::{
+    accounts::{
+        ForeignKey, MAX_KEY_LEN, MAX_NAME_LEN, SolAcl, SolBloom, SolDelegation, SolDirectory,
+        SolNonce, SolSlab, SolTable, SolTree, SolValue, SolValueAuthority, TableMode,
+    },
+    error::{Result, SolDbError},
+    pda::{
+        create_acl_address, create_bloom_address, create_delegation_address,
+        create_directory_address, create_global_value_address, create_nonce_address,
+        create_slab_address, create_table_address, create_tree_address, create_value_address,
+        create_value_authority_address,
+    },
+};
+
+/// Constraints usable in a [`context!`] declaration.
+pub mod constraints {
+    use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey};
+    use solana_program_error::ToStr;
+    use solana_system_interface::program as system_program;
+
+    use crate::{accounts::AccountKind, error::SolDbError};
+
+    pub fn signer(_program_id: &Pubkey, info: &AccountInfo) -> ProgramResult {
+        require!(info.is_signer, SolDbError::MissingSigner);
+        Ok(())
+    }
+
+    pub fn writable(_program_id: &Pubkey, info: &AccountInfo) -> ProgramResult {
+        require!(info.is_writable, SolDbError::NotWritable);
+        Ok(())
+    }
+
+    /// The account is owned by the program.
+    pub fn owned(program_id: &Pubkey, info: &AccountInfo) -> ProgramResult {
+        require_keys_eq!(info.owner, program_id, SolDbError::WrongOwner);
+        Ok(())
+    }
+
+    /// The account does not exist yet and is about to be created.
+    pub fn uninitialized(_program_id: &Pubkey, info: &AccountInfo) -> ProgramResult {
+        require!(
+            info.lamports() == 0 && info.data_is_empty(),
+            SolDbError::AccountAlreadyExists
+        );
+        Ok(())
+    }
+
+    pub fn system_program(_program_id: &Pubkey, info: &AccountInfo) -> ProgramResult {
+        require_keys_eq!(info.key, &system_program::ID, SolDbError::NotSystemProgram);
+        Ok(())
+    }
+
+    pub fn table(_program_id: &Pubkey, info: &AccountInfo) -> ProgramResult {
+        require!(
+            discriminator(info) == Some(AccountKind::Table as u8),
+            SolDbError::NotTable
+        );
+        Ok(())
+    }
+
+    pub fn value(_program_id: &Pubkey, info: &AccountInfo) -> ProgramResult {
+        require!(
+            discriminator(info) == Some(AccountKind::Value as u8),
+            SolDbError::NotValue
+        );
+        Ok(())
+    }
+
+    pub fn upload(_program_id: &Pubkey, info: &AccountInfo) -> ProgramResult {
+        require!(
+            discriminator(info) == Some(AccountKind::Upload as u8),
+            SolDbError::NotUpload
+        );
+        Ok(())
+    }
+
+    pub fn slab(_program_id: &Pubkey, info: &AccountInfo) -> ProgramResult {
+        require!(
+            discriminator(info) == Some(AccountKind::Slab as u8),
+            SolDbError::NotSlab
+        );
+        Ok(())
+    }
+
+    pub fn directory(_program_id: &Pubkey, info: &AccountInfo) -> ProgramResult {
+        require!(
+            discriminator(info) == Some(AccountKind::Directory as u8),
+            SolDbError::NotDirectory
+        );
+        Ok(())
+    }
+
+    pub fn tree(_program_id: &Pubkey, info: &AccountInfo) -> ProgramResult {
+        require!(
+            discriminator(info) == Some(AccountKind::Tree as u8),
+            SolDbError::NotTree
+        );
+        Ok(())
+    }
+
+    pub fn bloom(_program_id: &Pubkey, info: &AccountInfo) -> ProgramResult {
+        require!(
+            discriminator(info) == Some(AccountKind::Bloom as u8),
+            SolDbError::NotBloom
+        );
+        Ok(())
+    }
+
+    pub fn multisig(_program_id: &Pubkey, info: &AccountInfo) -> ProgramResult {
+        require!(
+            discriminator(info) == Some(AccountKind::Multisig as u8),
+            SolDbError::NotMultisig
+        );
+        Ok(())
+    }
+
+    pub fn delegation(_program_id: &Pubkey, info: &AccountInfo) -> ProgramResult {
+        require!(
+            discriminator(info) == Some(AccountKind::Delegation as u8),
+            SolDbError::NotDelegation
+        );
+        Ok(())
+    }
+
+    pub fn acl(_program_id: &Pubkey, info: &AccountInfo) -> ProgramResult {
+        require!(
+            discriminator(info) == Some(AccountKind::Acl as u8),
+            SolDbError::NotAcl
+        );
+        Ok(())
+    }
+
+    fn discriminator(info: &AccountInfo) -> Option<u8> {
+        info.data.borrow().first().copied()
+    }
+}
+
+context! {
+    /// Accounts of `InitTable`.
+    pub struct InitTableContext {
+        owner: [signer, writable],
+        table: [writable, uninitialized],
+        system_program: [system_program],
+    }
+}
+
+context! {
+    /// Accounts of `Insert`. The directory page is created by the processor
+    /// when it does not exist yet. The Bloom filter and the parent value are
+    /// only passed when the table has them, and the Bloom filter and the
+    /// directory page are left alone unless the signer created the table.
+    pub struct InsertContext {
+        owner: [signer, writable],
+        table: [writable, owned, table],
+        value: [writable, uninitialized],
+        system_program: [system_program],
+        directory: [writable],
+        bloom: [writable, owned, bloom] if has_bloom(table)? && is_creator(owner, table)?,
+        parent: [] if has_foreign_key(table)?,
+    }
+}
+
+context! {
+    /// Accounts of `Put`, `Patch`, `Append` and `Truncate`. Values migrated
+    /// out of a packed table take no directory page.
+    pub struct ValueContext {
+        owner: [signer, writable],
+        table: [owned, table],
+        value: [writable, owned, value],
+        system_program: [system_program],
+        directory: [writable] if is_dedicated(table)?,
+        parent: [] if has_foreign_key(table)?,
+    }
+}
+
+context! {
+    /// Accounts of `Delete`, which also removes the key from the directory.
+    /// Both pages are the same account when the key is in the last page.
+    pub struct DeleteContext {
+        owner: [signer, writable],
+        table: [writable, owned, table],
+        value: [writable, owned, value],
+        system_program: [system_program],
+        directory: [writable, owned, directory],
+        last_directory: [writable, owned, directory],
+        parent: [] if has_foreign_key(table)?,
+    }
+}
+
+context! {
+    /// Accounts of `Upsert`. The value and the directory page are created by
+    /// the processor when they do not exist yet. The Bloom filter is passed
+    /// whenever the table has one, as the value may be created.
+    pub struct UpsertContext {
+        owner: [signer, writable],
+        table: [writable, owned, table],
+        value: [writable],
+        system_program: [system_program],
+        directory: [writable],
+        bloom: [writable, owned, bloom] if has_bloom(table)?,
+        parent: [] if has_foreign_key(table)?,
+    }
+}
+
+context! {
+    /// Accounts of `OpenUpload`. The value and the directory page are created
+    /// by the processor when they do not exist yet, and the buffer is created
+    /// or reset.
+    pub struct OpenUploadContext {
+        owner: [signer, writable],
+        table: [writable, owned, table],
+        value: [writable],
+        buffer: [writable],
+        system_program: [system_program],
+        directory: [writable],
+        bloom: [writable, owned, bloom] if has_bloom(table)?,
+    }
+}
+
+context! {
+    /// Accounts of `Insert`, `Put` and `Delete` on a slab entry of a packed
+    /// table. The slab is created by `Insert` when it does not exist yet, and
+    /// only `Insert` takes and checks the Bloom filter.
+    pub struct SlabContext {
+        owner: [signer, writable],
+        table: [owned, table],
+        slab: [writable],
+        system_program: [system_program],
+        bloom: [] if has_bloom(table)?,
+    }
+}
+
+context! {
+    /// Accounts of `Migrate`.
+    pub struct MigrateContext {
+        owner: [signer, writable],
+        table: [owned, table],
+        slab: [writable, owned, slab],
+        value: [writable, uninitialized],
+        system_program: [system_program],
+    }
+}
+
+context! {
+    /// Accounts of `Delete` on a migrated value of a packed table, which also
+    /// releases the entry left in its slab.
+    pub struct DeleteMigratedContext {
+        owner: [signer, writable],
+        table: [owned, table],
+        value: [writable, owned, value],
+        system_program: [system_program],
+        slab: [writable, owned, slab],
+    }
+}
+
+context! {
+    /// Trailing account of `InitTable` for a compressed table, created by the
+    /// processor.
+    pub struct TreeContext {
+        tree: [writable, uninitialized],
+    }
+}
+
+context! {
+    /// Accounts of `CompressedPut` and `CompressedDelete`. The owner pays for
+    /// nothing, as the writes only change the tree.
+    pub struct CompressedContext {
+        owner: [signer],
+        table: [owned, table],
+        tree: [writable, owned, tree],
+    }
+}
+
+context! {
+    /// Accounts of `InitBloom`.
+    pub struct InitBloomContext {
+        owner: [signer, writable],
+        table: [writable, owned, table],
+        bloom: [writable, uninitialized],
+        system_program: [system_program],
+    }
+}
+
+context! {
+    /// Accounts of `MaybeContains`. The filter is only checked when the
+    /// table has one.
+    pub struct MaybeContainsContext {
+        table: [owned, table],
+        bloom: [],
+    }
+}
+
+context! {
+    /// Accounts of `RebuildBloom`, followed by the directory pages or slabs
+    /// whose keys are added.
+    pub struct RebuildBloomContext {
+        owner: [signer],
+        table: [owned, table],
+        bloom: [writable, owned, bloom],
+    }
+}
+
+context! {
+    /// Accounts of `SetForeignKey`.
+    pub struct ForeignKeyContext {
+        owner: [signer, writable],
+        table: [writable, owned, table],
+        parent: [owned, table],
+        system_program: [system_program],
+    }
+}
+
+context! {
+    /// Accounts of `SetHook`, followed by the hook program when one is
+    /// registered.
+    pub struct HookContext {
+        owner: [signer, writable],
+        table: [writable, owned, table],
+        system_program: [system_program],
+    }
+}
+
+context! {
+    /// Accounts of `SetTokenGate`.
+    pub struct TokenGateContext {
+        owner: [signer, writable],
+        table: [writable, owned, table],
+        system_program: [system_program],
+    }
+}
+
+context! {
+    /// Accounts of `Moderate`. The parent value is only passed when the table
+    /// declares a restricting foreign key. The value authority PDA is only
+    /// checked when the value has an authority, and the ACL entry when the
+    /// moderator is not the owner.
+    pub struct ModerateContext {
+        moderator: [signer],
+        owner: [writable],
+        table: [writable, owned, table],
+        value: [writable, owned, value],
+        system_program: [system_program],
+        directory: [writable, owned, directory],
+        last_directory: [writable, owned, directory],
+        destination: [writable],
+        record: [writable],
+        acl: [],
+        parent: [] if has_foreign_key(table)?,
+    }
+}
+
+context! {
+    /// Accounts of `SetModerationDestination`.
+    pub struct ModerationDestinationContext {
+        owner: [signer, writable],
+        table: [writable, owned, table],
+        system_program: [system_program],
+    }
+}
+
+context! {
+    /// Accounts of `VerifyProof`.
+    pub struct VerifyProofContext {
+        table: [owned, table],
+    }
+}
+
+context! {
+    /// Accounts of `RelayedInsert`. The nonce PDA and the directory page are
+    /// created by the processor when they do not exist yet.
+    pub struct RelayedInsertContext {
+        relayer: [signer, writable],
+        nonce: [writable],
+        instructions: [],
+        owner: [],
+        table: [writable, owned, table],
+        value: [writable, uninitialized],
+        system_program: [system_program],
+        directory: [writable],
+        bloom: [writable, owned, bloom] if has_bloom(table)?,
+        parent: [] if has_foreign_key(table)?,
+    }
+}
+
+context! {
+    /// Accounts of `RelayedPut`.
+    pub struct RelayedValueContext {
+        relayer: [signer, writable],
+        nonce: [writable],
+        instructions: [],
+        owner: [],
+        table: [writable, owned, table],
+        value: [writable, owned, value],
+        system_program: [system_program],
+        directory: [writable] if is_dedicated(table)?,
+        parent: [] if has_foreign_key(table)?,
+    }
+}
+
+context! {
+    /// Accounts of `RelayedDelete`. The owner receives the lamports of the
+    /// closed accounts.
+    pub struct RelayedDeleteContext {
+        relayer: [signer, writable],
+        nonce: [writable],
+        instructions: [],
+        owner: [writable],
+        table: [writable, owned, table],
+        value: [writable, owned, value],
+        system_program: [system_program],
+        directory: [writable, owned, directory],
+        last_directory: [writable, owned, directory],
+        parent: [] if has_foreign_key(table)?,
+    }
+}
+
+context! {
+    /// Accounts of `CreateMultisig`.
+    pub struct CreateMultisigContext {
+        creator: [signer, writable],
+        multisig: [writable, uninitialized],
+        system_program: [system_program],
+    }
+}
+
+context! {
+    /// Accounts of `SetMultisigSigners`. The multisig only counts as signing
+    /// when its current signers approve the instruction.
+    pub struct MultisigContext {
+        multisig: [signer, writable, owned, multisig],
+    }
+}
+
+context! {
+    /// Accounts of `Delegate`.
+    pub struct DelegateContext {
+        owner: [signer, writable],
+        table: [owned, table],
+        delegation: [writable, uninitialized],
+        system_program: [system_program],
+    }
+}
+
+context! {
+    /// Accounts of `Revoke`.
+    pub struct RevokeContext {
+        owner: [signer, writable],
+        table: [owned, table],
+        delegation: [writable, owned, delegation],
+    }
+}
+
+context! {
+    /// Accounts of an `Insert` made by a delegate, a principal holding a role
+    /// or a token holder, whose grant is its delegation, its ACL entry or its
+    /// token account. The directory page is created by the processor when it
+    /// does not exist yet.
+    pub struct DelegatedInsertContext {
+        delegate: [signer, writable],
+        grant: [],
+        owner: [],
+        table: [writable, owned, table],
+        value: [writable, uninitialized],
+        system_program: [system_program],
+        directory: [writable],
+        bloom: [writable, owned, bloom] if has_bloom(table)?,
+        parent: [] if has_foreign_key(table)?,
+    }
+}
+
+context! {
+    /// Accounts of a `Put` made by a delegate or a principal.
+    pub struct DelegatedValueContext {
+        delegate: [signer, writable],
+        grant: [],
+        owner: [],
+        table: [writable, owned, table],
+        value: [writable, owned, value],
+        system_program: [system_program],
+        directory: [writable] if is_dedicated(table)?,
+        parent: [] if has_foreign_key(table)?,
+    }
+}
+
+context! {
+    /// Accounts of a `Delete` made by a delegate or a principal. The owner
+    /// receives the lamports of the closed accounts.
+    pub struct DelegatedDeleteContext {
+        delegate: [signer, writable],
+        grant: [],
+        owner: [writable],
+        table: [writable, owned, table],
+        value: [writable, owned, value],
+        system_program: [system_program],
+        directory: [writable, owned, directory],
+        last_directory: [writable, owned, directory],
+        parent: [] if has_foreign_key(table)?,
+    }
+}
+
+context! {
+    /// Accounts of `GrantRoles`, followed by the ACL entry of the authority
+    /// when it is an admin rather than the owner.
+    pub struct GrantRolesContext {
+        authority: [signer, writable],
+        owner: [],
+        table: [owned, table],
+        acl: [writable],
+        system_program: [system_program],
+    }
+}
+
+context! {
+    /// Accounts of `RevokeRoles`, followed by the ACL entry of the authority
+    /// when it is an admin rather than the owner.
+    pub struct RevokeRolesContext {
+        authority: [signer],
+        owner: [writable],
+        table: [owned, table],
+        acl: [writable, owned, acl],
+    }
+}
+
+context! {
+    /// Accounts of `SetValueAuthority`. The value authority PDA is created by
+    /// the processor when the value has no authority yet.
+    pub struct SetValueAuthorityContext {
+        authority: [signer, writable],
+        owner: [writable],
+        table: [owned, table],
+        value: [writable, owned, value],
+        record: [writable],
+        system_program: [system_program],
+    }
+}
+
+context! {
+    /// Accounts of `WriteChunk` and `CommitUpload`. Only `CommitUpload` takes
+    /// the directory page and the parent value.
+    pub struct UploadContext {
+        owner: [signer, writable],
+        table: [owned, table],
+        value: [writable, owned, value],
+        buffer: [writable, owned, upload],
+        system_program: [system_program],
+        directory: [writable] if is_dedicated(table)?,
+        parent: [] if has_foreign_key(table)?,
+    }
+}
+
+impl InsertContext<'_, '_> {
+    /// Loads the table, and whether the signer created it.
+    ///
+    /// Other signers may insert into a dedicated table under keys of their
+    /// own, as the value PDA is derived from the signer. Those values are not
+    /// the table's: its directory, Merkle root, key count and Bloom filter
+    /// only cover the values of the owner. A global table has a single key
+    /// space, which other signers only write through a grant.
+    pub fn check_table(&self) -> Result<(SolTable, bool)> {
+        let table = SolTable::load(&self.table.data.borrow())?;
+        let creator = is_creator(self.owner, self.table)?;
+        require!(
+            creator || table.mode != TableMode::Global,
+            SolDbError::MissingRole
+        );
+        Ok((table, creator))
+    }
+}
+
+impl SlabContext<'_, '_> {
+    /// Checks that the table was created by the signer.
+    pub fn check_table(&self, program_id: &Pubkey) -> Result<SolTable> {
+        check_stored_table(program_id, self.owner, self.table)
+    }
+
+    pub fn check_pdas(&self, program_id: &Pubkey, table: &str, table_bump: u8) -> ProgramResult {
+        check_table_pda(program_id, self.owner, self.table, table, table_bump)
+    }
+
+    /// Checks that the slab exists and is the one `key` hashes to.
+    pub fn check_slab(&self, program_id: &Pubkey, key: &[u8], slabs: u8) -> ProgramResult {
+        constraints::owned(program_id, self.slab)?;
+        check_slab(program_id, self.table, self.slab, key, slabs)
+    }
+}
+
+impl MigrateContext<'_, '_> {
+    #[allow(clippy::too_many_arguments)]
+    pub fn check_pdas(
+        &self,
+        program_id: &Pubkey,
+        table: &str,
+        table_bump: u8,
+        key: &[u8],
+        key_bump: u8,
+        slabs: u8,
+    ) -> ProgramResult {
+        check_value_pdas(
+            program_id, self.owner, self.table, self.value, table, table_bump, key, key_bump,
+        )?;
+        check_slab(program_id, self.table, self.slab, key, slabs)
+    }
+}
+
+impl DeleteMigratedContext<'_, '_> {
+    #[allow(clippy::too_many_arguments)]
+    pub fn check_pdas(
+        &self,
+        program_id: &Pubkey,
+        table: &str,
+        table_bump: u8,
+        key: &[u8],
+        key_bump: u8,
+        slabs: u8,
+    ) -> ProgramResult {
+        check_value_pdas(
+            program_id, self.owner, self.table, self.value, table, table_bump, key, key_bump,
+        )?;
+        check_slab(program_id, self.table, self.slab, key, slabs)
+    }
+}
+
+impl ValueContext<'_, '_> {
+    pub fn check_pdas(
+        &self,
+        program_id: &Pubkey,
+        table: &str,
+        table_bump: u8,
+        key: &[u8],
+        key_bump: u8,
+    ) -> ProgramResult {
+        check_value_pdas(
+            program_id, self.owner, self.table, self.value, table, table_bump, key, key_bump,
+        )
+    }
+}
+
+impl DeleteContext<'_, '_> {
+    pub fn check_pdas(
+        &self,
+        program_id: &Pubkey,
+        table: &str,
+        table_bump: u8,
+        key: &[u8],
+        key_bump: u8,
+    ) -> ProgramResult {
+        check_value_pdas(
+            program_id, self.owner, self.table, self.value, table, table_bump, key, key_bump,
+        )
+    }
+}
+
+impl UpsertContext<'_, '_> {
+    pub fn check_pdas(
+        &self,
+        program_id: &Pubkey,
+        table: &str,
+        table_bump: u8,
+        key: &[u8],
+        key_bump: u8,
+    ) -> ProgramResult {
+        check_value_pdas(
+            program_id, self.owner, self.table, self.value, table, table_bump, key, key_bump,
+        )
+    }
+}
+
+impl OpenUploadContext<'_, '_> {
+    pub fn check_pdas(
+        &self,
+        program_id: &Pubkey,
+        table: &str,
+        table_bump: u8,
+        key: &[u8],
+        key_bump: u8,
+    ) -> ProgramResult {
+        check_value_pdas(
+            program_id, self.owner, self.table, self.value, table, table_bump, key, key_bump,
+        )
+    }
+}
+
+impl CompressedContext<'_, '_> {
+    /// Checks that the table is the compressed table `table` of the signer
+    /// and that the tree is its own, returning the tree depth.
+    pub fn check_pdas(&self, program_id: &Pubkey, table: &str, table_bump: u8) -> Result<u8> {
+        check_table_pda(program_id, self.owner, self.table, table, table_bump)?;
+        let TableMode::Compressed { depth } = SolTable::load(&self.table.data.borrow())?.mode
+        else {
+            return Err(SolDbError::WrongTableMode.into());
+        };
+
+        let tree = SolTree::load(&self.tree.data.borrow())?;
+        let expected_tree_pda = create_tree_address(program_id, self.table.key, tree.bump)
+            .map_err(|_| SolDbError::PdaMismatch)?;
+        require!(
+            self.tree.key == &expected_tree_pda && tree.depth == depth,
+            SolDbError::PdaMismatch
+        );
+
+        Ok(depth)
+    }
+}
+
+impl InitBloomContext<'_, '_> {
+    pub fn check_pdas(&self, program_id: &Pubkey, table: &str, table_bump: u8) -> ProgramResult {
+        check_table_pda(program_id, self.owner, self.table, table, table_bump)
+    }
+}
+
+impl RebuildBloomContext<'_, '_> {
+    pub fn check_pdas(&self, program_id: &Pubkey, table: &str, table_bump: u8) -> ProgramResult {
+        check_table_pda(program_id, self.owner, self.table, table, table_bump)?;
+        check_bloom(program_id, self.table, self.bloom)?;
+        Ok(())
+    }
+}
+
+impl ForeignKeyContext<'_, '_> {
+    /// Checks the table PDA, and that the parent is the table `foreign_key`
+    /// points at, returning it.
+    pub fn check_pdas(
+        &self,
+        program_id: &Pubkey,
+        table: &str,
+        table_bump: u8,
+        foreign_key: &ForeignKey,
+    ) -> Result<SolTable> {
+        check_table_pda(program_id, self.owner, self.table, table, table_bump)?;
+
+        let parent = SolTable::load(&self.parent.data.borrow())?;
+        let expected_parent_pda = create_table_address(
+            program_id,
+            &foreign_key.parent_owner,
+            &parent.name,
+            parent.bump,
+        )
+        .map_err(|_| SolDbError::PdaMismatch)?;
+        require!(
+            self.parent.key == &foreign_key.parent && self.parent.key == &expected_parent_pda,
+            SolDbError::PdaMismatch
+        );
+
+        Ok(parent)
+    }
+}
+
+impl HookContext<'_, '_> {
+    pub fn check_pdas(&self, program_id: &Pubkey, table: &str, table_bump: u8) -> ProgramResult {
+        check_table_pda(program_id, self.owner, self.table, table, table_bump)
+    }
+}
+
+impl TokenGateContext<'_, '_> {
+    pub fn check_pdas(&self, program_id: &Pubkey, table: &str, table_bump: u8) -> ProgramResult {
+        check_table_pda(program_id, self.owner, self.table, table, table_bump)
+    }
+}
+
+impl ModerateContext<'_, '_> {
+    /// Checks that the value is the one `writer` inserted under `key`, which
+    /// need not be the owner of the table.
+    #[allow(clippy::too_many_arguments)]
+    pub fn check_pdas(
+        &self,
+        program_id: &Pubkey,
+        table: &str,
+        table_bump: u8,
+        writer: &Pubkey,
+        key: &[u8],
+        key_bump: u8,
+    ) -> ProgramResult {
+        require!(key.len() <= MAX_KEY_LEN, SolDbError::KeyTooLong);
+        check_table_pda(program_id, self.owner, self.table, table, table_bump)?;
+        check_value_pda(program_id, self.table, self.value, writer, key, key_bump)
+    }
+}
+
+impl ModerationDestinationContext<'_, '_> {
+    pub fn check_pdas(&self, program_id: &Pubkey, table: &str, table_bump: u8) -> ProgramResult {
+        check_table_pda(program_id, self.owner, self.table, table, table_bump)
+    }
+}
+
+impl RelayedInsertContext<'_, '_> {
+    /// Checks that the table is the table `table` of the owner who signed
+    /// the write. The value PDA is checked when it is created.
+    pub fn check_pdas(
+        &self,
+        program_id: &Pubkey,
+        table: &str,
+        table_bump: u8,
+        key: &[u8],
+    ) -> ProgramResult {
+        require!(key.len() <= MAX_KEY_LEN, SolDbError::KeyTooLong);
+        check_table_pda(program_id, self.owner, self.table, table, table_bump)
+    }
+}
+
+impl RelayedValueContext<'_, '_> {
+    pub fn check_pdas(
+        &self,
+        program_id: &Pubkey,
+        table: &str,
+        table_bump: u8,
+        key: &[u8],
+        key_bump: u8,
+    ) -> ProgramResult {
+        check_value_pdas(
+            program_id, self.owner, self.table, self.value, table, table_bump, key, key_bump,
+        )
+    }
+}
+
+impl RelayedDeleteContext<'_, '_> {
+    pub fn check_pdas(
+        &self,
+        program_id: &Pubkey,
+        table: &str,
+        table_bump: u8,
+        key: &[u8],
+        key_bump: u8,
+    ) -> ProgramResult {
+        check_value_pdas(
+            program_id, self.owner, self.table, self.value, table, table_bump, key, key_bump,
+        )
+    }
+}
+
+impl DelegateContext<'_, '_> {
+    pub fn check_pdas(&self, program_id: &Pubkey, table: &str, table_bump: u8) -> ProgramResult {
+        check_table_pda(program_id, self.owner, self.table, table, table_bump)
+    }
+}
+
+impl RevokeContext<'_, '_> {
+    pub fn check_pdas(&self, program_id: &Pubkey, table: &str, table_bump: u8) -> ProgramResult {
+        check_table_pda(program_id, self.owner, self.table, table, table_bump)
+    }
+}
+
+impl GrantRolesContext<'_, '_> {
+    pub fn check_pdas(&self, program_id: &Pubkey, table: &str, table_bump: u8) -> ProgramResult {
+        check_table_pda(program_id, self.owner, self.table, table, table_bump)
+    }
+}
+
+impl RevokeRolesContext<'_, '_> {
+    pub fn check_pdas(&self, program_id: &Pubkey, table: &str, table_bump: u8) -> ProgramResult {
+        check_table_pda(program_id, self.owner, self.table, table, table_bump)
+    }
+}
+
+impl DelegatedInsertContext<'_, '_> {
+    /// Checks that the table was created by the owner, as the value PDA is
+    /// derived from both.
+    pub fn check_table(&self, program_id: &Pubkey) -> Result<SolTable> {
+        check_stored_table(program_id, self.owner, self.table)
+    }
+}
+
+impl DelegatedValueContext<'_, '_> {
+    pub fn check_pdas(
+        &self,
+        program_id: &Pubkey,
+        table: &str,
+        table_bump: u8,
+        key: &[u8],
+        key_bump: u8,
+    ) -> ProgramResult {
+        check_value_pdas(
+            program_id, self.owner, self.table, self.value, table, table_bump, key, key_bump,
+        )
+    }
+}
+
+impl DelegatedDeleteContext<'_, '_> {
+    pub fn check_pdas(
+        &self,
+        program_id: &Pubkey,
+        table: &str,
+        table_bump: u8,
+        key: &[u8],
+        key_bump: u8,
+    ) -> ProgramResult {
+        check_value_pdas(
+            program_id, self.owner, self.table, self.value, table, table_bump, key, key_bump,
+        )
+    }
+}
+
+impl SetValueAuthorityContext<'_, '_> {
+    pub fn check_pdas(
+        &self,
+        program_id: &Pubkey,
+        table: &str,
+        table_bump: u8,
+        key: &[u8],
+        key_bump: u8,
+    ) -> ProgramResult {
+        check_value_pdas(
+            program_id, self.owner, self.table, self.value, table, table_bump, key, key_bump,
+        )
+    }
+}
+
+impl UploadContext<'_, '_> {
+    pub fn check_pdas(
+        &self,
+        program_id: &Pubkey,
+        table: &str,
+        table_bump: u8,
+        key: &[u8],
+        key_bump: u8,
+    ) -> ProgramResult {
+        check_value_pdas(
+            program_id, self.owner, self.table, self.value, table, table_bump, key, key_bump,
+        )
+    }
+}
+
+/// Checks that `table_info` is the table PDA of `owner_info` and that
+/// `val_info` is the value PDA of `key` under that table, in the namespace
+/// of the owner unless the table is global.
+///
+/// Both accounts must already be owned by the program, and the program only
+/// creates them at their canonical bump, so the supplied bumps are enough to
+/// recompute the addresses without searching for them.
+#[allow(clippy::too_many_arguments)]
+fn check_value_pdas(
+    program_id: &Pubkey,
+    owner_info: &AccountInfo,
+    table_info: &AccountInfo,
+    val_info: &AccountInfo,
+    table: &str,
+    table_bump: u8,
+    key: &[u8],
+    key_bump: u8,
+) -> ProgramResult {
+    require!(key.len() <= MAX_KEY_LEN, SolDbError::KeyTooLong);
+    check_table_pda(program_id, owner_info, table_info, table, table_bump)?;
+    check_value_pda(
+        program_id,
+        table_info,
+        val_info,
+        owner_info.key,
+        key,
+        key_bump,
+    )
+}
+
+/// Checks that `val_info` is the value PDA of `key` in the table, written by
+/// `writer` unless the table is global.
+fn check_value_pda(
+    program_id: &Pubkey,
+    table_info: &AccountInfo,
+    val_info: &AccountInfo,
+    writer: &Pubkey,
+    key: &[u8],
+    key_bump: u8,
+) -> ProgramResult {
+    let table = SolTable::load(&table_info.data.borrow())?;
+    let expected_val_pda = if table.mode == TableMode::Global {
+        create_global_value_address(program_id, table_info.key, key, key_bump)
+    } else {
+        create_value_address(program_id, table_info.key, writer, key, key_bump)
+    }
+    .map_err(|_| SolDbError::PdaMismatch)?;
+    require!(val_info.key == &expected_val_pda, SolDbError::PdaMismatch);
+
+    Ok(())
+}
+
+/// Whether the table has a Bloom filter, taken by the writes that may create
+/// a key.
+fn has_bloom(table_info: &AccountInfo) -> Result<bool> {
+    Ok(SolTable::load(&table_info.data.borrow())?.bloom)
+}
+
+/// Whether the table declares a foreign key, whose parent value follows the
+/// other optional accounts of a write.
+fn has_foreign_key(table_info: &AccountInfo) -> Result<bool> {
+    Ok(SolTable::load(&table_info.data.borrow())?
+        .foreign_key
+        .is_some())
+}
+
+/// Whether `owner_info` created the table, whose PDA is derived from its
+/// creator. The table is owned by the program, whose id is then its owner.
+fn is_creator(owner_info: &AccountInfo, table_info: &AccountInfo) -> Result<bool> {
+    let table = SolTable::load(&table_info.data.borrow())?;
+    Ok(
+        create_table_address(table_info.owner, owner_info.key, &table.name, table.bump)
+            .is_ok_and(|pda| &pda == table_info.key),
+    )
+}
+
+/// Whether the writes to the table commit the Merkle leaf of the key to the
+/// directory page listing it.
+fn is_dedicated(table_info: &AccountInfo) -> Result<bool> {
+    Ok(SolTable::load(&table_info.data.borrow())?
+        .mode
+        .is_dedicated())
+}
+
+/// Checks that `table_info` is the table PDA `table` of `owner_info`.
+fn check_table_pda(
+    program_id: &Pubkey,
+    owner_info: &AccountInfo,
+    table_info: &AccountInfo,
+    table: &str,
+    table_bump: u8,
+) -> ProgramResult {
+    require!(table.len() <= MAX_NAME_LEN, SolDbError::NameTooLong);
+
+    let expected_table_pda = create_table_address(program_id, owner_info.key, table, table_bump)
+        .map_err(|_| SolDbError::PdaMismatch)?;
+    require!(
+        table_info.key == &expected_table_pda,
+        SolDbError::PdaMismatch
+    );
+
+    Ok(())
+}
+
+/// Checks `table_info` against the name and bump it stores, for instructions
+/// that do not carry them.
+fn check_stored_table(
+    program_id: &Pubkey,
+    owner_info: &AccountInfo,
+    table_info: &AccountInfo,
+) -> Result<SolTable> {
+    let table = SolTable::load(&table_info.data.borrow())?;
+
+    let expected_table_pda =
+        create_table_address(program_id, owner_info.key, &table.name, table.bump)
+            .map_err(|_| SolDbError::PdaMismatch)?;
+    require!(
+        table_info.key == &expected_table_pda,
+        SolDbError::PdaMismatch
+    );
+
+    Ok(table)
+}
+
+/// Checks that `slab_info` is the slab of `table_info` that `key` hashes to.
+/// The slab stores its bump, so its address is recomputed without a search.
+fn check_slab(
+    program_id: &Pubkey,
+    table_info: &AccountInfo,
+    slab_info: &AccountInfo,
+    key: &[u8],
+    slabs: u8,
+) -> ProgramResult {
+    let slab = SolSlab::load(&slab_info.data.borrow())?;
+    require!(
+        slab.index == SolSlab::index_of(key, slabs),
+        SolDbError::PdaMismatch
+    );
+
+    let expected_slab_pda = create_slab_address(program_id, table_info.key, slab.index, slab.bump)
+        .map_err(|_| SolDbError::PdaMismatch)?;
+    require!(slab_info.key == &expected_slab_pda, SolDbError::PdaMismatch);
+
+    Ok(())
+}
+
+/// Checks that `dir_info` is a directory page of `table_info` and returns its
+/// header. The page stores its index and bump, so its address is recomputed
+/// without a search.
+pub fn check_directory(
+    program_id: &Pubkey,
+    table_info: &AccountInfo,
+    dir_info: &AccountInfo,
+) -> Result<SolDirectory> {
+    constraints::owned(program_id, dir_info)?;
+    let directory = SolDirectory::load(&dir_info.data.borrow())?;
+
+    let expected_dir_pda =
+        create_directory_address(program_id, table_info.key, directory.page, directory.bump)
+            .map_err(|_| SolDbError::PdaMismatch)?;
+    require!(dir_info.key == &expected_dir_pda, SolDbError::PdaMismatch);
+
+    Ok(directory)
+}
+
+/// Checks that `slab_info` is a slab of `table_info`, whichever keys it holds,
+/// and returns its header.
+pub fn check_slab_account(
+    program_id: &Pubkey,
+    table_info: &AccountInfo,
+    slab_info: &AccountInfo,
+) -> Result<SolSlab> {
+    constraints::owned(program_id, slab_info)?;
+    let slab = SolSlab::load(&slab_info.data.borrow())?;
+
+    let expected_slab_pda = create_slab_address(program_id, table_info.key, slab.index, slab.bump)
+        .map_err(|_| SolDbError::PdaMismatch)?;
+    require!(slab_info.key == &expected_slab_pda, SolDbError::PdaMismatch);
+
+    Ok(slab)
+}
+
+/// Checks that `bloom_info` is the Bloom filter of `table_info` and returns
+/// its header.
+pub fn check_bloom(
+    program_id: &Pubkey,
+    table_info: &AccountInfo,
+    bloom_info: &AccountInfo,
+) -> Result<SolBloom> {
+    constraints::owned(program_id, bloom_info)?;
+    let bloom = SolBloom::load(&bloom_info.data.borrow())?;
+
+    let expected_bloom_pda = create_bloom_address(program_id, table_info.key, bloom.bump)
+        .map_err(|_| SolDbError::PdaMismatch)?;
+    require!(
+        bloom_info.key == &expected_bloom_pda,
+        SolDbError::PdaMismatch
+    );
+
+    Ok(bloom)
+}
+
+/// Checks that `parent_info` is the existing value of the key `reference` in
+/// the parent table of `foreign_key` and returns its header.
+pub fn check_parent(
+    program_id: &Pubkey,
+    foreign_key: &ForeignKey,
+    reference: &[u8],
+    parent_info: &AccountInfo,
+) -> Result<SolValue> {
+    require!(parent_info.owner == program_id, SolDbError::ParentNotFound);
+    let parent =
+        SolValue::load(&parent_info.data.borrow()).map_err(|_| SolDbError::ParentNotFound)?;
+
+    let expected_parent_pda = create_value_address(
+        program_id,
+        &foreign_key.parent,
+        &foreign_key.parent_owner,
+        reference,
+        parent.bump,
+    )
+    .map_err(|_| SolDbError::PdaMismatch)?;
+    require!(
+        parent_info.key == &expected_parent_pda,
+        SolDbError::PdaMismatch
+    );
+
+    Ok(parent)
+}
+
+/// Checks that `nonce_info` is the relay nonce PDA of `signer_info` and
+/// returns it. The account stores its bump, so its address is recomputed
+/// without a search.
+pub fn check_nonce(
+    program_id: &Pubkey,
+    signer_info: &AccountInfo,
+    nonce_info: &AccountInfo,
+) -> Result<SolNonce> {
+    constraints::owned(program_id, nonce_info)?;
+    let nonce = SolNonce::load(&nonce_info.data.borrow())?;
+
+    let expected_nonce_pda = create_nonce_address(program_id, signer_info.key, nonce.bump)
+        .map_err(|_| SolDbError::PdaMismatch)?;
+    require!(
+        nonce_info.key == &expected_nonce_pda,
+        SolDbError::PdaMismatch
+    );
+
+    Ok(nonce)
+}
+
+/// Checks that `delegation_info` is the delegation PDA of `delegate` on
+/// `table_info` and returns it. The account stores its bump, so its address
+/// is recomputed without a search.
+pub fn check_delegation(
+    program_id: &Pubkey,
+    table_info: &AccountInfo,
+    delegate: &Pubkey,
+    delegation_info: &AccountInfo,
+) -> Result<SolDelegation> {
+    constraints::owned(program_id, delegation_info)?;
+    let delegation = SolDelegation::load(&delegation_info.data.borrow())?;
+
+    let expected_delegation_pda =
+        create_delegation_address(program_id, table_info.key, delegate, delegation.bump)
+            .map_err(|_| SolDbError::PdaMismatch)?;
+    require!(
+        delegation_info.key == &expected_delegation_pda,
+        SolDbError::PdaMismatch
+    );
+
+    Ok(delegation)
+}
+
+/// Checks that `acl_info` is the ACL entry PDA of `principal` on
+/// `table_info` and returns it. The account stores its bump, so its address
+/// is recomputed without a search.
+pub fn check_acl(
+    program_id: &Pubkey,
+    table_info: &AccountInfo,
+    principal: &Pubkey,
+    acl_info: &AccountInfo,
+) -> Result<SolAcl> {
+    constraints::owned(program_id, acl_info)?;
+    let acl = SolAcl::load(&acl_info.data.borrow())?;
+
+    let expected_acl_pda = create_acl_address(program_id, table_info.key, principal, acl.bump)
+        .map_err(|_| SolDbError::PdaMismatch)?;
+    require!(acl_info.key == &expected_acl_pda, SolDbError::PdaMismatch);
+
+    Ok(acl)
+}
+
+/// Checks that `record_info` is the value authority PDA of `val_info` and
+/// returns it. The account stores its bump, so its address is recomputed
+/// without a search.
+pub fn check_value_authority(
+    program_id: &Pubkey,
+    val_info: &AccountInfo,
+    record_info: &AccountInfo,
+) -> Result<SolValueAuthority> {
+    constraints::owned(program_id, record_info)?;
+    let record = SolValueAuthority::load(&record_info.data.borrow())?;
+
+    let expected_record_pda = create_value_authority_address(program_id, val_info.key, record.bump)
+        .map_err(|_| SolDbError::PdaMismatch)?;
+    require!(
+        record_info.key == &expected_record_pda,
+        SolDbError::PdaMismatch
+    );
+
+    Ok(record)
+}
+
+/// Checks that `token_info` is an SPL Token or Token-2022 account of `holder`
+/// holding enough of the mint gating `table_info`.
+pub fn check_token_gate(
+    table_info: &AccountInfo,
+    holder: &Pubkey,
+    token_info: &AccountInfo,
+) -> ProgramResult {
+    let account = generic_token::Account::unpack(&token_info.data.borrow(), token_info.owner)
+        .ok_or(SolDbError::NotTokenAccount)?;
+    let gate = SolTable::load(&table_info.data.borrow())?
+        .token_gate
+        .ok_or(SolDbError::TokenGateFailed)?;
+
+    require!(
+        account.mint == gate.mint && &account.owner == holder && account.amount >= gate.min_amount,
+        SolDbError::TokenGateFailed
+    );
+
+    Ok(())
+}
@@ -1,6 +1,10 @@
 use solana_program::program_error::ProgramError;
 use solana_program_error::ToStr;
 
+/// Custom errors returned by the program as `ProgramError::Custom(code)`.
+///
+/// The discriminants are part of the public interface: clients decode them
+/// back from `InstructionError::Custom`, so existing codes must never change.
 #[repr(u32)]
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum SolDbError {
@@ -9,6 +13,68 @@ pub enum SolDbError {
     NotTable = 2,
     GrowthTooLarge = 3,
     WrongError = 4,
+    KeyTooLong = 5,
+    NameTooLong = 6,
+    MissingSigner = 7,
+    TableNotEmpty = 8,
+    QuotaExceeded = 9,
+    AccountAlreadyExists = 10,
+    OffsetOutOfBounds = 11,
+    ValueTooLarge = 12,
+    DigestMismatch = 13,
+    NotValue = 14,
+    NotUpload = 15,
+    NotWritable = 16,
+    NotSystemProgram = 17,
+    NotSlab = 18,
+    WrongTableMode = 19,
+    EntryTooLarge = 20,
+    KeyNotFound = 21,
+    SlabFull = 22,
+    EntryMigrated = 23,
+    NotDirectory = 24,
+    InvalidProof = 25,
+    NotTree = 26,
+    NotBloom = 27,
+    InvalidBloom = 28,
+    InvalidForeignKey = 29,
+    ForeignKeyMissing = 30,
+    ParentNotFound = 31,
+    ForeignKeyChanged = 32,
+    ValueReferenced = 33,
+    HookMissing = 34,
+    InvalidHook = 35,
+    NotNonce = 36,
+    InvalidSignature = 37,
+    NonceMismatch = 38,
+    SignatureExpired = 39,
+    NotMultisig = 40,
+    InvalidMultisig = 41,
+    NotDelegation = 42,
+    DelegationExpired = 43,
+    KeyOutOfScope = 44,
+    NotAcl = 45,
+    MissingRole = 46,
+    InvalidRoles = 47,
+    NotValueAuthority = 48,
+    ValueClaimed = 49,
+    ReservedKey = 50,
+    InvalidPayer = 51,
+    NotTokenAccount = 52,
+    TokenGateFailed = 53,
+    InvalidDestination = 54,
+}
+
+impl SolDbError {
+    /// Stable numeric code of the error.
+    pub fn code(self) -> u32 {
+        self as u32
+    }
+
+    /// Human readable message of the error.
+    pub fn message(self) -> &'static str {
+        self.to_str::<Self>()
+    }
 }
 
 impl From<SolDbError> for ProgramError {
@@ -18,14 +84,65 @@ impl From<SolDbError> for ProgramError {
 }
 
 impl TryFrom<u32> for SolDbError {
-    type Error = Self;
+    type Error = u32;
     fn try_from(value: u32) -> std::result::Result<Self, Self::Error> {
         Ok(match value {
             0 => Self::WrongOwner,
             1 => Self::PdaMismatch,
             2 => Self::NotTable,
             3 => Self::GrowthTooLarge,
-            _ => Self::WrongError,
+            4 => Self::WrongError,
+            5 => Self::KeyTooLong,
+            6 => Self::NameTooLong,
+            7 => Self::MissingSigner,
+            8 => Self::TableNotEmpty,
+            9 => Self::QuotaExceeded,
+            10 => Self::AccountAlreadyExists,
+            11 => Self::OffsetOutOfBounds,
+            12 => Self::ValueTooLarge,
+            13 => Self::DigestMismatch,
+            14 => Self::NotValue,
+            15 => Self::NotUpload,
+            16 => Self::NotWritable,
+            17 => Self::NotSystemProgram,
+            18 => Self::NotSlab,
+            19 => Self::WrongTableMode,
+            20 => Self::EntryTooLarge,
+            21 => Self::KeyNotFound,
+            22 => Self::SlabFull,
+            23 => Self::EntryMigrated,
+            24 => Self::NotDirectory,
+            25 => Self::InvalidProof,
+            26 => Self::NotTree,
+            27 => Self::NotBloom,
+            28 => Self::InvalidBloom,
+            29 => Self::InvalidForeignKey,
+            30 => Self::ForeignKeyMissing,
+            31 => Self::ParentNotFound,
+            32 => Self::ForeignKeyChanged,
+            33 => Self::ValueReferenced,
+            34 => Self::HookMissing,
+            35 => Self::InvalidHook,
+            36 => Self::NotNonce,
+            37 => Self::InvalidSignature,
+            38 => Self::NonceMismatch,
+            39 => Self::SignatureExpired,
+            40 => Self::NotMultisig,
+            41 => Self::InvalidMultisig,
+            42 => Self::NotDelegation,
+            43 => Self::DelegationExpired,
+            44 => Self::KeyOutOfScope,
+            45 => Self::NotAcl,
+            46 => Self::MissingRole,
+            47 => Self::InvalidRoles,
+            48 => Self::NotValueAuthority,
+            49 => Self::ValueClaimed,
+            50 => Self::ReservedKey,
+            51 => Self::InvalidPayer,
+            52 => Self::NotTokenAccount,
+            53 => Self::TokenGateFailed,
+            54 => Self::InvalidDestination,
+            _ => return Err(value),
         })
     }
 }
@@ -43,8 +160,74 @@ impl ToStr for SolDbError {
                 "Error: The growth of the account has exceeded the maximum of 10KB"
             }
             Self::WrongError => "Error: Wrong error value",
+            Self::KeyTooLong => "Error: Key exceeds the maximum seed length of 32 bytes",
+            Self::NameTooLong => "Error: Table name exceeds the maximum seed length of 32 bytes",
+            Self::MissingSigner => "Error: A required signature is missing",
+            Self::TableNotEmpty => "Error: The table still contains values",
+            Self::QuotaExceeded => "Error: The allowed quota has been exceeded",
+            Self::AccountAlreadyExists => "Error: Account already exists",
+            Self::OffsetOutOfBounds => "Error: Offset is outside of the stored value",
+            Self::ValueTooLarge => "Error: Value exceeds the maximum account size of 10MB",
+            Self::DigestMismatch => "Error: Uploaded data does not match the expected digest",
+            Self::NotValue => "Error: Not a SolValue Account",
+            Self::NotUpload => "Error: Not a SolUpload Account",
+            Self::NotWritable => "Error: A required account is not writable",
+            Self::NotSystemProgram => "Error: Expected the system program",
+            Self::NotSlab => "Error: Not a SolSlab Account",
+            Self::WrongTableMode => "Error: Instruction is not supported by the table mode",
+            Self::EntryTooLarge => "Error: Value does not fit in a slab entry",
+            Self::KeyNotFound => "Error: Key does not exist",
+            Self::SlabFull => "Error: Slab has no free entry left",
+            Self::EntryMigrated => "Error: Entry was migrated to a dedicated account",
+            Self::NotDirectory => "Error: Not a SolDirectory Account",
+            Self::InvalidProof => "Error: Merkle proof does not match the table root",
+            Self::NotTree => "Error: Not a SolTree Account",
+            Self::NotBloom => "Error: Not a SolBloom Account",
+            Self::InvalidBloom => "Error: Bloom filter size or hash count is out of range",
+            Self::InvalidForeignKey => "Error: Foreign key range is invalid or already declared",
+            Self::ForeignKeyMissing => "Error: Payload is too short to hold its foreign key",
+            Self::ParentNotFound => "Error: Referenced parent value does not exist",
+            Self::ForeignKeyChanged => "Error: Foreign key of a value cannot change",
+            Self::ValueReferenced => "Error: Value is still referenced by a foreign key",
+            Self::HookMissing => "Error: Hook program of the table was not passed",
+            Self::InvalidHook => "Error: Hook must be an executable program other than soldb",
+            Self::NotNonce => "Error: Not a SolNonce Account",
+            Self::InvalidSignature => {
+                "Error: Relayed write is not signed by the owner in the previous instruction"
+            }
+            Self::NonceMismatch => "Error: Relayed write was not signed with the current nonce",
+            Self::SignatureExpired => "Error: Relayed write signature has expired",
+            Self::NotMultisig => "Error: Not a SolMultisig Account",
+            Self::InvalidMultisig => "Error: Multisig threshold or signers are invalid",
+            Self::NotDelegation => "Error: Not a SolDelegation Account",
+            Self::DelegationExpired => "Error: The delegation has expired",
+            Self::KeyOutOfScope => "Error: Key is outside the prefix of the delegation",
+            Self::NotAcl => "Error: Not a SolAcl Account",
+            Self::MissingRole => "Error: The signer lacks the role this instruction requires",
+            Self::InvalidRoles => "Error: Roles are empty or unknown",
+            Self::NotValueAuthority => "Error: Not a SolValueAuthority Account",
+            Self::ValueClaimed => "Error: Value is controlled by its value authority",
+            Self::ReservedKey => "Error: Key is reserved for another account of the table",
+            Self::InvalidPayer => {
+                "Error: Payer is neither a system account nor owned by this program"
+            }
+            Self::NotTokenAccount => "Error: Not an SPL Token or Token-2022 account",
+            Self::TokenGateFailed => {
+                "Error: Token account does not pass the token gate of the table"
+            }
+            Self::InvalidDestination => {
+                "Error: Account is not the moderation destination of the table"
+            }
         }
     }
 }
 
+impl std::fmt::Display for SolDbError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for SolDbError {}
+
 pub type Result<T> = std::result::Result<T, ProgramError>;
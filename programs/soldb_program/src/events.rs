@@ -0,0 +1,42 @@
+//! Events logged by the writes to compressed tables.
+//!
+//! Compressed tables keep their keys and values in these events only. Each
+//! one is logged with `sol_log_data` as two fields, `EVENT_TAG` and the Borsh
+//! encoding of the event, so that clients can rebuild the table by replaying
+//! the logs of its transactions in `sequence` order.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{entrypoint::ProgramResult, log::sol_log_data, pubkey::Pubkey};
+
+/// First field of every event logged by the program.
+pub const EVENT_TAG: &[u8] = b"soldb:compressed";
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Eq, PartialEq)]
+pub enum CompressedEvent {
+    /// `key` now holds `payload` at leaf `index` of the tree of `table`.
+    Put {
+        table: Pubkey,
+        /// Tree sequence number after the change.
+        sequence: u64,
+        index: u32,
+        key: Vec<u8>,
+        payload: Vec<u8>,
+    },
+    /// `key` was removed from leaf `index` of the tree of `table`.
+    Delete {
+        table: Pubkey,
+        sequence: u64,
+        index: u32,
+        key: Vec<u8>,
+    },
+}
+
+impl CompressedEvent {
+    /// Logs the event.
+    pub fn emit(&self) -> ProgramResult {
+        let mut data = Vec::new();
+        self.serialize(&mut data)?;
+        sol_log_data(&[EVENT_TAG, &data]);
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+//! Interface of the hook programs that tables register with `SetHook`.
+//!
+//! After every instruction creating, changing or deleting a key of a table
+//! with a hook, the program invokes the hook with `HookInstruction::Execute`
+//! describing the mutation. The instruction fails when the hook fails, so
+//! hooks can enforce rules on the payloads or record the writes elsewhere.
+//!
+//! The hook receives the following accounts, none of them writable or
+//! signing:
+//! 1. `[]` Table account.
+//! 2. `[]` Account holding the value: its PDA, or the slab of a packed table.
+//!    It is already closed after a `Delete` of a dedicated value.
+//! 3. `[]` Owner of the table.
+//! 4. Any accounts following the hook program in the soldb instruction, with
+//!    their privileges.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    program::invoke,
+    pubkey::Pubkey,
+};
+use solana_program_error::ProgramError;
+
+use crate::{accounts::SolTable, error::SolDbError};
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MutationKind {
+    /// The key was created, by `Insert`, `Upsert` or `OpenUpload`.
+    Insert,
+    /// The value of the key changed, by `Put`, `Patch`, `Append`,
+    /// `Truncate`, `Upsert` or `CommitUpload`.
+    Put,
+    /// The key was deleted.
+    Delete,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Eq, PartialEq)]
+pub struct Mutation {
+    pub kind: MutationKind,
+    pub key: Vec<u8>,
+}
+
+/// Instructions the program sends to hook programs.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Eq, PartialEq)]
+pub enum HookInstruction {
+    /// Reports a mutation applied by the current instruction.
+    Execute(Mutation),
+}
+
+/// Invokes the hook of the table of a mutating instruction, if it has one.
+///
+/// `accounts` are those of the instruction, starting with the owner, the
+/// table and the account holding the value. The hook program is looked up
+/// among them, and the accounts after it are forwarded to the hook.
+pub fn execute(accounts: &[AccountInfo], mutation: Mutation) -> ProgramResult {
+    let [owner_info, table_info, holder_info, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    let Some(hook) = SolTable::load(&table_info.data.borrow())?.hook else {
+        return Ok(());
+    };
+    let position = accounts
+        .iter()
+        .position(|info| info.key == &hook)
+        .ok_or(SolDbError::HookMissing)?;
+    let extra = &accounts[position + 1..];
+
+    let mut metas = vec![
+        AccountMeta::new_readonly(*table_info.key, false),
+        AccountMeta::new_readonly(*holder_info.key, false),
+        AccountMeta::new_readonly(*owner_info.key, false),
+    ];
+    metas.extend(extra.iter().map(|info| AccountMeta {
+        pubkey: *info.key,
+        is_signer: info.is_signer,
+        is_writable: info.is_writable,
+    }));
+    let ix = Instruction::new_with_borsh(hook, &HookInstruction::Execute(mutation), metas);
+
+    let mut infos = vec![table_info.clone(), holder_info.clone(), owner_info.clone()];
+    infos.extend(extra.iter().cloned());
+    infos.push(accounts[position].clone());
+    invoke(&ix, &infos)
+}
+
+/// Builds the `Execute` instruction a hook receives, for hook programs to
+/// test against.
+pub fn execute_instruction(
+    hook: &Pubkey,
+    table: &Pubkey,
+    holder: &Pubkey,
+    owner: &Pubkey,
+    mutation: Mutation,
+    extra: &[AccountMeta],
+) -> Instruction {
+    let mut metas = vec![
+        AccountMeta::new_readonly(*table, false),
+        AccountMeta::new_readonly(*holder, false),
+        AccountMeta::new_readonly(*owner, false),
+    ];
+    metas.extend_from_slice(extra);
+    Instruction::new_with_borsh(*hook, &HookInstruction::Execute(mutation), metas)
+}
@@ -1,41 +1,495 @@
-use crate::error::Result;
+use crate::{
+    accounts::{ForeignKey, SolDirectory, SolSlab, TableMode, TokenGate},
+    error::Result,
+    pda::{
+        find_acl_address, find_bloom_address, find_delegation_address, find_directory_address,
+        find_global_value_address, find_multisig_address, find_nonce_address, find_slab_address,
+        find_table_address, find_tree_address, find_upload_address, find_value_address,
+        find_value_authority_address,
+    },
+    relay::RelayedMessage,
+};
 use borsh::{BorshDeserialize, BorshSerialize};
-use solana_program::program_error::ProgramError;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    sysvar,
+};
+use solana_system_interface::program as system_program;
 
+/// Instructions supported by the program.
+///
+/// The wire format is also published as an IDL in `idl/soldb_program.json`,
+/// which `tests/idl_tests.rs` keeps in sync with these types.
 #[derive(BorshDeserialize, BorshSerialize, Debug)]
 pub enum SolDbIntructions {
     /// Initializes a new PDA account for a table.
     ///
+    /// Packed tables keep values of up to `MAX_ENTRY_LEN` bytes in slab
+    /// accounts instead of one PDA per key. `Insert`, `Put` and `Delete`
+    /// then take the key's slab in place of the value account and no
+    /// directory page, and larger values must be moved out with `Migrate`
+    /// first.
+    ///
+    /// Compressed tables only take `CompressedPut` and `CompressedDelete`,
+    /// and also create their Merkle tree account.
+    ///
+    /// Global tables store every value in its own PDA as dedicated tables
+    /// do, derived from `[key, table]` instead of `[key, table, owner]`.
+    /// Each key then exists once in the table, created by the first writer
+    /// allowed to insert it, and is found from the table and key alone. It
+    /// does not belong to that writer: any writer allowed to put, such as a
+    /// token holder, may overwrite it.
+    ///
+    /// The owner may be a PDA of another program, which then signs this and
+    /// later instructions through `invoke_signed`. It pays for accounts as a
+    /// wallet does, so it must be owned by the system program and hold the
+    /// lamports, and it cannot be the table's hook as programs are not
+    /// reentrant. A PDA holding data of its program fails with
+    /// `InvalidPayer`, since only that program may debit it.
+    ///
     /// Expects the following accounts:
-    /// 1. `[signer]` Owner of the table.
+    /// 1. `[writable, signer]` Owner of the table, pays for the account.
     /// 2. `[writable]` PDA to create for the table.
     /// 3. `[]` System program.
+    /// 4. `[writable]` Tree PDA to create, derived from `["tree", table]`,
+    ///    for compressed tables only.
     InitTable(InitTable),
 
     /// Inserts a new key-value pair under an existing table.
     ///
     /// The key-value data is stored in a dedicated PDA derived from
-    /// the table name and key bytes.
+    /// the table name and key bytes. The account is sized for at least
+    /// `capacity` payload bytes, so that later writes up to it are done in
+    /// place without moving lamports.
+    ///
+    /// The key is appended to the table's directory, whose pages let clients
+    /// enumerate the table without `getProgramAccounts`.
+    ///
+    /// A delegate of the owner, or a principal holding the inserter role, may
+    /// insert instead, as described for `Delegate` and `GrantRoles`, and so
+    /// may holders of the token gating the table, see `SetTokenGate`.
+    ///
+    /// Any other signer may insert into a dedicated table under keys of its
+    /// own, which the table does not list: they leave its directory, Merkle
+    /// root and Bloom filter as they are. Global tables refuse such inserts
+    /// with `MissingRole`.
     ///
     /// Expects the following accounts:
-    /// 1. `[]` Table account (must match PDA derived from table name and owner).
-    /// 2. `[writable]` Key-value PDA to be created.
-    /// 3. `[signer]` Payer for account creation.
+    /// 1. `[writable, signer]` Owner of the table, pays for account creation.
+    /// 2. `[writable]` Table account (must match PDA derived from table name and owner).
+    /// 3. `[writable]` Key-value PDA to be created.
     /// 4. `[]` System program.
+    /// 5. `[writable]` Last directory page, derived from `["dir", table, page]`
+    ///    with `page = keys / PAGE_KEYS`. Created when it does not exist yet.
+    ///
+    /// The writes to a dedicated table then take these optional accounts, in
+    /// this order, each only when the table has it:
+    /// 1. `[writable]` Bloom filter PDA derived from `["bloom", table]`. Only
+    ///    taken by the writes that may create a key.
+    /// 2. `[writable]` Parent value the payload points at, when the table
+    ///    declares a foreign key.
     Insert(Insert),
 
     /// Updates an existing key-value pair under a table.
     ///
+    /// Every instruction changing a value of a dedicated table also updates
+    /// the Merkle leaf of its key, and with it the table root checked by
+    /// `VerifyProof`.
+    ///
+    /// A delegate of the owner, or a principal holding the updater role, may
+    /// put instead, as described for `Delegate` and `GrantRoles`, and so may
+    /// holders of the token gating the table. A value with an authority is
+    /// only put by it, as described for `SetValueAuthority`.
+    ///
     /// Expects the following accounts:
-    /// 1. `[writable]` Key-value PDA to overwrite.
+    /// 1. `[writable, signer]` Owner of the table, pays for any growth.
+    /// 2. `[writable]` Table account.
+    /// 3. `[writable]` Key-value PDA to overwrite.
+    /// 4. `[]` System program.
+    /// 5. `[writable]` Directory page listing the key. Values migrated out of
+    ///    a packed table are not committed and take no page.
+    /// 6. The optional accounts of `Insert`, without the Bloom filter.
+    ///    `Patch`, `Append` and `Truncate` only take the parent value when the
+    ///    payload did not hold the key yet.
     Put(Put),
 
     /// Deletes a key-value pair by closing its PDA.
     ///
+    /// The key is removed from the directory by moving the last key of the
+    /// directory into its entry. The owner receives the lamports of the
+    /// closed accounts, including the last page once it is empty.
+    ///
+    /// A delegate of the owner, or a principal holding the deleter role, may
+    /// delete instead, as described for `Delegate` and `GrantRoles`.
+    ///
     /// Expects the following accounts:
-    /// 1. `[writable]` Key-value PDA to close.
-    /// 2. `[writable]` Recipient of lamports from the closed account.
+    /// 1. `[writable, signer]` Owner of the table.
+    /// 2. `[writable]` Table account.
+    /// 3. `[writable]` Key-value PDA to close.
+    /// 4. `[]` System program.
+    /// 5. `[writable]` Directory page listing the key.
+    /// 6. `[writable]` Last directory page, which may be the same account.
+    /// 7. `[writable]` Parent value the payload points at, when the table
+    ///    declares a restricting foreign key.
     Delete(Delete),
+
+    /// Overwrites bytes of an existing value starting at `offset`.
+    ///
+    /// The value is extended when the written range goes past its end, but
+    /// `offset` itself must lie within the current value.
+    ///
+    /// Expects the same accounts as `Put`.
+    Patch(Patch),
+
+    /// Appends bytes to the tail of an existing value.
+    ///
+    /// Expects the same accounts as `Put`.
+    Append(Append),
+
+    /// Shrinks an existing value to `len` bytes, refunding the freed rent.
+    ///
+    /// Expects the same accounts as `Put`.
+    Truncate(Truncate),
+
+    /// Allocates a staging buffer to upload a value larger than a single
+    /// transaction. The value PDA is created empty if it does not exist yet,
+    /// except in a table declaring a foreign key, whose values must first be
+    /// inserted with a payload holding the key of their parent.
+    ///
+    /// Expects the following accounts:
+    /// 1. `[writable, signer]` Owner of the table, pays for the buffer.
+    /// 2. `[writable]` Table account.
+    /// 3. `[writable]` Key-value PDA the upload is for.
+    /// 4. `[writable]` Staging buffer PDA derived from `["upload", value]`.
+    /// 5. `[]` System program.
+    /// 6. `[writable]` Last directory page, as in `Insert`. Only used when
+    ///    the value is created.
+    /// 7. The optional accounts of `Insert`, without the parent value.
+    OpenUpload(OpenUpload),
+
+    /// Writes a chunk of the upload at `offset`, growing the buffer and the
+    /// value account by at most 10 KB each.
+    ///
+    /// Expects the first five accounts of `OpenUpload`, with a read-only
+    /// table.
+    WriteChunk(WriteChunk),
+
+    /// Checks the upload digest and installs the buffer contents as the
+    /// value, closing the buffer.
+    ///
+    /// Expects the same accounts as `OpenUpload`, the directory page being
+    /// the one listing the key as in `Put`, followed by the optional accounts
+    /// of `Put`.
+    CommitUpload(CommitUpload),
+
+    /// Overwrites the value of a key like `Put`, or creates it like `Insert`
+    /// when it does not exist yet. `capacity` is only used on creation.
+    ///
+    /// Expects the same accounts as `Insert`, except that the directory page
+    /// is the one listing the key as in `Put` when the value already exists.
+    /// The Bloom filter is taken either way.
+    Upsert(Upsert),
+
+    /// Moves a slab entry of a packed table to its dedicated value PDA, so
+    /// that it can outgrow `MAX_ENTRY_LEN`. The slab keeps the key, marked as
+    /// migrated, and the value is then used like any dedicated value.
+    /// Deleting it takes the slab as a fifth account.
+    ///
+    /// Expects the following accounts:
+    /// 1. `[writable, signer]` Owner of the table, pays for the value.
+    /// 2. `[]` Table account.
+    /// 3. `[writable]` Slab holding the key.
+    /// 4. `[writable]` Key-value PDA to create.
+    /// 5. `[]` System program.
+    Migrate(Migrate),
+
+    /// Checks that `key` of `writer` holds a value hashing to `value_hash` in
+    /// a dedicated table, from the Merkle proof of its leaf at directory
+    /// position `index`. Fails with `InvalidProof` otherwise, so that other programs
+    /// can rely on it through CPI without trusting an RPC node.
+    ///
+    /// Expects the following accounts:
+    /// 1. `[]` Table account.
+    VerifyProof(VerifyProof),
+
+    /// Writes `payload` under `key` in a compressed table, replacing leaf
+    /// `index` of its tree. The leaf must be free, or hold the key with a
+    /// value hashing to `previous_hash`, as proven by `proof` against `root`.
+    /// `root` may be any recent root of the tree, so that writes built from
+    /// the same state do not conflict unless they touch the same leaf.
+    ///
+    /// The value is only stored in a `CompressedEvent` logged by the program.
+    ///
+    /// Expects the following accounts:
+    /// 1. `[signer]` Owner of the table.
+    /// 2. `[]` Table account.
+    /// 3. `[writable]` Tree PDA of the table.
+    CompressedPut(CompressedPut),
+
+    /// Frees leaf `index` of the tree of a compressed table, which must hold
+    /// `key` with a value hashing to `value_hash`.
+    ///
+    /// Expects the same accounts as `CompressedPut`.
+    CompressedDelete(CompressedDelete),
+
+    /// Creates an empty Bloom filter of `bits` bits for a dedicated or packed
+    /// table, setting `hashes` bits per key. From then on, every instruction
+    /// creating a key takes the filter as a trailing account and adds the
+    /// key to it. Keys inserted before are added with `RebuildBloom`.
+    ///
+    /// Expects the following accounts:
+    /// 1. `[writable, signer]` Owner of the table, pays for the filter.
+    /// 2. `[writable]` Table account.
+    /// 3. `[writable]` Filter PDA to create, derived from `["bloom", table]`.
+    /// 4. `[]` System program.
+    InitBloom(InitBloom),
+
+    /// Sets the return data to `[1]` when `key` may exist in the table, and
+    /// to `[0]` when it certainly does not. Tables without a filter always
+    /// answer `[1]`.
+    ///
+    /// Expects the following accounts:
+    /// 1. `[]` Table account.
+    /// 2. `[]` Filter PDA of the table.
+    MaybeContains(MaybeContains),
+
+    /// Adds the keys of the trailing directory pages, or slabs of a packed
+    /// table, to the filter, after clearing it when `reset` is set. Large
+    /// tables are rebuilt over several instructions, the first one
+    /// resetting the filter.
+    ///
+    /// Expects the following accounts:
+    /// 1. `[signer]` Owner of the table.
+    /// 2. `[]` Table account.
+    /// 3. `[writable]` Filter PDA of the table.
+    /// 4. `[]` Any number of directory pages or slabs of the table.
+    RebuildBloom(RebuildBloom),
+
+    /// Declares that a byte range of every payload of an empty dedicated
+    /// table is the key of a value in another dedicated table.
+    ///
+    /// `Insert`, `Upsert`, `Put` and `CommitUpload` then require the payload
+    /// to hold the key and take the parent value, which must exist. The key
+    /// of a value cannot change once written. With `restrict`, the parent
+    /// counts the values pointing at it and cannot be deleted before them.
+    ///
+    /// Expects the following accounts:
+    /// 1. `[writable, signer]` Owner of the table, pays for the growth.
+    /// 2. `[writable]` Table account.
+    /// 3. `[]` Parent table account.
+    /// 4. `[]` System program.
+    SetForeignKey(SetForeignKey),
+
+    /// Registers `hook` as the hook program of a dedicated or packed table,
+    /// or removes it when `None`.
+    ///
+    /// Every instruction creating, changing or deleting a key of the table
+    /// then invokes the hook as described in [`crate::hook`], and fails when
+    /// it fails. Such instructions take the hook program after their own
+    /// accounts, followed by the accounts the hook needs.
+    ///
+    /// Expects the following accounts:
+    /// 1. `[writable, signer]` Owner of the table, pays for the growth.
+    /// 2. `[writable]` Table account.
+    /// 3. `[]` System program.
+    /// 4. `[]` Hook program, when registering one.
+    SetHook(SetHook),
+
+    /// `Insert` submitted by a relayer on behalf of the owner, who signed the
+    /// matching [`crate::relay::RelayedMessage`] off-chain instead of the
+    /// transaction. The instruction right before it must be the ed25519
+    /// program instruction verifying that signature, and consumes the
+    /// owner's relay nonce. Only dedicated tables accept relayed writes.
+    ///
+    /// The relayer pays for the accounts created or grown by relayed writes
+    /// and receives the lamports of those they shrink, while the owner
+    /// receives those of the accounts they close.
+    ///
+    /// Expects the following accounts:
+    /// 1. `[writable, signer]` Relayer.
+    /// 2. `[writable]` Relay nonce PDA of the owner, derived from
+    ///    `["nonce", owner]`. Created when it does not exist yet.
+    /// 3. `[]` Instructions sysvar.
+    /// 4. `[]` Owner of the table, who signed the message.
+    /// 5. The accounts of `Insert` after the owner.
+    RelayedInsert(RelayedInsert),
+
+    /// `Put` submitted by a relayer, as described for `RelayedInsert`.
+    ///
+    /// Expects the accounts of `RelayedInsert`, followed by the accounts of
+    /// `Put` after the owner.
+    RelayedPut(RelayedPut),
+
+    /// `Delete` submitted by a relayer, as described for `RelayedInsert`.
+    ///
+    /// Expects the accounts of `RelayedInsert` with a writable owner, followed
+    /// by the accounts of `Delete` after the owner.
+    RelayedDelete(RelayedDelete),
+
+    /// Creates a multisig authority requiring `threshold` of `signers`.
+    ///
+    /// Tables may then be created and written with the multisig in place of
+    /// their owner: the owner account of any instruction counts as signing
+    /// when it is a multisig and `threshold` of its signers sign the
+    /// transaction, passed after the other accounts of the instruction. The
+    /// multisig pays for the accounts of its tables out of its own lamports
+    /// and receives their refunds.
+    ///
+    /// Expects the following accounts:
+    /// 1. `[writable, signer]` Creator, pays for the account.
+    /// 2. `[writable]` Multisig PDA to create, derived from
+    ///    `["multisig", creator, name]`.
+    /// 3. `[]` System program.
+    CreateMultisig(CreateMultisig),
+
+    /// Replaces the signers and the threshold of a multisig, which must be
+    /// approved by its current signers.
+    ///
+    /// Expects the following accounts:
+    /// 1. `[writable]` Multisig account.
+    /// 2. `[signer]` At least `threshold` of its current signers.
+    SetMultisigSigners(SetMultisigSigners),
+
+    /// Grants `delegate`, typically an ephemeral session key, the right to
+    /// `Insert`, `Put` and `Delete` keys starting with `prefix` in a
+    /// dedicated table, at most `max_writes` times and until `expiry_slot`.
+    ///
+    /// The delegate signs those instructions in place of the owner, passing
+    /// itself and the delegation PDA before their accounts, where the owner
+    /// then does not sign. The delegate pays for the accounts it creates or
+    /// grows, while the owner receives the lamports of those it deletes.
+    ///
+    /// Expects the following accounts:
+    /// 1. `[writable, signer]` Owner of the table, pays for the account.
+    /// 2. `[]` Table account.
+    /// 3. `[writable]` Delegation PDA to create, derived from
+    ///    `["delegation", table, delegate]`.
+    /// 4. `[]` System program.
+    Delegate(Delegate),
+
+    /// Revokes a delegation before it expires, closing its account.
+    ///
+    /// Expects the following accounts:
+    /// 1. `[writable, signer]` Owner of the table, receives the lamports.
+    /// 2. `[]` Table account.
+    /// 3. `[writable]` Delegation PDA to close.
+    Revoke(Revoke),
+
+    /// Grants `roles`, a union of the `SolAcl` role flags, to `principal` on
+    /// a dedicated table, adding them to those it already holds.
+    ///
+    /// A principal holding the inserter, updater or deleter role signs the
+    /// matching `Insert`, `Put` or `Delete` in place of the owner, passing
+    /// itself and its ACL entry before their accounts as a delegate does,
+    /// with the same payment rules. Admins hold every role and may also
+    /// grant and revoke roles.
+    ///
+    /// Expects the following accounts:
+    /// 1. `[writable, signer]` Owner of the table or admin, pays for the
+    ///    entry.
+    /// 2. `[]` Owner of the table.
+    /// 3. `[]` Table account.
+    /// 4. `[writable]` ACL entry PDA of the principal, derived from
+    ///    `["acl", table, principal]`. Created when it does not exist yet.
+    /// 5. `[]` System program.
+    /// 6. `[]` ACL entry of the signer, when it is an admin.
+    GrantRoles(GrantRoles),
+
+    /// Revokes `roles` from `principal`, closing its ACL entry once it holds
+    /// none.
+    ///
+    /// Expects the following accounts:
+    /// 1. `[signer]` Owner of the table or admin.
+    /// 2. `[writable]` Owner of the table, receives the lamports of the
+    ///    entry.
+    /// 3. `[]` Table account.
+    /// 4. `[writable]` ACL entry PDA of the principal.
+    /// 5. `[]` ACL entry of the signer, when it is an admin.
+    RevokeRoles(RevokeRoles),
+
+    /// Hands control of a value of a dedicated table to `authority`, or
+    /// releases it back to the owner of the table when `None`.
+    ///
+    /// The owner sets the first authority, which then alone may change it.
+    /// While a value has an authority, the owner, its delegates and its
+    /// principals fail to write or delete it with `ValueClaimed`. The
+    /// authority signs `Put` for the value in place of the owner, passing
+    /// itself and the value authority PDA before its accounts as a delegate
+    /// does, and pays for any growth.
+    ///
+    /// Expects the following accounts:
+    /// 1. `[writable, signer]` Owner of the table when the value has no
+    ///    authority, its current authority otherwise.
+    /// 2. `[writable]` Owner of the table, pays for the value authority PDA
+    ///    and receives its lamports once released.
+    /// 3. `[]` Table account.
+    /// 4. `[writable]` Key-value PDA.
+    /// 5. `[writable]` Value authority PDA, derived from
+    ///    `["authority", value]`. Created when the value has no authority
+    ///    yet.
+    /// 6. `[]` System program.
+    SetValueAuthority(SetValueAuthority),
+
+    /// Lets holders of `token_gate.mint` insert and put values of a dedicated
+    /// table, or stops letting them when `None`.
+    ///
+    /// A holder signs `Insert` and `Put` in place of the owner, passing
+    /// itself and an SPL Token or Token-2022 account of the mint it owns,
+    /// holding at least `min_amount`, before their accounts as a delegate
+    /// does, with the same payment rules. Holders may not delete values.
+    ///
+    /// Holders write values of the owner rather than their own, so any holder
+    /// may put over a value another holder inserted.
+    ///
+    /// Expects the following accounts:
+    /// 1. `[writable, signer]` Owner of the table, pays for the growth.
+    /// 2. `[writable]` Table account.
+    /// 3. `[]` System program.
+    SetTokenGate(SetTokenGate),
+
+    /// Closes the value of `key` in a dedicated or global table, whoever
+    /// inserted it or controls it, and removes the key from the directory.
+    /// The value is the one `writer` inserted, or the only one of the key in
+    /// a global table. Values other signers inserted under their own keys
+    /// are not listed, so the directory is then left as is.
+    ///
+    /// Signed by the owner of the table or by a principal holding the
+    /// moderator role. The value may have an authority, whose PDA is then
+    /// closed as well. Its lamports go to the moderation destination of the
+    /// table, set by `SetModerationDestination`, while those of the value
+    /// authority PDA and of the emptied directory page go to the owner.
+    ///
+    /// A value still referenced through a restricting foreign key fails with
+    /// `ValueReferenced`, as it does for `Delete`: its children must be
+    /// moderated first.
+    ///
+    /// Expects the following accounts:
+    /// 1. `[signer]` Owner of the table or moderator.
+    /// 2. `[writable]` Owner of the table.
+    /// 3. `[writable]` Table account.
+    /// 4. `[writable]` Key-value PDA to close.
+    /// 5. `[]` System program.
+    /// 6. `[writable]` Directory page listing the key.
+    /// 7. `[writable]` Last directory page, as for `Delete`.
+    /// 8. `[writable]` Moderation destination of the table, or its owner
+    ///    when it has none.
+    /// 9. `[writable]` Value authority PDA, derived from `["authority",
+    ///    value]`. Only closed when the value has an authority.
+    /// 10. `[]` ACL entry of the signer. Only read when it is a moderator.
+    /// 11. `[writable]` Parent value the payload points at, when the table
+    ///     declares a restricting foreign key.
+    Moderate(Moderate),
+
+    /// Sets the account receiving the lamports of the values closed by
+    /// `Moderate` in a dedicated or global table, or the owner when `None`.
+    ///
+    /// Expects the following accounts:
+    /// 1. `[writable, signer]` Owner of the table, pays for the growth.
+    /// 2. `[writable]` Table account.
+    /// 3. `[]` System program.
+    SetModerationDestination(SetModerationDestination),
 }
 
 impl SolDbIntructions {
@@ -65,6 +519,151 @@ impl SolDbIntructions {
                     .map_err(|_| ProgramError::InvalidInstructionData)?;
                 Ok(Self::Delete(delete))
             }
+            4 => {
+                let patch = Patch::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Ok(Self::Patch(patch))
+            }
+            5 => {
+                let append = Append::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Ok(Self::Append(append))
+            }
+            6 => {
+                let truncate = Truncate::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Ok(Self::Truncate(truncate))
+            }
+            7 => {
+                let open_upload = OpenUpload::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Ok(Self::OpenUpload(open_upload))
+            }
+            8 => {
+                let write_chunk = WriteChunk::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Ok(Self::WriteChunk(write_chunk))
+            }
+            9 => {
+                let commit_upload = CommitUpload::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Ok(Self::CommitUpload(commit_upload))
+            }
+            10 => {
+                let upsert = Upsert::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Ok(Self::Upsert(upsert))
+            }
+            11 => {
+                let migrate = Migrate::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Ok(Self::Migrate(migrate))
+            }
+            12 => {
+                let verify_proof = VerifyProof::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Ok(Self::VerifyProof(verify_proof))
+            }
+            13 => {
+                let put = CompressedPut::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Ok(Self::CompressedPut(put))
+            }
+            14 => {
+                let delete = CompressedDelete::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Ok(Self::CompressedDelete(delete))
+            }
+            15 => {
+                let init_bloom = InitBloom::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Ok(Self::InitBloom(init_bloom))
+            }
+            16 => {
+                let maybe_contains = MaybeContains::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Ok(Self::MaybeContains(maybe_contains))
+            }
+            17 => {
+                let rebuild_bloom = RebuildBloom::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Ok(Self::RebuildBloom(rebuild_bloom))
+            }
+            18 => {
+                let set_foreign_key = SetForeignKey::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Ok(Self::SetForeignKey(set_foreign_key))
+            }
+            19 => {
+                let set_hook = SetHook::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Ok(Self::SetHook(set_hook))
+            }
+            20 => {
+                let insert = RelayedInsert::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Ok(Self::RelayedInsert(insert))
+            }
+            21 => {
+                let put = RelayedPut::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Ok(Self::RelayedPut(put))
+            }
+            22 => {
+                let delete = RelayedDelete::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Ok(Self::RelayedDelete(delete))
+            }
+            23 => {
+                let create_multisig = CreateMultisig::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Ok(Self::CreateMultisig(create_multisig))
+            }
+            24 => {
+                let set_signers = SetMultisigSigners::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Ok(Self::SetMultisigSigners(set_signers))
+            }
+            25 => {
+                let delegate = Delegate::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Ok(Self::Delegate(delegate))
+            }
+            26 => {
+                let revoke = Revoke::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Ok(Self::Revoke(revoke))
+            }
+            27 => {
+                let grant = GrantRoles::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Ok(Self::GrantRoles(grant))
+            }
+            28 => {
+                let revoke = RevokeRoles::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Ok(Self::RevokeRoles(revoke))
+            }
+            29 => {
+                let set_authority = SetValueAuthority::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Ok(Self::SetValueAuthority(set_authority))
+            }
+            30 => {
+                let set_token_gate = SetTokenGate::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Ok(Self::SetTokenGate(set_token_gate))
+            }
+            31 => {
+                let moderate = Moderate::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Ok(Self::Moderate(moderate))
+            }
+            32 => {
+                let set_destination = SetModerationDestination::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Ok(Self::SetModerationDestination(set_destination))
+            }
             _ => Err(ProgramError::InvalidInstructionData),
         }
     }
@@ -74,6 +673,7 @@ impl SolDbIntructions {
 pub struct InitTable {
     pub name: String,
     pub bump: u8,
+    pub mode: TableMode,
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Debug)]
@@ -81,6 +681,7 @@ pub struct Insert {
     pub key: Vec<u8>,
     pub payload: Vec<u8>,
     pub bump: u8,
+    pub capacity: u32,
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Debug)]
@@ -99,3 +700,1757 @@ pub struct Delete {
     pub key: Vec<u8>,
     pub key_bump: u8,
 }
+
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct Patch {
+    pub table: String,
+    pub table_bump: u8,
+    pub key: Vec<u8>,
+    pub key_bump: u8,
+    pub offset: u32,
+    pub data: Vec<u8>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct Append {
+    pub table: String,
+    pub table_bump: u8,
+    pub key: Vec<u8>,
+    pub key_bump: u8,
+    pub data: Vec<u8>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct Truncate {
+    pub table: String,
+    pub table_bump: u8,
+    pub key: Vec<u8>,
+    pub key_bump: u8,
+    pub len: u32,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct OpenUpload {
+    pub table: String,
+    pub table_bump: u8,
+    pub key: Vec<u8>,
+    pub key_bump: u8,
+    pub len: u32,
+    pub digest: [u8; 32],
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct WriteChunk {
+    pub table: String,
+    pub table_bump: u8,
+    pub key: Vec<u8>,
+    pub key_bump: u8,
+    pub offset: u32,
+    pub data: Vec<u8>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct CommitUpload {
+    pub table: String,
+    pub table_bump: u8,
+    pub key: Vec<u8>,
+    pub key_bump: u8,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct Upsert {
+    pub table: String,
+    pub table_bump: u8,
+    pub key: Vec<u8>,
+    pub key_bump: u8,
+    pub capacity: u32,
+    pub payload: Vec<u8>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct Migrate {
+    pub table: String,
+    pub table_bump: u8,
+    pub key: Vec<u8>,
+    pub key_bump: u8,
+    pub capacity: u32,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct VerifyProof {
+    /// Signer the value PDA is derived from, or the owner of a global table.
+    pub writer: Pubkey,
+    pub key: Vec<u8>,
+    pub value_hash: [u8; 32],
+    pub index: u32,
+    pub proof: Vec<[u8; 32]>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct CompressedPut {
+    pub table: String,
+    pub table_bump: u8,
+    pub key: Vec<u8>,
+    pub payload: Vec<u8>,
+    /// Hash of the value the key held, `None` when the leaf is free.
+    pub previous_hash: Option<[u8; 32]>,
+    pub root: [u8; 32],
+    pub index: u32,
+    pub proof: Vec<[u8; 32]>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct CompressedDelete {
+    pub table: String,
+    pub table_bump: u8,
+    pub key: Vec<u8>,
+    pub value_hash: [u8; 32],
+    pub root: [u8; 32],
+    pub index: u32,
+    pub proof: Vec<[u8; 32]>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct InitBloom {
+    pub table: String,
+    pub table_bump: u8,
+    pub bits: u32,
+    pub hashes: u8,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct MaybeContains {
+    pub key: Vec<u8>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct RebuildBloom {
+    pub table: String,
+    pub table_bump: u8,
+    pub reset: bool,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct SetForeignKey {
+    pub table: String,
+    pub table_bump: u8,
+    pub foreign_key: ForeignKey,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct SetHook {
+    pub table: String,
+    pub table_bump: u8,
+    pub hook: Option<Pubkey>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct RelayedInsert {
+    pub table: String,
+    pub table_bump: u8,
+    pub key: Vec<u8>,
+    pub key_bump: u8,
+    pub payload: Vec<u8>,
+    pub nonce: u64,
+    pub expiry: i64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct RelayedPut {
+    pub table: String,
+    pub table_bump: u8,
+    pub key: Vec<u8>,
+    pub key_bump: u8,
+    pub payload: Vec<u8>,
+    pub nonce: u64,
+    pub expiry: i64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct RelayedDelete {
+    pub table: String,
+    pub table_bump: u8,
+    pub key: Vec<u8>,
+    pub key_bump: u8,
+    pub nonce: u64,
+    pub expiry: i64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct CreateMultisig {
+    pub name: String,
+    pub bump: u8,
+    pub threshold: u8,
+    pub signers: Vec<Pubkey>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct SetMultisigSigners {
+    pub threshold: u8,
+    pub signers: Vec<Pubkey>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct Delegate {
+    pub table: String,
+    pub table_bump: u8,
+    pub delegate: Pubkey,
+    pub bump: u8,
+    pub prefix: Vec<u8>,
+    pub max_writes: u64,
+    pub expiry_slot: u64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct Revoke {
+    pub table: String,
+    pub table_bump: u8,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct GrantRoles {
+    pub table: String,
+    pub table_bump: u8,
+    pub principal: Pubkey,
+    pub bump: u8,
+    pub roles: u8,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct RevokeRoles {
+    pub table: String,
+    pub table_bump: u8,
+    pub principal: Pubkey,
+    pub roles: u8,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct SetValueAuthority {
+    pub table: String,
+    pub table_bump: u8,
+    pub key: Vec<u8>,
+    pub key_bump: u8,
+    pub bump: u8,
+    pub authority: Option<Pubkey>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct SetTokenGate {
+    pub table: String,
+    pub table_bump: u8,
+    pub token_gate: Option<TokenGate>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct Moderate {
+    pub table: String,
+    pub table_bump: u8,
+    /// Signer that inserted the value, from which the value PDA of a
+    /// dedicated table is derived. Global tables ignore it.
+    pub writer: Pubkey,
+    pub key: Vec<u8>,
+    pub key_bump: u8,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct SetModerationDestination {
+    pub table: String,
+    pub table_bump: u8,
+    pub destination: Option<Pubkey>,
+}
+
+/// Builds an `InitTable` instruction for the table `name` owned by `owner`.
+pub fn init_table(program_id: &Pubkey, owner: &Pubkey, name: &str) -> Instruction {
+    init_table_with_mode(program_id, owner, name, TableMode::Dedicated)
+}
+
+/// Builds an `InitTable` instruction for a packed table spreading its values
+/// over `slabs` slabs.
+pub fn init_packed_table(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    slabs: u8,
+) -> Instruction {
+    init_table_with_mode(program_id, owner, name, TableMode::Packed { slabs })
+}
+
+/// Builds an `InitTable` instruction for a global table, whose value PDAs
+/// are derived from `[key, table]` alone.
+pub fn init_global_table(program_id: &Pubkey, owner: &Pubkey, name: &str) -> Instruction {
+    init_table_with_mode(program_id, owner, name, TableMode::Global)
+}
+
+/// Builds an `InitTable` instruction for a compressed table whose tree has
+/// `depth` levels, holding up to `2^depth` keys.
+pub fn init_compressed_table(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    depth: u8,
+) -> Instruction {
+    init_table_with_mode(program_id, owner, name, TableMode::Compressed { depth })
+}
+
+fn init_table_with_mode(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    mode: TableMode,
+) -> Instruction {
+    let (table, bump) = find_table_address(program_id, owner, name);
+
+    let mut metas = vec![
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(table, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+    ];
+    if let TableMode::Compressed { .. } = mode {
+        let (tree, _) = find_tree_address(program_id, &table);
+        metas.push(AccountMeta::new(tree, false));
+    }
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::InitTable(InitTable {
+            name: name.to_string(),
+            bump,
+            mode,
+        }),
+        metas,
+    )
+}
+
+/// Builds an `Insert` instruction storing `payload` under `key` in `table`.
+///
+/// `page` is the last directory page of the table, which the key is
+/// appended to: `SolDirectory::page_of(keys)` for a table of `keys` keys.
+pub fn insert(
+    program_id: &Pubkey,
+    table: &Pubkey,
+    owner: &Pubkey,
+    key: &[u8],
+    payload: &[u8],
+    page: u32,
+) -> Instruction {
+    insert_with_capacity(program_id, table, owner, key, payload, 0, page)
+}
+
+/// Builds an `Insert` instruction reserving room for `capacity` payload
+/// bytes.
+pub fn insert_with_capacity(
+    program_id: &Pubkey,
+    table: &Pubkey,
+    owner: &Pubkey,
+    key: &[u8],
+    payload: &[u8],
+    capacity: u32,
+    page: u32,
+) -> Instruction {
+    let (value, bump) = find_value_address(program_id, table, owner, key);
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::Insert(Insert {
+            key: key.to_vec(),
+            payload: payload.to_vec(),
+            bump,
+            capacity,
+        }),
+        vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(*table, false),
+            AccountMeta::new(value, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            directory_meta(program_id, table, page),
+        ],
+    )
+}
+
+/// Builds an `Insert` instruction storing `payload` under `key` in the
+/// global `table`, where `owner` signs as it does in [`insert`].
+pub fn global_insert(
+    program_id: &Pubkey,
+    table: &Pubkey,
+    owner: &Pubkey,
+    key: &[u8],
+    payload: &[u8],
+    page: u32,
+) -> Instruction {
+    let (value, bump) = find_global_value_address(program_id, table, key);
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::Insert(Insert {
+            key: key.to_vec(),
+            payload: payload.to_vec(),
+            bump,
+            capacity: 0,
+        }),
+        vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(*table, false),
+            AccountMeta::new(value, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            directory_meta(program_id, table, page),
+        ],
+    )
+}
+
+/// Builds a `Put` instruction overwriting the value of `key` in the table
+/// `name` owned by `owner`.
+///
+/// `page` is the directory page listing the key, whose Merkle leaf is
+/// updated. It is ignored for values migrated out of a packed table.
+pub fn put(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    key: &[u8],
+    payload: &[u8],
+    page: u32,
+) -> Instruction {
+    let target = ValueTarget::new(program_id, owner, name, key);
+    put_target(program_id, owner, name, &target, key, payload, page)
+}
+
+/// Builds a `Put` instruction overwriting the value of `key` in the global
+/// table `name` owned by `owner`, as [`put`] does.
+pub fn global_put(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    key: &[u8],
+    payload: &[u8],
+    page: u32,
+) -> Instruction {
+    let target = ValueTarget::global(program_id, owner, name, key);
+    put_target(program_id, owner, name, &target, key, payload, page)
+}
+
+fn put_target(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    target: &ValueTarget,
+    key: &[u8],
+    payload: &[u8],
+    page: u32,
+) -> Instruction {
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::Put(Put {
+            table: name.to_string(),
+            table_bump: target.table_bump,
+            key: key.to_vec(),
+            key_bump: target.value_bump,
+            payload: payload.to_vec(),
+        }),
+        target.committed_metas(program_id, owner, page),
+    )
+}
+
+/// Builds a `Delete` instruction closing the value of `key` in the table
+/// `name` owned by `owner`.
+///
+/// `page` is the directory page listing the key and `keys` the number of
+/// keys the table holds, which tells its last page.
+pub fn delete(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    key: &[u8],
+    page: u32,
+    keys: u32,
+) -> Instruction {
+    let target = ValueTarget::new(program_id, owner, name, key);
+    delete_target(program_id, owner, name, &target, key, page, keys)
+}
+
+/// Builds a `Delete` instruction closing the value of `key` in the global
+/// table `name` owned by `owner`, as [`delete`] does.
+pub fn global_delete(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    key: &[u8],
+    page: u32,
+    keys: u32,
+) -> Instruction {
+    let target = ValueTarget::global(program_id, owner, name, key);
+    delete_target(program_id, owner, name, &target, key, page, keys)
+}
+
+fn delete_target(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    target: &ValueTarget,
+    key: &[u8],
+    page: u32,
+    keys: u32,
+) -> Instruction {
+    let last = SolDirectory::page_of(keys.saturating_sub(1));
+
+    let mut metas = target.table_metas(owner);
+    metas.push(directory_meta(program_id, &target.table, page));
+    metas.push(directory_meta(program_id, &target.table, last));
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::Delete(Delete {
+            table: name.to_string(),
+            table_bump: target.table_bump,
+            key: key.to_vec(),
+            key_bump: target.value_bump,
+        }),
+        metas,
+    )
+}
+
+/// Builds a `Patch` instruction writing `data` at `offset` of a value.
+/// `page` is used as in [`put`].
+pub fn patch(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    key: &[u8],
+    offset: u32,
+    data: &[u8],
+    page: u32,
+) -> Instruction {
+    let target = ValueTarget::new(program_id, owner, name, key);
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::Patch(Patch {
+            table: name.to_string(),
+            table_bump: target.table_bump,
+            key: key.to_vec(),
+            key_bump: target.value_bump,
+            offset,
+            data: data.to_vec(),
+        }),
+        target.committed_metas(program_id, owner, page),
+    )
+}
+
+/// Builds an `Append` instruction adding `data` to the tail of a value.
+/// `page` is used as in [`put`].
+pub fn append(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    key: &[u8],
+    data: &[u8],
+    page: u32,
+) -> Instruction {
+    let target = ValueTarget::new(program_id, owner, name, key);
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::Append(Append {
+            table: name.to_string(),
+            table_bump: target.table_bump,
+            key: key.to_vec(),
+            key_bump: target.value_bump,
+            data: data.to_vec(),
+        }),
+        target.committed_metas(program_id, owner, page),
+    )
+}
+
+/// Builds a `Truncate` instruction shrinking a value to `len` bytes.
+/// `page` is used as in [`put`].
+pub fn truncate(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    key: &[u8],
+    len: u32,
+    page: u32,
+) -> Instruction {
+    let target = ValueTarget::new(program_id, owner, name, key);
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::Truncate(Truncate {
+            table: name.to_string(),
+            table_bump: target.table_bump,
+            key: key.to_vec(),
+            key_bump: target.value_bump,
+            len,
+        }),
+        target.committed_metas(program_id, owner, page),
+    )
+}
+
+/// Builds an `OpenUpload` instruction for a value of `len` bytes whose
+/// chunks chain to `digest`. `page` is the last directory page as in
+/// [`insert`], and is only used when the value does not exist yet.
+pub fn open_upload(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    key: &[u8],
+    len: u32,
+    digest: [u8; 32],
+    page: u32,
+) -> Instruction {
+    let target = ValueTarget::new(program_id, owner, name, key);
+    let metas = target.committed_upload_metas(program_id, owner, page);
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::OpenUpload(OpenUpload {
+            table: name.to_string(),
+            table_bump: target.table_bump,
+            key: key.to_vec(),
+            key_bump: target.value_bump,
+            len,
+            digest,
+        }),
+        metas,
+    )
+}
+
+/// Builds a `WriteChunk` instruction writing `data` at `offset` of an upload.
+pub fn write_chunk(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    key: &[u8],
+    offset: u32,
+    data: &[u8],
+) -> Instruction {
+    let target = ValueTarget::new(program_id, owner, name, key);
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::WriteChunk(WriteChunk {
+            table: name.to_string(),
+            table_bump: target.table_bump,
+            key: key.to_vec(),
+            key_bump: target.value_bump,
+            offset,
+            data: data.to_vec(),
+        }),
+        target.upload_metas(program_id, owner),
+    )
+}
+
+/// Builds a `CommitUpload` instruction installing an upload as the value.
+/// `page` is used as in [`put`].
+pub fn commit_upload(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    key: &[u8],
+    page: u32,
+) -> Instruction {
+    let target = ValueTarget::new(program_id, owner, name, key);
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::CommitUpload(CommitUpload {
+            table: name.to_string(),
+            table_bump: target.table_bump,
+            key: key.to_vec(),
+            key_bump: target.value_bump,
+        }),
+        target.committed_upload_metas(program_id, owner, page),
+    )
+}
+
+/// Builds an `Upsert` instruction writing `payload` to the value of `key`,
+/// creating it with room for `capacity` payload bytes if it does not exist.
+/// `page` is the directory page listing the key, or the last page as in
+/// [`insert`] when it does not exist yet.
+pub fn upsert(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    key: &[u8],
+    payload: &[u8],
+    capacity: u32,
+    page: u32,
+) -> Instruction {
+    let target = ValueTarget::new(program_id, owner, name, key);
+    let metas = target.committed_metas(program_id, owner, page);
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::Upsert(Upsert {
+            table: name.to_string(),
+            table_bump: target.table_bump,
+            key: key.to_vec(),
+            key_bump: target.value_bump,
+            capacity,
+            payload: payload.to_vec(),
+        }),
+        metas,
+    )
+}
+
+/// Builds an `Insert` instruction storing `payload` under `key` in the slab
+/// it hashes to, in the packed table `name` of `slabs` slabs.
+pub fn packed_insert(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    key: &[u8],
+    payload: &[u8],
+    slabs: u8,
+) -> Instruction {
+    let target = SlabTarget::new(program_id, owner, name, key, slabs);
+    let metas = target.metas(owner);
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::Insert(Insert {
+            key: key.to_vec(),
+            payload: payload.to_vec(),
+            bump: 0,
+            capacity: 0,
+        }),
+        metas,
+    )
+}
+
+/// Builds a `Put` instruction overwriting the slab entry of `key` in a
+/// packed table.
+pub fn packed_put(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    key: &[u8],
+    payload: &[u8],
+    slabs: u8,
+) -> Instruction {
+    let target = SlabTarget::new(program_id, owner, name, key, slabs);
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::Put(Put {
+            table: name.to_string(),
+            table_bump: target.table_bump,
+            key: key.to_vec(),
+            key_bump: 0,
+            payload: payload.to_vec(),
+        }),
+        target.metas(owner),
+    )
+}
+
+/// Builds a `Delete` instruction removing the slab entry of `key` from a
+/// packed table.
+pub fn packed_delete(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    key: &[u8],
+    slabs: u8,
+) -> Instruction {
+    let target = SlabTarget::new(program_id, owner, name, key, slabs);
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::Delete(Delete {
+            table: name.to_string(),
+            table_bump: target.table_bump,
+            key: key.to_vec(),
+            key_bump: 0,
+        }),
+        target.metas(owner),
+    )
+}
+
+/// Builds a `Migrate` instruction moving the slab entry of `key` to its
+/// dedicated PDA, with room for `capacity` payload bytes.
+pub fn migrate(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    key: &[u8],
+    slabs: u8,
+    capacity: u32,
+) -> Instruction {
+    let target = ValueTarget::new(program_id, owner, name, key);
+    let (slab, _) = find_slab_address(program_id, &target.table, SolSlab::index_of(key, slabs));
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::Migrate(Migrate {
+            table: name.to_string(),
+            table_bump: target.table_bump,
+            key: key.to_vec(),
+            key_bump: target.value_bump,
+            capacity,
+        }),
+        vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new_readonly(target.table, false),
+            AccountMeta::new(slab, false),
+            AccountMeta::new(target.value, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+    )
+}
+
+/// Builds a `Delete` instruction closing a migrated value of a packed table
+/// and releasing its slab entry.
+pub fn delete_migrated(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    key: &[u8],
+    slabs: u8,
+) -> Instruction {
+    let target = ValueTarget::new(program_id, owner, name, key);
+    let (slab, _) = find_slab_address(program_id, &target.table, SolSlab::index_of(key, slabs));
+
+    let mut metas = target.metas(owner);
+    metas.push(AccountMeta::new(slab, false));
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::Delete(Delete {
+            table: name.to_string(),
+            table_bump: target.table_bump,
+            key: key.to_vec(),
+            key_bump: target.value_bump,
+        }),
+        metas,
+    )
+}
+
+/// Builds a `VerifyProof` instruction checking that `key` of `writer` holds a
+/// value hashing to `value_hash` in `table`. Only dedicated and global tables
+/// commit their values to a root: packed tables fail with `WrongTableMode`.
+pub fn verify_proof(
+    program_id: &Pubkey,
+    table: &Pubkey,
+    writer: &Pubkey,
+    key: &[u8],
+    value_hash: [u8; 32],
+    index: u32,
+    proof: Vec<[u8; 32]>,
+) -> Instruction {
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::VerifyProof(VerifyProof {
+            writer: *writer,
+            key: key.to_vec(),
+            value_hash,
+            index,
+            proof,
+        }),
+        vec![AccountMeta::new_readonly(*table, false)],
+    )
+}
+
+/// Builds a `CompressedPut` instruction writing `payload` under `key` at
+/// leaf `index` of the compressed table `name` of `owner`, from the `proof`
+/// of the leaf against `root`.
+#[allow(clippy::too_many_arguments)]
+pub fn compressed_put(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    key: &[u8],
+    payload: &[u8],
+    previous_hash: Option<[u8; 32]>,
+    root: [u8; 32],
+    index: u32,
+    proof: Vec<[u8; 32]>,
+) -> Instruction {
+    let (table, table_bump) = find_table_address(program_id, owner, name);
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::CompressedPut(CompressedPut {
+            table: name.to_string(),
+            table_bump,
+            key: key.to_vec(),
+            payload: payload.to_vec(),
+            previous_hash,
+            root,
+            index,
+            proof,
+        }),
+        compressed_metas(program_id, owner, &table),
+    )
+}
+
+/// Builds a `CompressedDelete` instruction freeing leaf `index` of the
+/// compressed table `name` of `owner`, which holds `key` with a value
+/// hashing to `value_hash`.
+#[allow(clippy::too_many_arguments)]
+pub fn compressed_delete(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    key: &[u8],
+    value_hash: [u8; 32],
+    root: [u8; 32],
+    index: u32,
+    proof: Vec<[u8; 32]>,
+) -> Instruction {
+    let (table, table_bump) = find_table_address(program_id, owner, name);
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::CompressedDelete(CompressedDelete {
+            table: name.to_string(),
+            table_bump,
+            key: key.to_vec(),
+            value_hash,
+            root,
+            index,
+            proof,
+        }),
+        compressed_metas(program_id, owner, &table),
+    )
+}
+
+fn compressed_metas(program_id: &Pubkey, owner: &Pubkey, table: &Pubkey) -> Vec<AccountMeta> {
+    let (tree, _) = find_tree_address(program_id, table);
+    vec![
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new_readonly(*table, false),
+        AccountMeta::new(tree, false),
+    ]
+}
+
+/// Builds an `InitBloom` instruction creating a filter of `bits` bits and
+/// `hashes` bits per key for the table `name` of `owner`.
+pub fn init_bloom(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    bits: u32,
+    hashes: u8,
+) -> Instruction {
+    let (table, table_bump) = find_table_address(program_id, owner, name);
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::InitBloom(InitBloom {
+            table: name.to_string(),
+            table_bump,
+            bits,
+            hashes,
+        }),
+        vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(table, false),
+            bloom_meta(program_id, &table),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+    )
+}
+
+/// Builds a `MaybeContains` instruction asking whether `key` may exist in
+/// `table`.
+pub fn maybe_contains(program_id: &Pubkey, table: &Pubkey, key: &[u8]) -> Instruction {
+    let (bloom, _) = find_bloom_address(program_id, table);
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::MaybeContains(MaybeContains { key: key.to_vec() }),
+        vec![
+            AccountMeta::new_readonly(*table, false),
+            AccountMeta::new_readonly(bloom, false),
+        ],
+    )
+}
+
+/// Builds a `RebuildBloom` instruction adding the keys of `sources`,
+/// directory pages or slabs of the table `name` of `owner`, to its filter.
+pub fn rebuild_bloom(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    reset: bool,
+    sources: &[Pubkey],
+) -> Instruction {
+    let (table, table_bump) = find_table_address(program_id, owner, name);
+
+    let mut metas = vec![
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new_readonly(table, false),
+        bloom_meta(program_id, &table),
+    ];
+    metas.extend(
+        sources
+            .iter()
+            .map(|source| AccountMeta::new_readonly(*source, false)),
+    );
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::RebuildBloom(RebuildBloom {
+            table: name.to_string(),
+            table_bump,
+            reset,
+        }),
+        metas,
+    )
+}
+
+/// Builds a `SetForeignKey` instruction declaring `foreign_key` on the table
+/// `name` of `owner`.
+pub fn set_foreign_key(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    foreign_key: ForeignKey,
+) -> Instruction {
+    let (table, table_bump) = find_table_address(program_id, owner, name);
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::SetForeignKey(SetForeignKey {
+            table: name.to_string(),
+            table_bump,
+            foreign_key,
+        }),
+        vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(table, false),
+            AccountMeta::new_readonly(foreign_key.parent, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+    )
+}
+
+/// Builds a `SetHook` instruction registering `hook` on the table `name` of
+/// `owner`, or removing its hook when `None`.
+pub fn set_hook(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    hook: Option<Pubkey>,
+) -> Instruction {
+    let (table, table_bump) = find_table_address(program_id, owner, name);
+
+    let mut metas = vec![
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(table, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+    ];
+    metas.extend(hook.map(|hook| AccountMeta::new_readonly(hook, false)));
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::SetHook(SetHook {
+            table: name.to_string(),
+            table_bump,
+            hook,
+        }),
+        metas,
+    )
+}
+
+/// Builds a `RelayedInsert` instruction carrying out `message`, signed by
+/// `owner` for their table `name`. It must follow the instruction built by
+/// [`crate::relay::signature_instruction`] for the same message.
+///
+/// `page` is the last directory page of the table, as in [`insert`].
+pub fn relayed_insert(
+    program_id: &Pubkey,
+    relayer: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    message: &RelayedMessage,
+    page: u32,
+) -> Instruction {
+    let target = ValueTarget::new(program_id, owner, name, &message.key);
+
+    let mut metas = target.relayed_metas(program_id, relayer, owner);
+    metas.push(directory_meta(program_id, &target.table, page));
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::RelayedInsert(RelayedInsert {
+            table: name.to_string(),
+            table_bump: target.table_bump,
+            key: message.key.clone(),
+            key_bump: target.value_bump,
+            payload: message.payload.clone(),
+            nonce: message.nonce,
+            expiry: message.expiry,
+        }),
+        metas,
+    )
+}
+
+/// Builds a `RelayedPut` instruction carrying out `message`, as
+/// [`relayed_insert`] does. `page` is used as in [`put`].
+pub fn relayed_put(
+    program_id: &Pubkey,
+    relayer: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    message: &RelayedMessage,
+    page: u32,
+) -> Instruction {
+    let target = ValueTarget::new(program_id, owner, name, &message.key);
+
+    let mut metas = target.relayed_metas(program_id, relayer, owner);
+    metas.push(directory_meta(program_id, &target.table, page));
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::RelayedPut(RelayedPut {
+            table: name.to_string(),
+            table_bump: target.table_bump,
+            key: message.key.clone(),
+            key_bump: target.value_bump,
+            payload: message.payload.clone(),
+            nonce: message.nonce,
+            expiry: message.expiry,
+        }),
+        metas,
+    )
+}
+
+/// Builds a `RelayedDelete` instruction carrying out `message`, as
+/// [`relayed_insert`] does. `page` and `keys` are used as in [`delete`].
+pub fn relayed_delete(
+    program_id: &Pubkey,
+    relayer: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    message: &RelayedMessage,
+    page: u32,
+    keys: u32,
+) -> Instruction {
+    let target = ValueTarget::new(program_id, owner, name, &message.key);
+    let last = SolDirectory::page_of(keys.saturating_sub(1));
+
+    let mut metas = target.relayed_metas(program_id, relayer, owner);
+    metas[3] = AccountMeta::new(*owner, false);
+    metas.push(directory_meta(program_id, &target.table, page));
+    metas.push(directory_meta(program_id, &target.table, last));
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::RelayedDelete(RelayedDelete {
+            table: name.to_string(),
+            table_bump: target.table_bump,
+            key: message.key.clone(),
+            key_bump: target.value_bump,
+            nonce: message.nonce,
+            expiry: message.expiry,
+        }),
+        metas,
+    )
+}
+
+/// Builds a `CreateMultisig` instruction creating the multisig `name` of
+/// `creator`, requiring `threshold` of `signers`.
+pub fn create_multisig(
+    program_id: &Pubkey,
+    creator: &Pubkey,
+    name: &str,
+    threshold: u8,
+    signers: &[Pubkey],
+) -> Instruction {
+    let (multisig, bump) = find_multisig_address(program_id, creator, name);
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::CreateMultisig(CreateMultisig {
+            name: name.to_string(),
+            bump,
+            threshold,
+            signers: signers.to_vec(),
+        }),
+        vec![
+            AccountMeta::new(*creator, true),
+            AccountMeta::new(multisig, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+    )
+}
+
+/// Builds a `SetMultisigSigners` instruction replacing the signers of
+/// `multisig`, approved by its current signers `approvers`.
+pub fn set_multisig_signers(
+    program_id: &Pubkey,
+    multisig: &Pubkey,
+    threshold: u8,
+    signers: &[Pubkey],
+    approvers: &[Pubkey],
+) -> Instruction {
+    let ix = Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::SetMultisigSigners(SetMultisigSigners {
+            threshold,
+            signers: signers.to_vec(),
+        }),
+        vec![AccountMeta::new(*multisig, true)],
+    );
+    with_multisig(ix, approvers)
+}
+
+/// Adapts `ix`, built with a multisig as the owner, to be approved by the
+/// signers `approvers` instead: the multisig no longer signs, and the
+/// approvers are pushed after the accounts of `ix`.
+///
+/// Metas the instruction takes at a fixed position, such as the parent
+/// value of a foreign key, must be pushed before, and hook metas after.
+pub fn with_multisig(mut ix: Instruction, approvers: &[Pubkey]) -> Instruction {
+    ix.accounts[0].is_signer = false;
+    ix.accounts.extend(
+        approvers
+            .iter()
+            .map(|approver| AccountMeta::new_readonly(*approver, true)),
+    );
+    ix
+}
+
+/// Builds a `Delegate` instruction letting `delegate` write the keys of the
+/// table `name` of `owner` starting with `prefix`, at most `max_writes`
+/// times and until `expiry_slot`.
+pub fn delegate(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    delegate: &Pubkey,
+    prefix: &[u8],
+    max_writes: u64,
+    expiry_slot: u64,
+) -> Instruction {
+    let (table, table_bump) = find_table_address(program_id, owner, name);
+    let (delegation, bump) = find_delegation_address(program_id, &table, delegate);
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::Delegate(Delegate {
+            table: name.to_string(),
+            table_bump,
+            delegate: *delegate,
+            bump,
+            prefix: prefix.to_vec(),
+            max_writes,
+            expiry_slot,
+        }),
+        vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new_readonly(table, false),
+            AccountMeta::new(delegation, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+    )
+}
+
+/// Builds a `Revoke` instruction closing the delegation of `delegate` on the
+/// table `name` of `owner`.
+pub fn revoke(program_id: &Pubkey, owner: &Pubkey, name: &str, delegate: &Pubkey) -> Instruction {
+    let (table, table_bump) = find_table_address(program_id, owner, name);
+    let (delegation, _) = find_delegation_address(program_id, &table, delegate);
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::Revoke(Revoke {
+            table: name.to_string(),
+            table_bump,
+        }),
+        vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new_readonly(table, false),
+            AccountMeta::new(delegation, false),
+        ],
+    )
+}
+
+/// Builds a `GrantRoles` instruction adding `roles` to `principal` on the
+/// table `name` of `owner`, signed by `authority`: the owner or an admin.
+pub fn grant_roles(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    principal: &Pubkey,
+    roles: u8,
+) -> Instruction {
+    let (table, table_bump) = find_table_address(program_id, owner, name);
+    let (acl, bump) = find_acl_address(program_id, &table, principal);
+
+    let mut metas = vec![
+        AccountMeta::new(*authority, true),
+        AccountMeta::new_readonly(*owner, false),
+        AccountMeta::new_readonly(table, false),
+        AccountMeta::new(acl, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+    ];
+    metas.extend(admin_meta(program_id, authority, owner, &table));
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::GrantRoles(GrantRoles {
+            table: name.to_string(),
+            table_bump,
+            principal: *principal,
+            bump,
+            roles,
+        }),
+        metas,
+    )
+}
+
+/// Builds a `RevokeRoles` instruction removing `roles` from `principal`, as
+/// [`grant_roles`] does.
+pub fn revoke_roles(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    principal: &Pubkey,
+    roles: u8,
+) -> Instruction {
+    let (table, table_bump) = find_table_address(program_id, owner, name);
+    let (acl, _) = find_acl_address(program_id, &table, principal);
+
+    let mut metas = vec![
+        AccountMeta::new_readonly(*authority, true),
+        AccountMeta::new(*owner, false),
+        AccountMeta::new_readonly(table, false),
+        AccountMeta::new(acl, false),
+    ];
+    metas.extend(admin_meta(program_id, authority, owner, &table));
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::RevokeRoles(RevokeRoles {
+            table: name.to_string(),
+            table_bump,
+            principal: *principal,
+            roles,
+        }),
+        metas,
+    )
+}
+
+/// Builds a `SetValueAuthority` instruction handing the value of `key` in
+/// the table `name` of `owner` to `authority`, or releasing it when `None`,
+/// signed by `signer`: the owner or the current authority.
+pub fn set_value_authority(
+    program_id: &Pubkey,
+    signer: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    key: &[u8],
+    authority: Option<Pubkey>,
+) -> Instruction {
+    let target = ValueTarget::new(program_id, owner, name, key);
+    let (record, bump) = find_value_authority_address(program_id, &target.value);
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::SetValueAuthority(SetValueAuthority {
+            table: name.to_string(),
+            table_bump: target.table_bump,
+            key: key.to_vec(),
+            key_bump: target.value_bump,
+            bump,
+            authority,
+        }),
+        vec![
+            AccountMeta::new(*signer, true),
+            AccountMeta::new(*owner, false),
+            AccountMeta::new_readonly(target.table, false),
+            AccountMeta::new(target.value, false),
+            AccountMeta::new(record, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+    )
+}
+
+/// Builds a `SetTokenGate` instruction letting holders of `token_gate.mint`
+/// write the table `name` of `owner`, or removing its gate when `None`.
+pub fn set_token_gate(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    token_gate: Option<TokenGate>,
+) -> Instruction {
+    let (table, table_bump) = find_table_address(program_id, owner, name);
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::SetTokenGate(SetTokenGate {
+            table: name.to_string(),
+            table_bump,
+            token_gate,
+        }),
+        vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(table, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+    )
+}
+
+/// Builds a `Moderate` instruction closing the value `writer` inserted under
+/// `key` in the table `name` of `owner`, signed by `moderator`: the owner or
+/// a principal holding the moderator role. `page` and `keys` are used as in
+/// [`delete`], and `destination` is the moderation destination of the table.
+#[allow(clippy::too_many_arguments)]
+pub fn moderate(
+    program_id: &Pubkey,
+    moderator: &Pubkey,
+    owner: &Pubkey,
+    writer: &Pubkey,
+    name: &str,
+    key: &[u8],
+    page: u32,
+    keys: u32,
+    destination: &Pubkey,
+) -> Instruction {
+    let target = ValueTarget::written_by(program_id, owner, writer, name, key);
+    moderate_target(
+        program_id,
+        moderator,
+        owner,
+        writer,
+        name,
+        &target,
+        key,
+        page,
+        keys,
+        destination,
+    )
+}
+
+/// Builds a `Moderate` instruction for a global table, as [`moderate`] does.
+#[allow(clippy::too_many_arguments)]
+pub fn global_moderate(
+    program_id: &Pubkey,
+    moderator: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    key: &[u8],
+    page: u32,
+    keys: u32,
+    destination: &Pubkey,
+) -> Instruction {
+    let target = ValueTarget::global(program_id, owner, name, key);
+    moderate_target(
+        program_id,
+        moderator,
+        owner,
+        owner,
+        name,
+        &target,
+        key,
+        page,
+        keys,
+        destination,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn moderate_target(
+    program_id: &Pubkey,
+    moderator: &Pubkey,
+    owner: &Pubkey,
+    writer: &Pubkey,
+    name: &str,
+    target: &ValueTarget,
+    key: &[u8],
+    page: u32,
+    keys: u32,
+    destination: &Pubkey,
+) -> Instruction {
+    // Moderating takes the accounts of `Delete`, where the owner no longer
+    // signs.
+    let ix = delete_target(program_id, owner, name, target, key, page, keys);
+    let (record, _) = find_value_authority_address(program_id, &target.value);
+    let (acl, _) = find_acl_address(program_id, &target.table, moderator);
+
+    let mut metas = vec![AccountMeta::new_readonly(*moderator, true)];
+    metas.extend(ix.accounts);
+    metas[1].is_signer = false;
+    metas.extend([
+        AccountMeta::new(*destination, false),
+        AccountMeta::new(record, false),
+        AccountMeta::new_readonly(acl, false),
+    ]);
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::Moderate(Moderate {
+            table: name.to_string(),
+            table_bump: target.table_bump,
+            writer: *writer,
+            key: key.to_vec(),
+            key_bump: target.value_bump,
+        }),
+        metas,
+    )
+}
+
+/// Builds a `SetModerationDestination` instruction sending the lamports of
+/// the values moderated in the table `name` of `owner` to `destination`, or
+/// back to the owner when `None`.
+pub fn set_moderation_destination(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    destination: Option<Pubkey>,
+) -> Instruction {
+    let (table, table_bump) = find_table_address(program_id, owner, name);
+
+    Instruction::new_with_borsh(
+        *program_id,
+        &SolDbIntructions::SetModerationDestination(SetModerationDestination {
+            table: name.to_string(),
+            table_bump,
+            destination,
+        }),
+        vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(table, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+    )
+}
+
+/// Adapts an `Insert`, `Put` or `Delete` instruction `ix` to be signed by
+/// `delegate` instead of the owner: the owner no longer signs, and the
+/// delegate and its delegation PDA are inserted before the accounts of `ix`.
+pub fn with_delegate(program_id: &Pubkey, ix: Instruction, delegate: &Pubkey) -> Instruction {
+    let (delegation, _) = find_delegation_address(program_id, &ix.accounts[1].pubkey, delegate);
+    with_grant(ix, delegate, AccountMeta::new(delegation, false))
+}
+
+/// Adapts an `Insert`, `Put` or `Delete` instruction `ix` to be signed by
+/// `principal`, holding the matching role, as [`with_delegate`] does with
+/// its ACL entry.
+pub fn with_role(program_id: &Pubkey, ix: Instruction, principal: &Pubkey) -> Instruction {
+    let (acl, _) = find_acl_address(program_id, &ix.accounts[1].pubkey, principal);
+    with_grant(ix, principal, AccountMeta::new_readonly(acl, false))
+}
+
+/// Adapts a `Put` instruction `ix` to be signed by `authority`, controlling
+/// the value, as [`with_delegate`] does with its value authority PDA.
+pub fn with_value_authority(
+    program_id: &Pubkey,
+    ix: Instruction,
+    authority: &Pubkey,
+) -> Instruction {
+    let (record, _) = find_value_authority_address(program_id, &ix.accounts[2].pubkey);
+    with_grant(ix, authority, AccountMeta::new_readonly(record, false))
+}
+
+/// Adapts an `Insert` or `Put` instruction `ix` to be signed by `holder`,
+/// owning `token_account` of the mint gating the table, as [`with_delegate`]
+/// does with its delegation PDA.
+pub fn with_token_account(ix: Instruction, holder: &Pubkey, token_account: &Pubkey) -> Instruction {
+    with_grant(ix, holder, AccountMeta::new_readonly(*token_account, false))
+}
+
+fn with_grant(mut ix: Instruction, signer: &Pubkey, grant: AccountMeta) -> Instruction {
+    ix.accounts[0].is_signer = false;
+    ix.accounts
+        .splice(0..0, [AccountMeta::new(*signer, true), grant]);
+    ix
+}
+
+/// Meta of the ACL entry of `authority` on `table`, needed when it manages
+/// roles as an admin rather than as the `owner`.
+fn admin_meta(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    owner: &Pubkey,
+    table: &Pubkey,
+) -> Option<AccountMeta> {
+    (authority != owner).then(|| {
+        let (acl, _) = find_acl_address(program_id, table, authority);
+        AccountMeta::new_readonly(acl, false)
+    })
+}
+
+/// Metas to push after the accounts of an instruction mutating a table with
+/// the hook `hook`: the hook program followed by the `extra` accounts it
+/// needs.
+pub fn hook_metas(hook: &Pubkey, extra: &[AccountMeta]) -> Vec<AccountMeta> {
+    let mut metas = vec![AccountMeta::new_readonly(*hook, false)];
+    metas.extend_from_slice(extra);
+    metas
+}
+
+/// Meta of the parent value of `reference` through `foreign_key`, to push
+/// after the accounts of an instruction writing to a table that declares it.
+pub fn parent_meta(program_id: &Pubkey, foreign_key: &ForeignKey, reference: &[u8]) -> AccountMeta {
+    let (parent, _) = find_value_address(
+        program_id,
+        &foreign_key.parent,
+        &foreign_key.parent_owner,
+        reference,
+    );
+    AccountMeta::new(parent, false)
+}
+
+/// Meta of the Bloom filter of `table`, to push after the accounts of an
+/// instruction that may create a key in a table with a filter, before the
+/// parent value.
+pub fn bloom_meta(program_id: &Pubkey, table: &Pubkey) -> AccountMeta {
+    let (bloom, _) = find_bloom_address(program_id, table);
+    AccountMeta::new(bloom, false)
+}
+
+/// Meta of the directory page `page` of `table`.
+fn directory_meta(program_id: &Pubkey, table: &Pubkey, page: u32) -> AccountMeta {
+    let (directory, _) = find_directory_address(program_id, table, page);
+    AccountMeta::new(directory, false)
+}
+
+/// Table and slab PDAs shared by the instructions operating on a slab entry.
+struct SlabTarget {
+    table: Pubkey,
+    table_bump: u8,
+    slab: Pubkey,
+}
+
+impl SlabTarget {
+    fn new(program_id: &Pubkey, owner: &Pubkey, name: &str, key: &[u8], slabs: u8) -> Self {
+        let (table, table_bump) = find_table_address(program_id, owner, name);
+        let (slab, _) = find_slab_address(program_id, &table, SolSlab::index_of(key, slabs));
+
+        Self {
+            table,
+            table_bump,
+            slab,
+        }
+    }
+
+    fn metas(&self, owner: &Pubkey) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new_readonly(self.table, false),
+            AccountMeta::new(self.slab, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ]
+    }
+}
+
+/// Table and value PDAs shared by the instructions operating on a value.
+struct ValueTarget {
+    table: Pubkey,
+    table_bump: u8,
+    value: Pubkey,
+    value_bump: u8,
+}
+
+impl ValueTarget {
+    fn new(program_id: &Pubkey, owner: &Pubkey, name: &str, key: &[u8]) -> Self {
+        Self::written_by(program_id, owner, owner, name, key)
+    }
+
+    /// Like `new`, for the value `writer` inserted into the table of `owner`.
+    fn written_by(
+        program_id: &Pubkey,
+        owner: &Pubkey,
+        writer: &Pubkey,
+        name: &str,
+        key: &[u8],
+    ) -> Self {
+        let (table, table_bump) = find_table_address(program_id, owner, name);
+        let (value, value_bump) = find_value_address(program_id, &table, writer, key);
+
+        Self {
+            table,
+            table_bump,
+            value,
+            value_bump,
+        }
+    }
+
+    /// Like `new`, for a global table.
+    fn global(program_id: &Pubkey, owner: &Pubkey, name: &str, key: &[u8]) -> Self {
+        let (table, table_bump) = find_table_address(program_id, owner, name);
+        let (value, value_bump) = find_global_value_address(program_id, &table, key);
+
+        Self {
+            table,
+            table_bump,
+            value,
+            value_bump,
+        }
+    }
+
+    fn metas(&self, owner: &Pubkey) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new_readonly(self.table, false),
+            AccountMeta::new(self.value, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ]
+    }
+
+    /// Like `metas`, for the instructions updating the table.
+    fn table_metas(&self, owner: &Pubkey) -> Vec<AccountMeta> {
+        let mut metas = self.metas(owner);
+        metas[1].is_writable = true;
+        metas
+    }
+
+    /// Relay accounts followed by `table_metas`, for the relayed
+    /// instructions, where `owner` does not sign.
+    fn relayed_metas(
+        &self,
+        program_id: &Pubkey,
+        relayer: &Pubkey,
+        owner: &Pubkey,
+    ) -> Vec<AccountMeta> {
+        let (nonce, _) = find_nonce_address(program_id, owner);
+
+        let mut metas = vec![
+            AccountMeta::new(*relayer, true),
+            AccountMeta::new(nonce, false),
+            AccountMeta::new_readonly(sysvar::instructions::ID, false),
+        ];
+        let mut table_metas = self.table_metas(owner);
+        table_metas[0] = AccountMeta::new_readonly(*owner, false);
+        metas.extend(table_metas);
+        metas
+    }
+
+    /// Like `table_metas`, followed by the directory page `page` holding the
+    /// Merkle leaf of the value.
+    fn committed_metas(&self, program_id: &Pubkey, owner: &Pubkey, page: u32) -> Vec<AccountMeta> {
+        let mut metas = self.table_metas(owner);
+        metas.push(directory_meta(program_id, &self.table, page));
+        metas
+    }
+
+    /// Like `committed_metas`, for the instructions taking the staging
+    /// buffer.
+    fn committed_upload_metas(
+        &self,
+        program_id: &Pubkey,
+        owner: &Pubkey,
+        page: u32,
+    ) -> Vec<AccountMeta> {
+        let mut metas = self.upload_metas(program_id, owner);
+        metas[1].is_writable = true;
+        metas.push(directory_meta(program_id, &self.table, page));
+        metas
+    }
+
+    fn upload_metas(&self, program_id: &Pubkey, owner: &Pubkey) -> Vec<AccountMeta> {
+        let (buffer, _) = find_upload_address(program_id, &self.value);
+
+        vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new_readonly(self.table, false),
+            AccountMeta::new(self.value, false),
+            AccountMeta::new(buffer, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ]
+    }
+}
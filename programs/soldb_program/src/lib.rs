@@ -1,11 +1,18 @@
 #![allow(unexpected_cfgs)]
 
+#[macro_use]
+pub mod macros;
+
 pub mod accounts;
+pub mod contexts;
 pub mod error;
+pub mod events;
+pub mod hook;
 pub mod instructions;
-#[macro_use]
-pub mod macros;
+pub mod merkle;
+pub mod pda;
 pub mod processor;
+pub mod relay;
 
 #[cfg(not(feature = "no-entrypoint"))]
 pub mod entrypoint;
@@ -20,3 +20,77 @@ macro_rules! require_keys_eq {
         }
     };
 }
+
+/// Declares the accounts of an instruction as a struct, in the order they are
+/// passed, together with a `load` constructor checking every constraint.
+///
+/// Each constraint names a function of [`crate::contexts::constraints`],
+/// which is called with the program id and the account. An account followed
+/// by `if` is optional: it is only taken when the condition, which may use
+/// the accounts before it, holds and the account was passed.
+///
+/// ```ignore
+/// context! {
+///     pub struct ExampleContext {
+///         owner: [signer, writable],
+///         table: [owned, table],
+///         parent: [] if has_foreign_key(table)?,
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! context {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[doc = $doc:literal])*
+                $field:ident: [$($constraint:ident),* $(,)?] $(if $cond:expr)?
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name<'a, 'info> {
+            $(
+                $(#[doc = $doc])*
+                pub $field: $crate::context!(@type 'a, 'info $(, $cond)?),
+            )*
+        }
+
+        impl<'a, 'info> $name<'a, 'info> {
+            pub fn load(
+                program_id: &::solana_program::pubkey::Pubkey,
+                accounts: &'a [::solana_program::account_info::AccountInfo<'info>],
+            ) -> $crate::error::Result<Self> {
+                let account_iter = &mut accounts.iter();
+                $(
+                    let $field = $crate::context!(
+                        @load program_id, account_iter, [$($constraint),*] $(, $cond)?
+                    );
+                )*
+
+                Ok(Self { $($field),* })
+            }
+        }
+    };
+    (@type $a:lifetime, $info:lifetime) => {
+        &$a ::solana_program::account_info::AccountInfo<$info>
+    };
+    (@type $a:lifetime, $info:lifetime, $cond:expr) => {
+        ::core::option::Option<&$a ::solana_program::account_info::AccountInfo<$info>>
+    };
+    (@load $program_id:ident, $iter:ident, [$($constraint:ident),*]) => {{
+        let info = ::solana_program::account_info::next_account_info($iter)?;
+        $($crate::contexts::constraints::$constraint($program_id, info)?;)*
+        info
+    }};
+    (@load $program_id:ident, $iter:ident, [$($constraint:ident),*], $cond:expr) => {{
+        let info = if $cond { $iter.next() } else { None };
+        $(
+            if let Some(info) = info {
+                $crate::contexts::constraints::$constraint($program_id, info)?;
+            }
+        )*
+        info
+    }};
+}
@@ -0,0 +1,119 @@
+//! Merkle commitment over the keys and values of a dedicated table.
+//!
+//! Every key listed in the directory has a leaf hashing its writer and the
+//! key with the hash of its value, at the key's directory position, so that
+//! a proof tells whose value it is. Each directory page
+//! is the root of a subtree over its `PAGE_KEYS` entries, free entries being
+//! `EMPTY`, and the table root is the tree over its page roots. Levels with
+//! an odd number of nodes pair the last one with `EMPTY`.
+//!
+//! Compressed tables use the same nodes in a tree of fixed depth, whose
+//! missing subtrees are the roots of empty subtrees instead. Only their owner
+//! writes to them, so their leaves hash the key and value alone.
+
+use solana_program::{
+    hash::{hash, hashv},
+    pubkey::Pubkey,
+};
+
+use crate::accounts::SolDirectory;
+
+/// Leaf of a free directory entry, and root of a table without keys.
+pub const EMPTY: [u8; 32] = [0; 32];
+
+/// Number of levels of the subtree of a directory page.
+pub const PAGE_DEPTH: usize = SolDirectory::PAGE_KEYS.trailing_zeros() as usize;
+
+const LEAF_PREFIX: &[u8] = &[0];
+const NODE_PREFIX: &[u8] = &[1];
+const ENTRY_PREFIX: &[u8] = &[2];
+
+/// Leaf of the directory entry of `key` of `writer` holding `value`.
+pub fn entry_leaf(writer: &Pubkey, key: &[u8], value: &[u8]) -> [u8; 32] {
+    entry_leaf_from_hash(writer, key, &hash(value).to_bytes())
+}
+
+/// Leaf of the directory entry of `key` of `writer` holding a value hashing
+/// to `value_hash`.
+pub fn entry_leaf_from_hash(writer: &Pubkey, key: &[u8], value_hash: &[u8; 32]) -> [u8; 32] {
+    hashv(&[
+        ENTRY_PREFIX,
+        writer.as_ref(),
+        &[key.len() as u8],
+        key,
+        value_hash,
+    ])
+    .to_bytes()
+}
+
+/// Leaf of `key` holding `value` in a compressed table.
+pub fn leaf(key: &[u8], value: &[u8]) -> [u8; 32] {
+    leaf_from_hash(key, &hash(value).to_bytes())
+}
+
+/// Leaf of `key` holding a value hashing to `value_hash` in a compressed
+/// table.
+pub fn leaf_from_hash(key: &[u8], value_hash: &[u8; 32]) -> [u8; 32] {
+    hashv(&[LEAF_PREFIX, &[key.len() as u8], key, value_hash]).to_bytes()
+}
+
+/// Parent of `left` and `right`.
+pub fn node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    hashv(&[NODE_PREFIX, left, right]).to_bytes()
+}
+
+/// Root of the tree over `nodes`.
+pub fn root(nodes: &[[u8; 32]]) -> [u8; 32] {
+    let mut level = nodes.to_vec();
+    while level.len() > 1 {
+        level = parents(&level);
+    }
+    level.first().copied().unwrap_or(EMPTY)
+}
+
+/// Root of a subtree of `level` levels without leaves, used by the
+/// fixed-depth trees of compressed tables.
+pub fn empty_subtree(level: usize) -> [u8; 32] {
+    (0..level).fold(EMPTY, |node, _| self::node(&node, &node))
+}
+
+/// Number of levels of the tree over `count` nodes, which is the length of
+/// their proofs.
+pub fn depth(count: usize) -> usize {
+    count.next_power_of_two().trailing_zeros() as usize
+}
+
+/// Siblings of the node at `index` from the bottom of the tree over `nodes`
+/// up to its root.
+pub fn proof(nodes: &[[u8; 32]], index: usize) -> Vec<[u8; 32]> {
+    let mut siblings = Vec::new();
+    let mut level = nodes.to_vec();
+    let mut index = index;
+    while level.len() > 1 {
+        siblings.push(level.get(index ^ 1).copied().unwrap_or(EMPTY));
+        level = parents(&level);
+        index /= 2;
+    }
+    siblings
+}
+
+/// Recomputes the root from the node at `index` and its `proof`.
+pub fn fold(leaf: [u8; 32], index: u32, proof: &[[u8; 32]]) -> [u8; 32] {
+    proof
+        .iter()
+        .enumerate()
+        .fold(leaf, |acc, (level, sibling)| {
+            if index.checked_shr(level as u32).unwrap_or(0) & 1 == 0 {
+                node(&acc, sibling)
+            } else {
+                node(sibling, &acc)
+            }
+        })
+}
+
+fn parents(level: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    level
+        .chunks(2)
+        .map(|pair| node(&pair[0], pair.get(1).unwrap_or(&EMPTY)))
+        .collect()
+}
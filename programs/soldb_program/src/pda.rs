@@ -0,0 +1,225 @@
+use solana_program::pubkey::{Pubkey, PubkeyError};
+
+use crate::accounts::{
+    ACL_SEED, BLOOM_SEED, DELEGATION_SEED, DIRECTORY_SEED, MULTISIG_SEED, NONCE_SEED, SLAB_SEED,
+    TREE_SEED, UPLOAD_SEED, VALUE_AUTHORITY_SEED,
+};
+
+/// Derives the table PDA from `[name, owner]`.
+pub fn find_table_address(program_id: &Pubkey, owner: &Pubkey, name: &str) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[name.as_bytes(), owner.as_ref()], program_id)
+}
+
+/// Derives the key-value PDA from `[key, table, owner]`.
+pub fn find_value_address(
+    program_id: &Pubkey,
+    table: &Pubkey,
+    owner: &Pubkey,
+    key: &[u8],
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[key, table.as_ref(), owner.as_ref()], program_id)
+}
+
+/// Derives the key-value PDA of a global table from `[key, table]`.
+pub fn find_global_value_address(program_id: &Pubkey, table: &Pubkey, key: &[u8]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[key, table.as_ref()], program_id)
+}
+
+/// Derives the staging buffer PDA of a value from `["upload", value]`.
+pub fn find_upload_address(program_id: &Pubkey, value: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[UPLOAD_SEED, value.as_ref()], program_id)
+}
+
+/// Derives the slab PDA of a packed table from `["slab", table, index]`.
+pub fn find_slab_address(program_id: &Pubkey, table: &Pubkey, index: u8) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[SLAB_SEED, table.as_ref(), &[index]], program_id)
+}
+
+/// Derives a directory page PDA of a table from `["dir", table, page]`.
+pub fn find_directory_address(program_id: &Pubkey, table: &Pubkey, page: u32) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[DIRECTORY_SEED, table.as_ref(), &page.to_le_bytes()],
+        program_id,
+    )
+}
+
+/// Derives the Merkle tree PDA of a compressed table from `["tree", table]`.
+pub fn find_tree_address(program_id: &Pubkey, table: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[TREE_SEED, table.as_ref()], program_id)
+}
+
+/// Derives the Bloom filter PDA of a table from `["bloom", table]`.
+pub fn find_bloom_address(program_id: &Pubkey, table: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[BLOOM_SEED, table.as_ref()], program_id)
+}
+
+/// Derives the relay nonce PDA of a signer from `["nonce", signer]`.
+pub fn find_nonce_address(program_id: &Pubkey, signer: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[NONCE_SEED, signer.as_ref()], program_id)
+}
+
+/// Derives the multisig PDA `name` of `creator` from
+/// `["multisig", creator, name]`.
+pub fn find_multisig_address(program_id: &Pubkey, creator: &Pubkey, name: &str) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[MULTISIG_SEED, creator.as_ref(), name.as_bytes()],
+        program_id,
+    )
+}
+
+/// Derives the delegation PDA of `delegate` on a table from
+/// `["delegation", table, delegate]`.
+pub fn find_delegation_address(
+    program_id: &Pubkey,
+    table: &Pubkey,
+    delegate: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[DELEGATION_SEED, table.as_ref(), delegate.as_ref()],
+        program_id,
+    )
+}
+
+/// Derives the ACL entry PDA of `principal` on a table from
+/// `["acl", table, principal]`.
+pub fn find_acl_address(program_id: &Pubkey, table: &Pubkey, principal: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[ACL_SEED, table.as_ref(), principal.as_ref()], program_id)
+}
+
+/// Derives the PDA naming the authority of a value from
+/// `["authority", value]`.
+pub fn find_value_authority_address(program_id: &Pubkey, value: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[VALUE_AUTHORITY_SEED, value.as_ref()], program_id)
+}
+
+/// Recomputes the table PDA from a known bump, skipping the bump search.
+pub fn create_table_address(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    bump: u8,
+) -> Result<Pubkey, PubkeyError> {
+    Pubkey::create_program_address(&[name.as_bytes(), owner.as_ref(), &[bump]], program_id)
+}
+
+/// Recomputes the key-value PDA from a known bump, skipping the bump search.
+pub fn create_value_address(
+    program_id: &Pubkey,
+    table: &Pubkey,
+    owner: &Pubkey,
+    key: &[u8],
+    bump: u8,
+) -> Result<Pubkey, PubkeyError> {
+    Pubkey::create_program_address(&[key, table.as_ref(), owner.as_ref(), &[bump]], program_id)
+}
+
+/// Recomputes the key-value PDA of a global table from a known bump,
+/// skipping the bump search.
+pub fn create_global_value_address(
+    program_id: &Pubkey,
+    table: &Pubkey,
+    key: &[u8],
+    bump: u8,
+) -> Result<Pubkey, PubkeyError> {
+    Pubkey::create_program_address(&[key, table.as_ref(), &[bump]], program_id)
+}
+
+/// Recomputes the staging buffer PDA from a known bump, skipping the bump
+/// search.
+pub fn create_upload_address(
+    program_id: &Pubkey,
+    value: &Pubkey,
+    bump: u8,
+) -> Result<Pubkey, PubkeyError> {
+    Pubkey::create_program_address(&[UPLOAD_SEED, value.as_ref(), &[bump]], program_id)
+}
+
+/// Recomputes the slab PDA from a known bump, skipping the bump search.
+pub fn create_slab_address(
+    program_id: &Pubkey,
+    table: &Pubkey,
+    index: u8,
+    bump: u8,
+) -> Result<Pubkey, PubkeyError> {
+    Pubkey::create_program_address(&[SLAB_SEED, table.as_ref(), &[index], &[bump]], program_id)
+}
+
+/// Recomputes a directory page PDA from a known bump, skipping the bump
+/// search.
+pub fn create_directory_address(
+    program_id: &Pubkey,
+    table: &Pubkey,
+    page: u32,
+    bump: u8,
+) -> Result<Pubkey, PubkeyError> {
+    Pubkey::create_program_address(
+        &[DIRECTORY_SEED, table.as_ref(), &page.to_le_bytes(), &[bump]],
+        program_id,
+    )
+}
+
+/// Recomputes the Merkle tree PDA from a known bump, skipping the bump
+/// search.
+pub fn create_tree_address(
+    program_id: &Pubkey,
+    table: &Pubkey,
+    bump: u8,
+) -> Result<Pubkey, PubkeyError> {
+    Pubkey::create_program_address(&[TREE_SEED, table.as_ref(), &[bump]], program_id)
+}
+
+/// Recomputes the Bloom filter PDA from a known bump, skipping the bump
+/// search.
+pub fn create_bloom_address(
+    program_id: &Pubkey,
+    table: &Pubkey,
+    bump: u8,
+) -> Result<Pubkey, PubkeyError> {
+    Pubkey::create_program_address(&[BLOOM_SEED, table.as_ref(), &[bump]], program_id)
+}
+
+/// Recomputes the relay nonce PDA from a known bump, skipping the bump
+/// search.
+pub fn create_nonce_address(
+    program_id: &Pubkey,
+    signer: &Pubkey,
+    bump: u8,
+) -> Result<Pubkey, PubkeyError> {
+    Pubkey::create_program_address(&[NONCE_SEED, signer.as_ref(), &[bump]], program_id)
+}
+
+/// Recomputes a delegation PDA from a known bump, skipping the bump search.
+pub fn create_delegation_address(
+    program_id: &Pubkey,
+    table: &Pubkey,
+    delegate: &Pubkey,
+    bump: u8,
+) -> Result<Pubkey, PubkeyError> {
+    Pubkey::create_program_address(
+        &[DELEGATION_SEED, table.as_ref(), delegate.as_ref(), &[bump]],
+        program_id,
+    )
+}
+
+/// Recomputes an ACL entry PDA from a known bump, skipping the bump search.
+pub fn create_acl_address(
+    program_id: &Pubkey,
+    table: &Pubkey,
+    principal: &Pubkey,
+    bump: u8,
+) -> Result<Pubkey, PubkeyError> {
+    Pubkey::create_program_address(
+        &[ACL_SEED, table.as_ref(), principal.as_ref(), &[bump]],
+        program_id,
+    )
+}
+
+/// Recomputes a value authority PDA from a known bump, skipping the bump
+/// search.
+pub fn create_value_authority_address(
+    program_id: &Pubkey,
+    value: &Pubkey,
+    bump: u8,
+) -> Result<Pubkey, PubkeyError> {
+    Pubkey::create_program_address(&[VALUE_AUTHORITY_SEED, value.as_ref(), &[bump]], program_id)
+}
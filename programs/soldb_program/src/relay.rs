@@ -0,0 +1,131 @@
+//! Writes relayed on behalf of table owners who hold no SOL.
+//!
+//! The owner signs a `RelayedMessage` off-chain, and a relayer submits the
+//! signature in an ed25519 program instruction right before the relayed
+//! instruction, which the relayer signs and pays for. The program reads the
+//! ed25519 instruction back from the instructions sysvar to check that the
+//! verified signature is the owner's, over the message the relayed
+//! instruction carries out.
+//!
+//! Every message holds the relay nonce of its signer, stored in the PDA
+//! `["nonce", signer]`, so that each signature is used at most once, and an
+//! expiry after which it is rejected.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_instructions_sysvar::{load_current_index_checked, load_instruction_at_checked};
+use solana_program::{
+    account_info::AccountInfo, ed25519_program, entrypoint::ProgramResult,
+    instruction::Instruction, pubkey::Pubkey,
+};
+use solana_program_error::ToStr;
+
+use crate::{
+    error::{Result, SolDbError},
+    hook::MutationKind,
+};
+
+/// Prefix of every signed message, so that the signature of a relayed write
+/// cannot be mistaken for one made for another purpose.
+pub const DOMAIN: &[u8] = b"soldb:relay";
+
+const SIGNATURE_OFFSETS_START: usize = 2;
+const SIGNATURE_OFFSETS_LEN: usize = 14;
+const DATA_START: usize = SIGNATURE_OFFSETS_START + SIGNATURE_OFFSETS_LEN;
+const PUBKEY_LEN: usize = 32;
+const SIGNATURE_LEN: usize = 64;
+/// Instruction index the ed25519 program reads as the instruction itself.
+const CURRENT_INSTRUCTION: u16 = u16::MAX;
+
+/// Write an owner authorizes a relayer to submit.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Eq, PartialEq)]
+pub struct RelayedMessage {
+    /// `Insert`, `Put` or `Delete`, matching the relayed instruction.
+    pub kind: MutationKind,
+    pub table: Pubkey,
+    pub key: Vec<u8>,
+    /// Payload written by the instruction, empty for a `Delete`.
+    pub payload: Vec<u8>,
+    /// Relay nonce of the owner when signing.
+    pub nonce: u64,
+    /// Unix timestamp after which the message is rejected.
+    pub expiry: i64,
+}
+
+impl RelayedMessage {
+    /// Bytes the owner signs: `DOMAIN` followed by the Borsh encoding of the
+    /// message.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = DOMAIN.to_vec();
+        self.serialize(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+/// Checks that the instruction before the current one is an ed25519 program
+/// instruction verifying a single signature of `signer` over `message`, all
+/// of it held in the instruction data.
+pub fn verify(instructions_info: &AccountInfo, signer: &Pubkey, message: &[u8]) -> ProgramResult {
+    let current = load_current_index_checked(instructions_info)?;
+    require!(current > 0, SolDbError::InvalidSignature);
+    let ix = load_instruction_at_checked(current as usize - 1, instructions_info)?;
+
+    require!(
+        ix.program_id == ed25519_program::ID && ix.data.len() >= DATA_START && ix.data[0] == 1,
+        SolDbError::InvalidSignature
+    );
+
+    // Fields of the single `Ed25519SignatureOffsets`, in order: signature
+    // offset and instruction, public key offset and instruction, message
+    // offset, size and instruction.
+    let field = |index: usize| {
+        let at = SIGNATURE_OFFSETS_START + 2 * index;
+        u16::from_le_bytes([ix.data[at], ix.data[at + 1]])
+    };
+    require!(
+        [field(1), field(3), field(6)]
+            .iter()
+            .all(|index| *index == CURRENT_INSTRUCTION),
+        SolDbError::InvalidSignature
+    );
+
+    let pubkey_offset = field(2) as usize;
+    let message_offset = field(4) as usize;
+    let message_len = field(5) as usize;
+    require!(
+        ix.data.get(pubkey_offset..pubkey_offset + PUBKEY_LEN) == Some(signer.as_ref())
+            && ix.data.get(message_offset..message_offset + message_len) == Some(message),
+        SolDbError::InvalidSignature
+    );
+
+    Ok(())
+}
+
+/// Builds the ed25519 program instruction verifying `signature` of `signer`
+/// over `message`, to place right before a relayed instruction.
+pub fn signature_instruction(
+    signer: &Pubkey,
+    signature: &[u8; SIGNATURE_LEN],
+    message: &[u8],
+) -> Instruction {
+    let pubkey_offset = DATA_START;
+    let signature_offset = pubkey_offset + PUBKEY_LEN;
+    let message_offset = signature_offset + SIGNATURE_LEN;
+
+    let mut data = vec![1, 0];
+    for offset in [
+        signature_offset,
+        CURRENT_INSTRUCTION as usize,
+        pubkey_offset,
+        CURRENT_INSTRUCTION as usize,
+        message_offset,
+        message.len(),
+        CURRENT_INSTRUCTION as usize,
+    ] {
+        data.extend_from_slice(&(offset as u16).to_le_bytes());
+    }
+    data.extend_from_slice(signer.as_ref());
+    data.extend_from_slice(signature);
+    data.extend_from_slice(message);
+
+    Instruction::new_with_bytes(ed25519_program::ID, &data, vec![])
+}
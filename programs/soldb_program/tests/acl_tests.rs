@@ -0,0 +1,187 @@
+mod utils;
+
+use solana_program_test::*;
+use solana_sdk::{
+    hash::Hash, instruction::Instruction, signature::Keypair, signer::Signer,
+    transport::TransportError,
+};
+use solana_system_interface::instruction::transfer;
+use soldb_program::{
+    accounts::{SolAcl, SolValue},
+    error::SolDbError,
+    id as program_id, instructions,
+    pda::{find_acl_address, find_value_address},
+};
+use utils::{process, setup};
+
+/// Creates a collaborator holding enough lamports to pay for its writes.
+async fn collaborator(
+    banks_client: &BanksClient,
+    payer: &Keypair,
+    last_blockhash: Hash,
+) -> Result<Keypair, TransportError> {
+    let collaborator = Keypair::new();
+    let fund = transfer(&payer.pubkey(), &collaborator.pubkey(), 1_000_000_000);
+    process(banks_client, payer, last_blockhash, fund).await?;
+    Ok(collaborator)
+}
+
+/// Processes `ix`, built for the owner, signed by `principal` alone.
+async fn as_principal(
+    banks_client: &BanksClient,
+    principal: &Keypair,
+    last_blockhash: Hash,
+    ix: Instruction,
+) -> Result<(), TransportError> {
+    let ix = instructions::with_role(&program_id(), ix, &principal.pubkey());
+    process(banks_client, principal, last_blockhash, ix).await
+}
+
+#[tokio::test]
+async fn test_roles_gate_writes() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let (table, _) = utils::init_table(&banks_client, &payer, last_blockhash, "Test").await?;
+    let writer = collaborator(&banks_client, &payer, last_blockhash).await?;
+    let grant = |roles| {
+        instructions::grant_roles(&program_id, &owner, &owner, "Test", &writer.pubkey(), roles)
+    };
+    process(
+        &banks_client,
+        &payer,
+        last_blockhash,
+        grant(SolAcl::INSERTER),
+    )
+    .await?;
+
+    let ix = instructions::insert(&program_id, &table, &owner, b"k-0", b"v-0", 0);
+    as_principal(&banks_client, &writer, last_blockhash, ix).await?;
+
+    // The key lives in the namespace of the owner.
+    let (value, _) = find_value_address(&program_id, &table, &owner, b"k-0");
+    let account = banks_client.get_account(value).await?.unwrap();
+    assert_eq!(SolValue::payload(&account.data).unwrap(), b"v-0");
+
+    let ix = instructions::put(&program_id, &owner, "Test", b"k-0", b"v-1", 0);
+    let err = as_principal(&banks_client, &writer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::MissingRole));
+
+    process(
+        &banks_client,
+        &payer,
+        last_blockhash,
+        grant(SolAcl::UPDATER),
+    )
+    .await?;
+    let ix = instructions::put(&program_id, &owner, "Test", b"k-0", b"v-2", 0);
+    as_principal(&banks_client, &writer, last_blockhash, ix).await?;
+
+    let account = banks_client.get_account(value).await?.unwrap();
+    assert_eq!(SolValue::payload(&account.data).unwrap(), b"v-2");
+
+    let (acl, _) = find_acl_address(&program_id, &table, &writer.pubkey());
+    let account = banks_client.get_account(acl).await?.unwrap();
+    assert_eq!(
+        SolAcl::load(&account.data).unwrap().roles,
+        SolAcl::INSERTER | SolAcl::UPDATER
+    );
+
+    let ix = instructions::delete(&program_id, &owner, "Test", b"k-0", 0, 1);
+    let err = as_principal(&banks_client, &writer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::MissingRole));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_admin_manages_roles() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let (table, _) = utils::init_table(&banks_client, &payer, last_blockhash, "Test").await?;
+    utils::insert(
+        &banks_client,
+        &payer,
+        last_blockhash,
+        &table,
+        b"k-0".to_vec(),
+        b"v-0",
+    )
+    .await?;
+    let admin = collaborator(&banks_client, &payer, last_blockhash).await?;
+    let writer = collaborator(&banks_client, &payer, last_blockhash).await?;
+
+    let ix = instructions::grant_roles(
+        &program_id,
+        &owner,
+        &owner,
+        "Test",
+        &admin.pubkey(),
+        SolAcl::ADMIN,
+    );
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    // Only the owner and admins manage roles.
+    let ix = instructions::grant_roles(
+        &program_id,
+        &writer.pubkey(),
+        &owner,
+        "Test",
+        &writer.pubkey(),
+        SolAcl::DELETER,
+    );
+    let err = process(&banks_client, &writer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::MissingRole));
+
+    let ix = instructions::grant_roles(
+        &program_id,
+        &admin.pubkey(),
+        &owner,
+        "Test",
+        &writer.pubkey(),
+        0,
+    );
+    let err = process(&banks_client, &admin, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::InvalidRoles));
+
+    let ix = instructions::grant_roles(
+        &program_id,
+        &admin.pubkey(),
+        &owner,
+        "Test",
+        &writer.pubkey(),
+        SolAcl::DELETER,
+    );
+    process(&banks_client, &admin, last_blockhash, ix).await?;
+
+    let ix = instructions::delete(&program_id, &owner, "Test", b"k-0", 0, 1);
+    as_principal(&banks_client, &writer, last_blockhash, ix).await?;
+    assert_eq!(utils::table_keys(&banks_client, &table).await?, 0);
+
+    // Revoking the last role closes the entry.
+    let ix = instructions::revoke_roles(
+        &program_id,
+        &admin.pubkey(),
+        &owner,
+        "Test",
+        &writer.pubkey(),
+        SolAcl::DELETER,
+    );
+    process(&banks_client, &admin, last_blockhash, ix).await?;
+
+    let (acl, _) = find_acl_address(&program_id, &table, &writer.pubkey());
+    assert!(banks_client.get_account(acl).await?.is_none());
+
+    Ok(())
+}
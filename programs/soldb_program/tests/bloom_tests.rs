@@ -0,0 +1,160 @@
+mod utils;
+
+use solana_program_test::*;
+use solana_sdk::{
+    hash::Hash, pubkey::Pubkey, signature::Keypair, signer::Signer, transaction::Transaction,
+    transport::TransportError,
+};
+use soldb_program::{
+    accounts::SolBloom,
+    error::SolDbError,
+    id as program_id, instructions,
+    pda::{find_bloom_address, find_directory_address, find_slab_address, find_table_address},
+};
+use utils::{process, setup};
+
+const BITS: u32 = 1024;
+const SLABS: u8 = 4;
+
+fn key(i: usize) -> Vec<u8> {
+    format!("k-{i}").into_bytes()
+}
+
+/// Simulates `MaybeContains`. The runtime drops trailing zeros from the
+/// return data, so a `[0]` answer comes back empty.
+async fn maybe_contains(
+    banks_client: &BanksClient,
+    payer: &Keypair,
+    last_blockhash: Hash,
+    table: &Pubkey,
+    key: &[u8],
+) -> Result<bool, TransportError> {
+    let ix = instructions::maybe_contains(&program_id(), table, key);
+    let txn =
+        Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[payer], last_blockhash);
+
+    let simulation = banks_client.simulate_transaction(txn).await?;
+    simulation.result.unwrap()?;
+    let return_data = simulation.simulation_details.unwrap().return_data;
+
+    Ok(return_data.is_some_and(|return_data| return_data.data == [1]))
+}
+
+#[tokio::test]
+async fn test_bloom_tracks_inserted_keys() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let (table, _) = utils::init_table(&banks_client, &payer, last_blockhash, "Test").await?;
+    for i in 0..4 {
+        utils::insert(&banks_client, &payer, last_blockhash, &table, key(i), b"v").await?;
+    }
+
+    // Without a filter every key may exist.
+    assert!(maybe_contains(&banks_client, &payer, last_blockhash, &table, b"missing").await?);
+
+    let ix = instructions::init_bloom(&program_id, &owner, "Test", BITS, 4);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    // Keys inserted before the filter was created are not in it yet.
+    assert!(!maybe_contains(&banks_client, &payer, last_blockhash, &table, &key(0)).await?);
+
+    // Once the table has a filter, the writes creating a key pass it.
+    let ix = instructions::insert(&program_id, &table, &owner, &key(4), b"v", 0);
+    assert!(
+        process(&banks_client, &payer, last_blockhash, ix)
+            .await
+            .is_err()
+    );
+
+    let mut ix = instructions::insert(&program_id, &table, &owner, &key(4), b"v", 0);
+    ix.accounts
+        .push(instructions::bloom_meta(&program_id, &table));
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    assert!(maybe_contains(&banks_client, &payer, last_blockhash, &table, &key(4)).await?);
+
+    let (page, _) = find_directory_address(&program_id, &table, 0);
+    let ix = instructions::rebuild_bloom(&program_id, &owner, "Test", true, &[page]);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    for i in 0..5 {
+        assert!(maybe_contains(&banks_client, &payer, last_blockhash, &table, &key(i)).await?);
+    }
+    assert!(!maybe_contains(&banks_client, &payer, last_blockhash, &table, b"missing").await?);
+
+    let (bloom, _) = find_bloom_address(&program_id, &table);
+    let account = banks_client.get_account(bloom).await?.unwrap();
+    assert_eq!(SolBloom::load(&account.data).unwrap().count, 5);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bloom_of_packed_table() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let ix = instructions::init_packed_table(&program_id, &owner, "Test", SLABS);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    let (table, _) = find_table_address(&program_id, &owner, "Test");
+
+    let ix = instructions::packed_insert(&program_id, &owner, "Test", &key(0), b"v", SLABS);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let ix = instructions::init_bloom(&program_id, &owner, "Test", BITS, 4);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let mut ix = instructions::packed_insert(&program_id, &owner, "Test", &key(1), b"v", SLABS);
+    ix.accounts
+        .push(instructions::bloom_meta(&program_id, &table));
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    assert!(maybe_contains(&banks_client, &payer, last_blockhash, &table, &key(1)).await?);
+
+    let slabs: Vec<Pubkey> = (0..SLABS)
+        .map(|index| find_slab_address(&program_id, &table, index).0)
+        .collect();
+    let ix = instructions::rebuild_bloom(&program_id, &owner, "Test", false, &slabs);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    assert!(maybe_contains(&banks_client, &payer, last_blockhash, &table, &key(0)).await?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bloom_parameters_are_checked() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    utils::init_table(&banks_client, &payer, last_blockhash, "Test").await?;
+
+    for (bits, hashes) in [
+        (0, 4),
+        (BITS + 4, 4),
+        (SolBloom::MAX_BITS + 8, 4),
+        (BITS, 0),
+        (BITS, SolBloom::MAX_HASHES + 1),
+    ] {
+        let ix = instructions::init_bloom(&program_id, &owner, "Test", bits, hashes);
+        let err = process(&banks_client, &payer, last_blockhash, ix)
+            .await
+            .unwrap_err();
+        assert_eq!(utils::custom_error(err), Some(SolDbError::InvalidBloom));
+    }
+
+    // The filter of a table is checked against its PDA.
+    let (other, _) = utils::init_table(&banks_client, &payer, last_blockhash, "Other").await?;
+    let ix = instructions::init_bloom(&program_id, &owner, "Other", BITS, 4);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let mut ix = instructions::rebuild_bloom(&program_id, &owner, "Test", true, &[]);
+    ix.accounts[2].pubkey = find_bloom_address(&program_id, &other).0;
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::PdaMismatch));
+
+    Ok(())
+}
@@ -0,0 +1,82 @@
+mod utils;
+
+use solana_program_test::*;
+use solana_sdk::{pubkey::Pubkey, signer::Signer, transport::TransportError};
+use soldb_program::{
+    accounts::{SolTable, SolUpload, SolValue},
+    id as program_id, instructions,
+    pda::{find_table_address, find_upload_address, find_value_address},
+};
+use utils::{process, setup};
+
+async fn read_value(banks_client: &BanksClient, value: &Pubkey) -> Result<Vec<u8>, TransportError> {
+    let account = banks_client.get_account(*value).await?.unwrap();
+    Ok(SolValue::payload(&account.data).unwrap().to_vec())
+}
+
+/// Runs every builder through the processor, so that a builder producing the
+/// wrong accounts, flags or PDAs fails here instead of in client code.
+#[tokio::test]
+async fn test_builders_match_processor() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let (table, _) = find_table_address(&program_id, &owner, "Test");
+    let (value, _) = find_value_address(&program_id, &table, &owner, b"k-0");
+    let (buffer, _) = find_upload_address(&program_id, &value);
+
+    let ix = instructions::init_table(&program_id, &owner, "Test");
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    let account = banks_client.get_account(table).await?.unwrap();
+    let sol_table = SolTable::load(&account.data).unwrap();
+    assert_eq!(sol_table.name, "Test");
+
+    let ix = instructions::insert(&program_id, &table, &owner, b"k-0", b"v-0", 0);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    assert_eq!(read_value(&banks_client, &value).await?, b"v-0");
+
+    let ix = instructions::put(&program_id, &owner, "Test", b"k-0", b"value-1", 0);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    assert_eq!(read_value(&banks_client, &value).await?, b"value-1");
+
+    let ix = instructions::patch(&program_id, &owner, "Test", b"k-0", 0, b"V", 0);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    assert_eq!(read_value(&banks_client, &value).await?, b"Value-1");
+
+    let ix = instructions::append(&program_id, &owner, "Test", b"k-0", b"!", 0);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    assert_eq!(read_value(&banks_client, &value).await?, b"Value-1!");
+
+    let ix = instructions::truncate(&program_id, &owner, "Test", b"k-0", 5, 0);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    assert_eq!(read_value(&banks_client, &value).await?, b"Value");
+
+    let payload = b"uploaded value";
+    let digest = SolUpload::chain(&[0; 32], 0, payload);
+    let ix = instructions::open_upload(
+        &program_id,
+        &owner,
+        "Test",
+        b"k-0",
+        payload.len() as u32,
+        digest,
+        1,
+    );
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    assert!(banks_client.get_account(buffer).await?.is_some());
+
+    let ix = instructions::write_chunk(&program_id, &owner, "Test", b"k-0", 0, payload);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let ix = instructions::commit_upload(&program_id, &owner, "Test", b"k-0", 0);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    assert_eq!(read_value(&banks_client, &value).await?, payload);
+    assert!(banks_client.get_account(buffer).await?.is_none());
+
+    let ix = instructions::delete(&program_id, &owner, "Test", b"k-0", 0, 1);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    assert!(banks_client.get_account(value).await?.is_none());
+
+    Ok(())
+}
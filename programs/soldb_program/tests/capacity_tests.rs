@@ -0,0 +1,92 @@
+mod utils;
+
+use solana_program_test::*;
+use solana_sdk::{pubkey::Pubkey, signer::Signer, transport::TransportError};
+use soldb_program::{accounts::SolValue, id as program_id, instructions, pda::find_value_address};
+use utils::{process, setup};
+
+async fn lamports(banks_client: &BanksClient, account: &Pubkey) -> Result<u64, TransportError> {
+    Ok(banks_client.get_account(*account).await?.unwrap().lamports)
+}
+
+#[tokio::test]
+async fn test_put_within_capacity_stays_in_place() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let (table, _) = utils::init_table(&banks_client, &payer, last_blockhash, "Test").await?;
+    let (value, _) = find_value_address(&program_id, &table, &owner, b"k-0");
+
+    let ix =
+        instructions::insert_with_capacity(&program_id, &table, &owner, b"k-0", b"v-0", 256, 0);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let account = banks_client.get_account(value).await?.unwrap();
+    assert_eq!(account.data.len(), SolValue::space(256));
+    assert_eq!(SolValue::payload(&account.data).unwrap(), b"v-0");
+
+    for payload in [vec![1; 200], vec![2; 256], vec![3; 10]] {
+        let value_before = lamports(&banks_client, &value).await?;
+
+        let ix = instructions::put(&program_id, &owner, "Test", b"k-0", &payload, 0);
+        process(&banks_client, &payer, last_blockhash, ix).await?;
+
+        let account = banks_client.get_account(value).await?.unwrap();
+        assert_eq!(SolValue::payload(&account.data).unwrap(), payload);
+        assert_eq!(
+            account.data.len(),
+            SolValue::space(256),
+            "value was resized"
+        );
+        assert_eq!(account.lamports, value_before, "lamports were moved");
+    }
+
+    let ix = instructions::put(&program_id, &owner, "Test", b"k-0", &[4; 300], 0);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let account = banks_client.get_account(value).await?.unwrap();
+    assert_eq!(account.data.len(), SolValue::space(300));
+
+    let ix = instructions::truncate(&program_id, &owner, "Test", b"k-0", 5, 0);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let account = banks_client.get_account(value).await?.unwrap();
+    assert_eq!(
+        account.data.len(),
+        SolValue::space(256),
+        "value must not shrink below its capacity"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_upsert() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let (table, _) = utils::init_table(&banks_client, &payer, last_blockhash, "Test").await?;
+    let (value, _) = find_value_address(&program_id, &table, &owner, b"k-0");
+
+    let ix = instructions::upsert(&program_id, &owner, "Test", b"k-0", b"v-0", 64, 0);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let account = banks_client.get_account(value).await?.unwrap();
+    assert_eq!(SolValue::payload(&account.data).unwrap(), b"v-0");
+    assert_eq!(account.data.len(), SolValue::space(64));
+
+    let ix = instructions::upsert(&program_id, &owner, "Test", b"k-0", b"value-1", 0, 0);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let account = banks_client.get_account(value).await?.unwrap();
+    assert_eq!(SolValue::payload(&account.data).unwrap(), b"value-1");
+    assert_eq!(
+        account.data.len(),
+        SolValue::space(64),
+        "capacity is only set on creation"
+    );
+
+    Ok(())
+}
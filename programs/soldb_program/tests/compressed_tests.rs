@@ -0,0 +1,149 @@
+mod utils;
+
+use solana_program_test::*;
+use solana_sdk::{hash::hash, pubkey::Pubkey, signer::Signer, transport::TransportError};
+use soldb_program::{
+    accounts::SolTree,
+    error::SolDbError,
+    id as program_id, instructions,
+    merkle::{self, EMPTY, empty_subtree, node},
+    pda::{find_table_address, find_tree_address},
+};
+use utils::{process, setup};
+
+const DEPTH: u8 = 3;
+
+async fn tree_root(banks_client: &BanksClient, table: &Pubkey) -> Result<[u8; 32], TransportError> {
+    let (tree, _) = find_tree_address(&program_id(), table);
+    let account = banks_client.get_account(tree).await?.unwrap();
+    Ok(SolTree::root(&account.data).unwrap())
+}
+
+fn empty_proof() -> Vec<[u8; 32]> {
+    (0..DEPTH as usize).map(empty_subtree).collect()
+}
+
+#[tokio::test]
+async fn test_compressed_writes() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let ix = instructions::init_compressed_table(&program_id, &owner, "Test", DEPTH);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    let (table, _) = find_table_address(&program_id, &owner, "Test");
+
+    let empty_root = empty_subtree(DEPTH as usize);
+    assert_eq!(tree_root(&banks_client, &table).await?, empty_root);
+
+    // Both writes prove their free leaf against the empty tree, the second
+    // one being fast-forwarded over the first.
+    for (index, key) in [(0, b"k-0"), (1, b"k-1")] {
+        let ix = instructions::compressed_put(
+            &program_id,
+            &owner,
+            "Test",
+            key,
+            b"v",
+            None,
+            empty_root,
+            index,
+            empty_proof(),
+        );
+        process(&banks_client, &payer, last_blockhash, ix).await?;
+    }
+
+    let leaf_0 = merkle::leaf(b"k-0", b"v");
+    let leaf_1 = merkle::leaf(b"k-1", b"v");
+    let root = node(
+        &node(&node(&leaf_0, &leaf_1), &empty_subtree(1)),
+        &empty_subtree(2),
+    );
+    assert_eq!(tree_root(&banks_client, &table).await?, root);
+
+    let ix = instructions::compressed_delete(
+        &program_id,
+        &owner,
+        "Test",
+        b"k-0",
+        hash(b"v").to_bytes(),
+        root,
+        0,
+        vec![leaf_1, empty_subtree(1), empty_subtree(2)],
+    );
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let root = node(
+        &node(&node(&EMPTY, &leaf_1), &empty_subtree(1)),
+        &empty_subtree(2),
+    );
+    assert_eq!(tree_root(&banks_client, &table).await?, root);
+
+    // The leaf of the deleted key changed since the empty tree.
+    let ix = instructions::compressed_put(
+        &program_id,
+        &owner,
+        "Test",
+        b"k-2",
+        b"v",
+        None,
+        empty_root,
+        0,
+        empty_proof(),
+    );
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::InvalidProof));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_compressed_table_mode_is_checked() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let ix = instructions::init_compressed_table(&program_id, &owner, "Compressed", DEPTH);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    let (compressed, _) = find_table_address(&program_id, &owner, "Compressed");
+
+    let ix = instructions::insert(&program_id, &compressed, &owner, b"k-0", b"v", 0);
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::WrongTableMode));
+
+    utils::init_table(&banks_client, &payer, last_blockhash, "Dedicated").await?;
+    let mut ix = instructions::compressed_put(
+        &program_id,
+        &owner,
+        "Dedicated",
+        b"k-0",
+        b"v",
+        None,
+        empty_subtree(DEPTH as usize),
+        0,
+        empty_proof(),
+    );
+    let (tree, _) = find_tree_address(&program_id, &compressed);
+    ix.accounts[2].pubkey = tree;
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::WrongTableMode));
+
+    let ix = instructions::init_compressed_table(
+        &program_id,
+        &owner,
+        "Deep",
+        SolTree::MAX_DEPTH as u8 + 1,
+    );
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::WrongTableMode));
+
+    Ok(())
+}
@@ -0,0 +1,111 @@
+mod utils;
+
+use solana_program_test::*;
+use solana_sdk::{
+    hash::Hash, instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer,
+    transaction::Transaction, transport::TransportError,
+};
+use soldb_program::{
+    id as program_id, instructions,
+    pda::{find_table_address, find_value_address},
+};
+use utils::{process, setup};
+
+/// Cost of a single `create_program_address`, which is also what every
+/// extra bump tried by `find_program_address` costs.
+const PDA_DERIVATION_UNITS: u64 = 1_500;
+
+/// Builds an instruction on a key of the table `Test`, from the program id,
+/// the owner and the key.
+type Builder = fn(&Pubkey, &Pubkey, &[u8]) -> Instruction;
+
+async fn units(
+    banks_client: &BanksClient,
+    payer: &Keypair,
+    last_blockhash: Hash,
+    ix: Instruction,
+) -> Result<u64, TransportError> {
+    let txn =
+        Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[payer], last_blockhash);
+
+    let processed = banks_client.process_transaction_with_metadata(txn).await?;
+    processed.result?;
+
+    Ok(processed.metadata.unwrap().compute_units_consumed)
+}
+
+/// Finds a key of a fixed length whose value PDA has a bump matching `pred`.
+fn key_with_bump(table: &Pubkey, owner: &Pubkey, pred: impl Fn(u8) -> bool) -> Vec<u8> {
+    (0..1000)
+        .map(|i| format!("k-{i:03}").into_bytes())
+        .find(|key| pred(find_value_address(&program_id(), table, owner, key).1))
+        .expect("no key with the requested bump")
+}
+
+/// Measures every value instruction on two keys whose canonical bumps are
+/// far apart. A bump search would make the low-bump key cost at least
+/// `PDA_DERIVATION_UNITS` more per retried bump; validating with the supplied
+/// bump makes both cost the same.
+#[tokio::test]
+async fn test_value_instructions_skip_bump_search() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let ix = instructions::init_table(&program_id, &owner, "Test");
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    let (table, _) = find_table_address(&program_id, &owner, "Test");
+
+    let fast = key_with_bump(&table, &owner, |bump| bump == u8::MAX);
+    let slow = key_with_bump(&table, &owner, |bump| bump <= u8::MAX - 5);
+
+    // The extra key stays in the directory, so that neither delete empties
+    // and closes its page.
+    for key in [&fast, &slow, &b"k-last".to_vec()] {
+        let ix = instructions::insert(&program_id, &table, &owner, key, b"v-0", 0);
+        process(&banks_client, &payer, last_blockhash, ix).await?;
+    }
+
+    let builders: [(&str, Builder); 5] = [
+        ("put", |p, o, k| {
+            instructions::put(p, o, "Test", k, b"v-1", 0)
+        }),
+        ("patch", |p, o, k| {
+            instructions::patch(p, o, "Test", k, 0, b"V", 0)
+        }),
+        ("append", |p, o, k| {
+            instructions::append(p, o, "Test", k, b"!", 0)
+        }),
+        ("truncate", |p, o, k| {
+            instructions::truncate(p, o, "Test", k, 2, 0)
+        }),
+        ("delete", |p, o, k| {
+            instructions::delete(p, o, "Test", k, 0, 3)
+        }),
+    ];
+
+    for (name, build) in builders {
+        let fast_units = units(
+            &banks_client,
+            &payer,
+            last_blockhash,
+            build(&program_id, &owner, &fast),
+        )
+        .await?;
+        let slow_units = units(
+            &banks_client,
+            &payer,
+            last_blockhash,
+            build(&program_id, &owner, &slow),
+        )
+        .await?;
+
+        println!("{name}: {fast_units} CU (bump 255), {slow_units} CU (bump <= 250)");
+        assert!(
+            fast_units.abs_diff(slow_units) < PDA_DERIVATION_UNITS,
+            "{name} cost depends on the bump: {fast_units} vs {slow_units}"
+        );
+    }
+
+    Ok(())
+}
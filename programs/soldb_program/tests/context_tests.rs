@@ -0,0 +1,111 @@
+mod utils;
+
+use solana_program_test::BanksClient;
+use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer, transport::TransportError};
+use solana_system_interface::instruction::transfer;
+use soldb_program::{
+    accounts::{SolTable, SolValue},
+    error::SolDbError,
+    id as program_id, instructions,
+    pda::find_value_address,
+};
+use utils::{process, setup};
+
+/// Merkle root of the table.
+async fn table_root(
+    banks_client: &BanksClient,
+    table: &Pubkey,
+) -> Result<[u8; 32], TransportError> {
+    let account = banks_client.get_account(*table).await?.unwrap();
+    Ok(SolTable::load(&account.data).unwrap().root)
+}
+
+#[tokio::test]
+async fn test_insert_into_foreign_table() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let (table, _) = utils::init_table(&banks_client, &payer, last_blockhash, "Test").await?;
+    let ix = instructions::insert(&program_id, &table, &owner, b"k-0", b"v-0", 0);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let writer = Keypair::new();
+    let fund = transfer(&payer.pubkey(), &writer.pubkey(), 1_000_000_000);
+    process(&banks_client, &payer, last_blockhash, fund).await?;
+
+    // Any signer inserts into the table, under keys of its own.
+    let root = table_root(&banks_client, &table).await?;
+    let ix = instructions::insert(&program_id, &table, &writer.pubkey(), b"k-0", b"v-1", 0);
+    process(&banks_client, &writer, last_blockhash, ix).await?;
+
+    for (signer, payload) in [(owner, b"v-0"), (writer.pubkey(), b"v-1")] {
+        let (value, _) = find_value_address(&program_id, &table, &signer, b"k-0");
+        let account = banks_client.get_account(value).await?.unwrap();
+        assert_eq!(SolValue::payload(&account.data).unwrap(), payload);
+    }
+    // The table only lists the keys of its owner.
+    assert_eq!(utils::table_keys(&banks_client, &table).await?, 1);
+    assert_eq!(table_root(&banks_client, &table).await?, root);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_value_passed_as_table() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let (table, _) = utils::init_table(&banks_client, &payer, last_blockhash, "Test").await?;
+    let (value, _) = find_value_address(&program_id, &table, &owner, b"k-0");
+    let ix = instructions::insert(&program_id, &table, &owner, b"k-0", b"v-0", 0);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let mut ix = instructions::put(&program_id, &owner, "Test", b"k-0", b"v-1", 0);
+    ix.accounts[1].pubkey = value;
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+
+    assert_eq!(utils::custom_error(err), Some(SolDbError::NotTable));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_readonly_value() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let (table, _) = utils::init_table(&banks_client, &payer, last_blockhash, "Test").await?;
+    let ix = instructions::insert(&program_id, &table, &owner, b"k-0", b"v-0", 0);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let mut ix = instructions::put(&program_id, &owner, "Test", b"k-0", b"v-1", 0);
+    ix.accounts[2].is_writable = false;
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+
+    assert_eq!(utils::custom_error(err), Some(SolDbError::NotWritable));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_wrong_system_program() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+
+    let mut ix = instructions::init_table(&program_id, &payer.pubkey(), "Test");
+    ix.accounts[2].pubkey = Pubkey::new_unique();
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+
+    assert_eq!(utils::custom_error(err), Some(SolDbError::NotSystemProgram));
+
+    Ok(())
+}
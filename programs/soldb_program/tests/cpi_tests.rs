@@ -0,0 +1,147 @@
+mod utils;
+
+use solana_program_test::*;
+use solana_sdk::{
+    account::Account,
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    hash::Hash,
+    instruction::{AccountMeta, Instruction},
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    signature::Keypair,
+    transport::TransportError,
+};
+use solana_system_interface::program as system_program;
+use soldb_program::{
+    accounts::SolValue,
+    error::SolDbError,
+    id as program_id, instructions,
+    pda::{find_table_address, find_value_address},
+};
+use utils::process;
+
+const CALLER_ID: Pubkey = Pubkey::new_from_array([9; 32]);
+
+const AUTHORITY_SEED: &[u8] = b"authority";
+
+/// Forwards the soldb instruction in `data`, after the bump of its authority
+/// PDA, signing for that PDA. The first account is the soldb program.
+fn process_caller(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let (bump, data) = data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let seeds: &[&[u8]] = &[AUTHORITY_SEED, &[*bump]];
+    let authority = Pubkey::create_program_address(seeds, program_id)?;
+
+    let (soldb, infos) = accounts
+        .split_first()
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let metas = infos
+        .iter()
+        .map(|info| AccountMeta {
+            pubkey: *info.key,
+            is_signer: info.is_signer || info.key == &authority,
+            is_writable: info.is_writable,
+        })
+        .collect();
+    let ix = Instruction {
+        program_id: *soldb.key,
+        accounts: metas,
+        data: data.to_vec(),
+    };
+    invoke_signed(&ix, accounts, &[seeds])
+}
+
+/// Starts the test validator with the caller program, and its authority PDA
+/// holding lamports as an account of `owner`.
+async fn setup(owner: &Pubkey) -> Result<(BanksClient, Keypair, Hash, Pubkey, u8), TransportError> {
+    let pid = Pubkey::new_from_array(program_id().to_bytes());
+    let mut program_test = ProgramTest::new("soldb_program", pid, None);
+    program_test.add_program("caller", CALLER_ID, processor!(process_caller));
+
+    let (authority, bump) = Pubkey::find_program_address(&[AUTHORITY_SEED], &CALLER_ID);
+    program_test.add_account(
+        authority,
+        Account {
+            lamports: 1_000_000_000,
+            owner: *owner,
+            ..Account::default()
+        },
+    );
+
+    let (banks_client, payer, last_blockhash) = program_test.start().await;
+    Ok((banks_client, payer, last_blockhash, authority, bump))
+}
+
+/// Routes `ix`, built for the authority PDA, through the caller program.
+fn via_caller(ix: Instruction, authority: &Pubkey, bump: u8) -> Instruction {
+    let mut accounts = vec![AccountMeta::new_readonly(ix.program_id, false)];
+    accounts.extend(ix.accounts.into_iter().map(|meta| AccountMeta {
+        is_signer: meta.is_signer && &meta.pubkey != authority,
+        ..meta
+    }));
+    let mut data = vec![bump];
+    data.extend(ix.data);
+
+    Instruction {
+        program_id: CALLER_ID,
+        accounts,
+        data,
+    }
+}
+
+#[tokio::test]
+async fn test_program_owned_table() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash, authority, bump) = setup(&system_program::ID).await?;
+    let program_id = program_id();
+
+    let ix = instructions::init_table(&program_id, &authority, "Test");
+    let ix = via_caller(ix, &authority, bump);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    let (table, _) = find_table_address(&program_id, &authority, "Test");
+
+    let ix = instructions::insert(&program_id, &table, &authority, b"k-0", b"v-0", 0);
+    let ix = via_caller(ix, &authority, bump);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let ix = instructions::put(&program_id, &authority, "Test", b"k-0", b"v-1", 0);
+    let ix = via_caller(ix, &authority, bump);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let (value, _) = find_value_address(&program_id, &table, &authority, b"k-0");
+    let account = banks_client.get_account(value).await?.unwrap();
+    assert_eq!(SolValue::payload(&account.data).unwrap(), b"v-1");
+
+    // Without the caller signing for its PDA, the table is out of reach.
+    let mut ix = instructions::put(&program_id, &authority, "Test", b"k-0", b"v-2", 0);
+    ix.accounts[0].is_signer = false;
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::MissingSigner));
+
+    let ix = instructions::delete(&program_id, &authority, "Test", b"k-0", 0, 1);
+    let ix = via_caller(ix, &authority, bump);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    assert!(banks_client.get_account(value).await?.is_none());
+    assert_eq!(utils::table_keys(&banks_client, &table).await?, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_program_account_cannot_pay() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash, authority, bump) = setup(&CALLER_ID).await?;
+    let program_id = program_id();
+
+    let ix = instructions::init_table(&program_id, &authority, "Test");
+    let ix = via_caller(ix, &authority, bump);
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::InvalidPayer));
+
+    Ok(())
+}
@@ -0,0 +1,186 @@
+mod utils;
+
+use solana_program_test::*;
+use solana_sdk::{
+    hash::Hash, instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer,
+    transport::TransportError,
+};
+use solana_system_interface::instruction::transfer;
+use soldb_program::{
+    accounts::{SolDelegation, SolValue},
+    error::SolDbError,
+    id as program_id, instructions,
+    pda::{find_delegation_address, find_value_address},
+};
+use utils::{process, setup};
+
+/// Creates a session key holding enough lamports to pay for its writes.
+async fn session_key(
+    banks_client: &BanksClient,
+    payer: &Keypair,
+    last_blockhash: Hash,
+) -> Result<Keypair, TransportError> {
+    let session = Keypair::new();
+    let fund = transfer(&payer.pubkey(), &session.pubkey(), 1_000_000_000);
+    process(banks_client, payer, last_blockhash, fund).await?;
+    Ok(session)
+}
+
+/// Processes `ix`, built for the owner, signed by `session` alone.
+async fn delegated(
+    banks_client: &BanksClient,
+    session: &Keypair,
+    last_blockhash: Hash,
+    ix: Instruction,
+) -> Result<(), TransportError> {
+    let ix = instructions::with_delegate(&program_id(), ix, &session.pubkey());
+    process(banks_client, session, last_blockhash, ix).await
+}
+
+#[tokio::test]
+async fn test_delegated_insert_put_delete() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let (table, _) = utils::init_table(&banks_client, &payer, last_blockhash, "Test").await?;
+    let session = session_key(&banks_client, &payer, last_blockhash).await?;
+    let ix = instructions::delegate(
+        &program_id,
+        &owner,
+        "Test",
+        &session.pubkey(),
+        b"s-",
+        3,
+        u64::MAX,
+    );
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let (value, _) = find_value_address(&program_id, &table, &owner, b"s-0");
+    let balance = banks_client.get_balance(owner).await?;
+
+    let ix = instructions::insert(&program_id, &table, &owner, b"s-0", b"v-0", 0);
+    delegated(&banks_client, &session, last_blockhash, ix).await?;
+    let ix = instructions::put(&program_id, &owner, "Test", b"s-0", b"v-1", 0);
+    delegated(&banks_client, &session, last_blockhash, ix).await?;
+
+    let account = banks_client.get_account(value).await?.unwrap();
+    assert_eq!(SolValue::payload(&account.data).unwrap(), b"v-1");
+    // The session key paid for the value.
+    assert_eq!(banks_client.get_balance(owner).await?, balance);
+
+    let ix = instructions::delete(&program_id, &owner, "Test", b"s-0", 0, 1);
+    delegated(&banks_client, &session, last_blockhash, ix).await?;
+
+    assert!(banks_client.get_account(value).await?.is_none());
+    assert_eq!(utils::table_keys(&banks_client, &table).await?, 0);
+    // The owner receives the lamports of the closed accounts.
+    assert!(banks_client.get_balance(owner).await? > balance);
+
+    let (delegation, _) = find_delegation_address(&program_id, &table, &session.pubkey());
+    let account = banks_client.get_account(delegation).await?.unwrap();
+    assert_eq!(SolDelegation::load(&account.data).unwrap().remaining, 0);
+
+    // Every granted write was used.
+    let ix = instructions::insert(&program_id, &table, &owner, b"s-1", b"v-2", 0);
+    let err = delegated(&banks_client, &session, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::QuotaExceeded));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_delegate_is_scoped_and_revocable() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let (table, _) = utils::init_table(&banks_client, &payer, last_blockhash, "Test").await?;
+    let session = session_key(&banks_client, &payer, last_blockhash).await?;
+    let ix = instructions::delegate(
+        &program_id,
+        &owner,
+        "Test",
+        &session.pubkey(),
+        b"s-",
+        10,
+        u64::MAX,
+    );
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    // Keys outside the prefix are out of reach.
+    let ix = instructions::insert(&program_id, &table, &owner, b"k-0", b"v-0", 0);
+    let err = delegated(&banks_client, &session, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::KeyOutOfScope));
+
+    // Another key cannot use the delegation of the session key.
+    let impostor = session_key(&banks_client, &payer, last_blockhash).await?;
+    let ix = instructions::insert(&program_id, &table, &owner, b"s-0", b"v-1", 0);
+    let mut ix = instructions::with_delegate(&program_id, ix, &session.pubkey());
+    ix.accounts[0].pubkey = impostor.pubkey();
+    let err = process(&banks_client, &impostor, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::PdaMismatch));
+
+    let ix = instructions::insert(&program_id, &table, &owner, b"s-0", b"v-2", 0);
+    delegated(&banks_client, &session, last_blockhash, ix).await?;
+
+    let ix = instructions::revoke(&program_id, &owner, "Test", &session.pubkey());
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let (delegation, _) = find_delegation_address(&program_id, &table, &session.pubkey());
+    assert!(banks_client.get_account(delegation).await?.is_none());
+
+    // Without its delegation, the accounts of the session key are read as
+    // those of the owner.
+    let ix = instructions::put(&program_id, &owner, "Test", b"s-0", b"v-3", 0);
+    let err = delegated(&banks_client, &session, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::WrongOwner));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_delegation_expires() -> Result<(), TransportError> {
+    let pid = Pubkey::new_from_array(program_id().to_bytes());
+    let program_test = ProgramTest::new("soldb_program", pid, None);
+    let mut context = program_test.start_with_context().await;
+    let program_id = program_id();
+    let owner = context.payer.pubkey();
+    let banks_client = context.banks_client.clone();
+    let payer = context.payer.insecure_clone();
+    let last_blockhash = context.last_blockhash;
+
+    let (table, _) = utils::init_table(&banks_client, &payer, last_blockhash, "Test").await?;
+    let session = session_key(&banks_client, &payer, last_blockhash).await?;
+    let ix = instructions::delegate(&program_id, &owner, "Test", &session.pubkey(), b"", 10, 100);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let ix = instructions::insert(&program_id, &table, &owner, b"k-0", b"v-0", 0);
+    delegated(&banks_client, &session, last_blockhash, ix).await?;
+
+    context.warp_to_slot(200).unwrap();
+    let last_blockhash = banks_client.get_latest_blockhash().await?;
+
+    let ix = instructions::put(&program_id, &owner, "Test", b"k-0", b"v-1", 0);
+    let err = delegated(&banks_client, &session, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        utils::custom_error(err),
+        Some(SolDbError::DelegationExpired)
+    );
+
+    // The owner keeps writing without the session key.
+    let ix = instructions::put(&program_id, &owner, "Test", b"k-0", b"v-2", 0);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    Ok(())
+}
@@ -7,9 +7,9 @@ use solana_sdk::{
 };
 use solana_system_interface::program;
 use soldb_program::{
-    accounts::{SolTable, SolValue},
     id as program_id,
     instructions::{Delete, SolDbIntructions},
+    pda::find_directory_address,
 };
 
 use crate::utils::setup;
@@ -21,20 +21,18 @@ async fn test_delete() -> Result<(), TransportError> {
     let (banks_client, payer, last_blockhash) = setup().await?;
 
     let name = "Test".to_string();
-    let table = SolTable { name: name.clone() };
     let (pda_table_pubkey, table_bump) =
-        utils::init_table(&banks_client, &payer, last_blockhash, &table).await?;
+        utils::init_table(&banks_client, &payer, last_blockhash, "Test").await?;
 
     let key: Vec<u8> = "k-0".into();
     let value: Vec<u8> = "v-0".into();
-    let sol_value = SolValue { val: value.clone() };
     let (pda_val_pubkey, value_bump) = utils::insert(
         &banks_client,
         &payer,
         last_blockhash,
         &pda_table_pubkey,
         key.into(),
-        &sol_value,
+        &value,
     )
     .await?;
 
@@ -50,6 +48,8 @@ async fn test_delete() -> Result<(), TransportError> {
     let mut ix_data = Vec::new();
     instr.serialize(&mut ix_data).unwrap();
 
+    let (directory, _) = find_directory_address(&program_id(), &pda_table_pubkey, 0);
+
     let ix = Instruction {
         program_id: program_id(),
         accounts: vec![
@@ -57,6 +57,8 @@ async fn test_delete() -> Result<(), TransportError> {
             AccountMeta::new(pda_table_pubkey, false),
             AccountMeta::new(pda_val_pubkey, false),
             AccountMeta::new_readonly(program::ID, false),
+            AccountMeta::new(directory, false),
+            AccountMeta::new(directory, false),
         ],
         data: ix_data,
     };
@@ -87,6 +89,8 @@ async fn test_delete() -> Result<(), TransportError> {
         .unwrap()
         .unwrap();
 
+    let dir_before_acc = banks_client.get_account(directory).await.unwrap().unwrap();
+
     banks_client.process_transaction_with_metadata(txn).await?;
 
     let owner_after = banks_client
@@ -97,11 +101,12 @@ async fn test_delete() -> Result<(), TransportError> {
         .lamports;
 
     let old_val_lamports = val_before_acc.lamports;
+    let old_dir_lamports = dir_before_acc.lamports;
 
-    let expected_owner_after = owner_before - tx_fee + old_val_lamports;
+    let expected_owner_after = owner_before - tx_fee + old_val_lamports + old_dir_lamports;
     assert_eq!(
         owner_after, expected_owner_after,
-        "El pagador debería recuperar los lamports de la cuenta cerrada menos el fee"
+        "El pagador debería recuperar los lamports de las cuentas cerradas menos el fee"
     );
 
     let val_after_acc = banks_client.get_account(pda_val_pubkey).await.unwrap();
@@ -110,5 +115,11 @@ async fn test_delete() -> Result<(), TransportError> {
         "La cuenta del valor debería estar cerrada tras Delete"
     );
 
+    let dir_after_acc = banks_client.get_account(directory).await.unwrap();
+    assert!(
+        dir_after_acc.is_none(),
+        "La página vacía del directorio debería estar cerrada tras Delete"
+    );
+
     Ok(())
 }
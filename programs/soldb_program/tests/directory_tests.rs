@@ -0,0 +1,96 @@
+mod utils;
+
+use solana_program_test::*;
+use solana_sdk::{pubkey::Pubkey, signer::Signer, transport::TransportError};
+use soldb_program::{
+    accounts::SolDirectory, error::SolDbError, id as program_id, instructions,
+    pda::find_directory_address,
+};
+use utils::{process, setup};
+
+const KEYS: usize = SolDirectory::PAGE_KEYS + 1;
+
+fn key(i: usize) -> Vec<u8> {
+    format!("k-{i}").into_bytes()
+}
+
+async fn page_keys(
+    banks_client: &BanksClient,
+    table: &Pubkey,
+    page: u32,
+) -> Result<Option<Vec<Vec<u8>>>, TransportError> {
+    let (directory, _) = find_directory_address(&program_id(), table, page);
+    Ok(banks_client
+        .get_account(directory)
+        .await?
+        .map(|account| SolDirectory::keys(&account.data).unwrap()))
+}
+
+#[tokio::test]
+async fn test_directory_lists_keys() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let (table, _) = utils::init_table(&banks_client, &payer, last_blockhash, "Test").await?;
+    for i in 0..KEYS {
+        utils::insert(&banks_client, &payer, last_blockhash, &table, key(i), b"v").await?;
+    }
+
+    assert_eq!(utils::table_keys(&banks_client, &table).await?, KEYS as u32);
+    let first = page_keys(&banks_client, &table, 0).await?.unwrap();
+    assert_eq!(
+        first,
+        (0..SolDirectory::PAGE_KEYS).map(key).collect::<Vec<_>>()
+    );
+    let last = page_keys(&banks_client, &table, 1).await?.unwrap();
+    assert_eq!(last, vec![key(KEYS - 1)]);
+
+    // The last key takes the place of the deleted one, and the emptied last
+    // page is closed.
+    let ix = instructions::delete(&program_id, &owner, "Test", &key(3), 0, KEYS as u32);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    assert_eq!(
+        utils::table_keys(&banks_client, &table).await?,
+        KEYS as u32 - 1
+    );
+    let first = page_keys(&banks_client, &table, 0).await?.unwrap();
+    assert_eq!(first[3], key(KEYS - 1));
+    assert!(!first.contains(&key(3)));
+    assert!(page_keys(&banks_client, &table, 1).await?.is_none());
+
+    let ix = instructions::upsert(&program_id, &owner, "Test", &key(3), b"v", 0, 1);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let last = page_keys(&banks_client, &table, 1).await?.unwrap();
+    assert_eq!(last, vec![key(3)]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_directory_pages_are_checked() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let (table, _) = utils::init_table(&banks_client, &payer, last_blockhash, "Test").await?;
+    utils::insert(&banks_client, &payer, last_blockhash, &table, key(0), b"v").await?;
+
+    // A wrong page points the insert at a page the key does not go to.
+    let ix = instructions::insert(&program_id, &table, &owner, &key(1), b"v", 1);
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::PdaMismatch));
+
+    let mut ix = instructions::delete(&program_id, &owner, "Test", &key(0), 0, 1);
+    ix.accounts[4].pubkey = table;
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::NotDirectory));
+
+    Ok(())
+}
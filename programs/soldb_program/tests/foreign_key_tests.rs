@@ -0,0 +1,260 @@
+mod utils;
+
+use solana_program_test::*;
+use solana_sdk::{
+    hash::Hash, pubkey::Pubkey, signature::Keypair, signer::Signer, transport::TransportError,
+};
+use soldb_program::{
+    accounts::{ForeignKey, SolValue},
+    error::SolDbError,
+    id as program_id, instructions,
+    pda::find_table_address,
+};
+use utils::{process, setup};
+
+/// Creates the table `Parent` holding `p-0`, and the empty table `Child`
+/// whose payloads start with a key of `Parent`.
+async fn setup_tables(
+    banks_client: &BanksClient,
+    payer: &Keypair,
+    last_blockhash: Hash,
+    restrict: bool,
+) -> Result<(ForeignKey, Pubkey), TransportError> {
+    let program_id = program_id();
+
+    let (parent, _) = utils::init_table(banks_client, payer, last_blockhash, "Parent").await?;
+    let (parent_value, _) = utils::insert(
+        banks_client,
+        payer,
+        last_blockhash,
+        &parent,
+        b"p-0".to_vec(),
+        b"v",
+    )
+    .await?;
+    utils::init_table(banks_client, payer, last_blockhash, "Child").await?;
+
+    let foreign_key = ForeignKey {
+        parent,
+        parent_owner: payer.pubkey(),
+        offset: 0,
+        len: 3,
+        restrict,
+    };
+    let ix = instructions::set_foreign_key(&program_id, &payer.pubkey(), "Child", foreign_key);
+    process(banks_client, payer, last_blockhash, ix).await?;
+
+    Ok((foreign_key, parent_value))
+}
+
+async fn refs(banks_client: &BanksClient, value: &Pubkey) -> Result<u32, TransportError> {
+    let account = banks_client.get_account(*value).await?.unwrap();
+    Ok(SolValue::load(&account.data).unwrap().refs)
+}
+
+#[tokio::test]
+async fn test_insert_requires_existing_parent() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let (foreign_key, _) = setup_tables(&banks_client, &payer, last_blockhash, false).await?;
+    let (child, _) = find_table_address(&program_id, &owner, "Child");
+
+    let mut ix = instructions::insert(&program_id, &child, &owner, b"c-0", b"p-0 row", 0);
+    ix.accounts
+        .push(instructions::parent_meta(&program_id, &foreign_key, b"p-0"));
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let mut ix = instructions::insert(&program_id, &child, &owner, b"c-1", b"p-9 row", 0);
+    ix.accounts
+        .push(instructions::parent_meta(&program_id, &foreign_key, b"p-9"));
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::ParentNotFound));
+
+    // The parent of another key does not do.
+    let mut ix = instructions::insert(&program_id, &child, &owner, b"c-1", b"p-1 row", 0);
+    ix.accounts
+        .push(instructions::parent_meta(&program_id, &foreign_key, b"p-0"));
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::PdaMismatch));
+
+    let ix = instructions::insert(&program_id, &child, &owner, b"c-1", b"p", 0);
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        utils::custom_error(err),
+        Some(SolDbError::ForeignKeyMissing)
+    );
+
+    // Put keeps checking the parent, and the key cannot change.
+    let mut ix = instructions::put(&program_id, &owner, "Child", b"c-0", b"p-0 new row", 0);
+    ix.accounts
+        .push(instructions::parent_meta(&program_id, &foreign_key, b"p-0"));
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let ix = instructions::patch(&program_id, &owner, "Child", b"c-0", 2, b"1", 0);
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        utils::custom_error(err),
+        Some(SolDbError::ForeignKeyChanged)
+    );
+
+    let ix = instructions::patch(&program_id, &owner, "Child", b"c-0", 4, b"edited", 0);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_parent_follows_bloom_filter() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let (foreign_key, _) = setup_tables(&banks_client, &payer, last_blockhash, false).await?;
+    let (child, _) = find_table_address(&program_id, &owner, "Child");
+    let ix = instructions::init_bloom(&program_id, &owner, "Child", 1024, 4);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    // The writes that may create a key pass the filter first, and all of
+    // them pass the parent last.
+    let mut ix = instructions::insert(&program_id, &child, &owner, b"c-0", b"p-0 row", 0);
+    ix.accounts
+        .push(instructions::bloom_meta(&program_id, &child));
+    ix.accounts
+        .push(instructions::parent_meta(&program_id, &foreign_key, b"p-0"));
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let mut ix = instructions::upsert(&program_id, &owner, "Child", b"c-0", b"p-0 new", 0, 0);
+    ix.accounts
+        .push(instructions::bloom_meta(&program_id, &child));
+    ix.accounts
+        .push(instructions::parent_meta(&program_id, &foreign_key, b"p-0"));
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let mut ix = instructions::put(&program_id, &owner, "Child", b"c-0", b"p-0 last", 0);
+    ix.accounts
+        .push(instructions::parent_meta(&program_id, &foreign_key, b"p-0"));
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_upload_requires_inserted_value() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let (foreign_key, _) = setup_tables(&banks_client, &payer, last_blockhash, true).await?;
+    let (child, _) = find_table_address(&program_id, &owner, "Child");
+
+    // An upload would list an empty value, pointing at no parent.
+    let ix = instructions::open_upload(&program_id, &owner, "Child", b"c-0", 16, [0; 32], 0);
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        utils::custom_error(err),
+        Some(SolDbError::ForeignKeyMissing)
+    );
+    assert_eq!(utils::table_keys(&banks_client, &child).await?, 0);
+
+    let mut ix = instructions::insert(&program_id, &child, &owner, b"c-0", b"p-0 row", 0);
+    ix.accounts
+        .push(instructions::parent_meta(&program_id, &foreign_key, b"p-0"));
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let ix = instructions::open_upload(&program_id, &owner, "Child", b"c-0", 16, [0; 32], 0);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_restrict_blocks_parent_delete() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let (foreign_key, parent_value) =
+        setup_tables(&banks_client, &payer, last_blockhash, true).await?;
+    let (child, _) = find_table_address(&program_id, &owner, "Child");
+
+    let mut ix = instructions::insert(&program_id, &child, &owner, b"c-0", b"p-0 row", 0);
+    ix.accounts
+        .push(instructions::parent_meta(&program_id, &foreign_key, b"p-0"));
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    assert_eq!(refs(&banks_client, &parent_value).await?, 1);
+
+    let ix = instructions::delete(&program_id, &owner, "Parent", b"p-0", 0, 1);
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::ValueReferenced));
+
+    let ix = instructions::delete(&program_id, &owner, "Child", b"c-0", 0, 1);
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::ParentNotFound));
+
+    let mut ix = instructions::delete(&program_id, &owner, "Child", b"c-0", 0, 1);
+    ix.accounts
+        .push(instructions::parent_meta(&program_id, &foreign_key, b"p-0"));
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    assert_eq!(refs(&banks_client, &parent_value).await?, 0);
+
+    // The parent is free to go once nothing points at it. The extra account
+    // only keeps the transaction apart from the rejected one.
+    let mut ix = instructions::delete(&program_id, &owner, "Parent", b"p-0", 0, 1);
+    ix.accounts
+        .push(instructions::parent_meta(&program_id, &foreign_key, b"p-0"));
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    assert!(banks_client.get_account(parent_value).await?.is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_foreign_key_is_declared_on_empty_tables() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let (foreign_key, _) = setup_tables(&banks_client, &payer, last_blockhash, false).await?;
+
+    // `Parent` already holds a key, and `Child` already has a foreign key.
+    for (name, offset, error) in [
+        ("Parent", 0, SolDbError::TableNotEmpty),
+        ("Child", 1, SolDbError::InvalidForeignKey),
+    ] {
+        let foreign_key = ForeignKey {
+            offset,
+            ..foreign_key
+        };
+        let ix = instructions::set_foreign_key(&program_id, &owner, name, foreign_key);
+        let err = process(&banks_client, &payer, last_blockhash, ix)
+            .await
+            .unwrap_err();
+        assert_eq!(utils::custom_error(err), Some(error));
+    }
+
+    let ix = instructions::init_packed_table(&program_id, &owner, "Packed", 1);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    let ix = instructions::set_foreign_key(&program_id, &owner, "Packed", foreign_key);
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::WrongTableMode));
+
+    Ok(())
+}
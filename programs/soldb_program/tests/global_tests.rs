@@ -0,0 +1,135 @@
+mod utils;
+
+use solana_program_test::*;
+use solana_sdk::{signature::Keypair, signer::Signer, transport::TransportError};
+use solana_system_interface::instruction::transfer;
+use soldb_program::{
+    accounts::{SolAcl, SolValue},
+    error::SolDbError,
+    id as program_id, instructions,
+    pda::{find_global_value_address, find_table_address},
+};
+use utils::{process, setup};
+
+#[tokio::test]
+async fn test_global_keys_are_unique() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let ix = instructions::init_global_table(&program_id, &owner, "Test");
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    let (table, _) = find_table_address(&program_id, &owner, "Test");
+
+    let writer = Keypair::new();
+    let fund = transfer(&owner, &writer.pubkey(), 1_000_000_000);
+    process(&banks_client, &payer, last_blockhash, fund).await?;
+    let ix = instructions::grant_roles(
+        &program_id,
+        &owner,
+        &owner,
+        "Test",
+        &writer.pubkey(),
+        SolAcl::INSERTER,
+    );
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let ix = instructions::global_insert(&program_id, &table, &owner, b"k-0", b"v-0", 0);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    // The first insert takes the key for every writer.
+    let ix = instructions::global_insert(&program_id, &table, &owner, b"k-0", b"v-1", 0);
+    let ix = instructions::with_role(&program_id, ix, &writer.pubkey());
+    let err = process(&banks_client, &writer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        utils::custom_error(err),
+        Some(SolDbError::AccountAlreadyExists)
+    );
+
+    let ix = instructions::global_insert(&program_id, &table, &owner, b"k-1", b"v-2", 0);
+    let ix = instructions::with_role(&program_id, ix, &writer.pubkey());
+    process(&banks_client, &writer, last_blockhash, ix).await?;
+
+    // Values are found from the table and the key alone.
+    let (value, _) = find_global_value_address(&program_id, &table, b"k-1");
+    let account = banks_client.get_account(value).await?.unwrap();
+    assert_eq!(SolValue::payload(&account.data).unwrap(), b"v-2");
+
+    let ix = instructions::global_put(&program_id, &owner, "Test", b"k-1", b"v-3", 0);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    let account = banks_client.get_account(value).await?.unwrap();
+    assert_eq!(SolValue::payload(&account.data).unwrap(), b"v-3");
+
+    let ix = instructions::global_delete(&program_id, &owner, "Test", b"k-1", 0, 2);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    assert!(banks_client.get_account(value).await?.is_none());
+    assert_eq!(utils::table_keys(&banks_client, &table).await?, 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_global_key_cannot_take_bloom_address() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let ix = instructions::init_global_table(&program_id, &owner, "Test");
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    let (table, _) = find_table_address(&program_id, &owner, "Test");
+
+    let ix = instructions::global_insert(&program_id, &table, &owner, b"bloom", b"v-0", 0);
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::ReservedKey));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_unprivileged_signer_cannot_insert() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let ix = instructions::init_global_table(&program_id, &owner, "Test");
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    let (table, _) = find_table_address(&program_id, &owner, "Test");
+
+    let writer = Keypair::new();
+    let fund = transfer(&owner, &writer.pubkey(), 1_000_000_000);
+    process(&banks_client, &payer, last_blockhash, fund).await?;
+
+    // Signing alone does not take a key of the table.
+    let ix = instructions::global_insert(&program_id, &table, &writer.pubkey(), b"k-0", b"v", 0);
+    let err = process(&banks_client, &writer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::MissingRole));
+
+    // Nor does a role other than inserter.
+    let ix = instructions::grant_roles(
+        &program_id,
+        &owner,
+        &owner,
+        "Test",
+        &writer.pubkey(),
+        SolAcl::READER,
+    );
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    let ix = instructions::global_insert(&program_id, &table, &owner, b"k-0", b"v", 0);
+    let ix = instructions::with_role(&program_id, ix, &writer.pubkey());
+    let err = process(&banks_client, &writer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::MissingRole));
+
+    let (value, _) = find_global_value_address(&program_id, &table, b"k-0");
+    assert!(banks_client.get_account(value).await?.is_none());
+    assert_eq!(utils::table_keys(&banks_client, &table).await?, 0);
+
+    Ok(())
+}
@@ -0,0 +1,148 @@
+mod utils;
+
+use borsh::BorshDeserialize;
+use solana_program_test::*;
+use solana_sdk::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    hash::Hash,
+    instruction::{Instruction, InstructionError},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    transaction::TransactionError,
+    transport::TransportError,
+};
+use soldb_program::{
+    accounts::SolValue,
+    error::SolDbError,
+    hook::{HookInstruction, MutationKind},
+    id as program_id, instructions,
+    pda::find_table_address,
+};
+use utils::process;
+
+const HOOK_ID: Pubkey = Pubkey::new_from_array([7; 32]);
+
+/// Error of the test hook when it rejects a mutation.
+const REJECTED: u32 = 1_000;
+
+/// Rejects payloads containing `forbidden`, and deleting `k-keep`.
+fn process_hook(_program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let HookInstruction::Execute(mutation) =
+        HookInstruction::try_from_slice(data).map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    let rejected = match mutation.kind {
+        MutationKind::Delete => mutation.key == b"k-keep",
+        MutationKind::Insert | MutationKind::Put => {
+            let data = accounts[1].data.borrow();
+            SolValue::payload(&data)?
+                .windows(9)
+                .any(|window| window == b"forbidden")
+        }
+    };
+    if rejected {
+        return Err(ProgramError::Custom(REJECTED));
+    }
+    Ok(())
+}
+
+async fn setup() -> Result<(BanksClient, Keypair, Hash), TransportError> {
+    let pid = Pubkey::new_from_array(program_id().to_bytes());
+    let mut program_test = ProgramTest::new("soldb_program", pid, None);
+    program_test.add_program("hook", HOOK_ID, processor!(process_hook));
+
+    Ok(program_test.start().await)
+}
+
+fn rejected_by_hook(err: TransportError) -> bool {
+    matches!(
+        err,
+        TransportError::TransactionError(TransactionError::InstructionError(
+            _,
+            InstructionError::Custom(REJECTED),
+        ))
+    )
+}
+
+fn with_hook(mut ix: Instruction) -> Instruction {
+    ix.accounts.extend(instructions::hook_metas(&HOOK_ID, &[]));
+    ix
+}
+
+#[tokio::test]
+async fn test_hook_can_reject_mutations() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    utils::init_table(&banks_client, &payer, last_blockhash, "Test").await?;
+    let (table, _) = find_table_address(&program_id, &owner, "Test");
+    let ix = instructions::set_hook(&program_id, &owner, "Test", Some(HOOK_ID));
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let ix = instructions::insert(&program_id, &table, &owner, b"k-0", b"fine", 0);
+    process(&banks_client, &payer, last_blockhash, with_hook(ix)).await?;
+
+    let ix = instructions::insert(&program_id, &table, &owner, b"k-1", b"forbidden", 0);
+    let err = process(&banks_client, &payer, last_blockhash, with_hook(ix))
+        .await
+        .unwrap_err();
+    assert!(rejected_by_hook(err));
+
+    let ix = instructions::insert(&program_id, &table, &owner, b"k-1", b"fine", 0);
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::HookMissing));
+
+    let ix = instructions::put(&program_id, &owner, "Test", b"k-0", b"forbidden!", 0);
+    let err = process(&banks_client, &payer, last_blockhash, with_hook(ix))
+        .await
+        .unwrap_err();
+    assert!(rejected_by_hook(err));
+
+    let ix = instructions::insert(&program_id, &table, &owner, b"k-keep", b"fine", 0);
+    process(&banks_client, &payer, last_blockhash, with_hook(ix)).await?;
+    let ix = instructions::delete(&program_id, &owner, "Test", b"k-keep", 0, 2);
+    let err = process(&banks_client, &payer, last_blockhash, with_hook(ix))
+        .await
+        .unwrap_err();
+    assert!(rejected_by_hook(err));
+
+    // Without a hook, nothing is rejected anymore.
+    let ix = instructions::set_hook(&program_id, &owner, "Test", None);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    let ix = instructions::insert(&program_id, &table, &owner, b"k-1", b"forbidden", 0);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_hook_must_be_a_program() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    utils::init_table(&banks_client, &payer, last_blockhash, "Test").await?;
+
+    for hook in [owner, program_id] {
+        let ix = instructions::set_hook(&program_id, &owner, "Test", Some(hook));
+        let err = process(&banks_client, &payer, last_blockhash, ix)
+            .await
+            .unwrap_err();
+        assert_eq!(utils::custom_error(err), Some(SolDbError::InvalidHook));
+    }
+
+    let ix = instructions::init_compressed_table(&program_id, &owner, "Compressed", 4);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    let ix = instructions::set_hook(&program_id, &owner, "Compressed", Some(HOOK_ID));
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::WrongTableMode));
+
+    Ok(())
+}
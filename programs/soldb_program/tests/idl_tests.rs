@@ -0,0 +1,451 @@
+use serde_json::Value;
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+use soldb_program::{
+    accounts::{
+        ForeignKey, SolAcl, SolBloom, SolDelegation, SolDirectory, SolMultisig, SolNonce, SolSlab,
+        SolTable, SolTree, SolUpload, SolValue, SolValueAuthority, TokenGate,
+    },
+    error::SolDbError,
+    hook::MutationKind,
+    id as program_id,
+    instructions::{self, SolDbIntructions},
+    relay::RelayedMessage,
+};
+
+const IDL: &str = include_str!("../idl/soldb_program.json");
+
+fn idl() -> Value {
+    serde_json::from_str(IDL).expect("IDL must be valid JSON")
+}
+
+/// IDL name of every instruction variant. The match is exhaustive so that a
+/// new variant does not compile until it is added here and to the IDL.
+fn idl_name(ix: &SolDbIntructions) -> &'static str {
+    match ix {
+        SolDbIntructions::InitTable(_) => "initTable",
+        SolDbIntructions::Insert(_) => "insert",
+        SolDbIntructions::Put(_) => "put",
+        SolDbIntructions::Delete(_) => "delete",
+        SolDbIntructions::Patch(_) => "patch",
+        SolDbIntructions::Append(_) => "append",
+        SolDbIntructions::Truncate(_) => "truncate",
+        SolDbIntructions::OpenUpload(_) => "openUpload",
+        SolDbIntructions::WriteChunk(_) => "writeChunk",
+        SolDbIntructions::CommitUpload(_) => "commitUpload",
+        SolDbIntructions::Upsert(_) => "upsert",
+        SolDbIntructions::Migrate(_) => "migrate",
+        SolDbIntructions::VerifyProof(_) => "verifyProof",
+        SolDbIntructions::CompressedPut(_) => "compressedPut",
+        SolDbIntructions::CompressedDelete(_) => "compressedDelete",
+        SolDbIntructions::InitBloom(_) => "initBloom",
+        SolDbIntructions::MaybeContains(_) => "maybeContains",
+        SolDbIntructions::RebuildBloom(_) => "rebuildBloom",
+        SolDbIntructions::SetForeignKey(_) => "setForeignKey",
+        SolDbIntructions::SetHook(_) => "setHook",
+        SolDbIntructions::RelayedInsert(_) => "relayedInsert",
+        SolDbIntructions::RelayedPut(_) => "relayedPut",
+        SolDbIntructions::RelayedDelete(_) => "relayedDelete",
+        SolDbIntructions::CreateMultisig(_) => "createMultisig",
+        SolDbIntructions::SetMultisigSigners(_) => "setMultisigSigners",
+        SolDbIntructions::Delegate(_) => "delegate",
+        SolDbIntructions::Revoke(_) => "revoke",
+        SolDbIntructions::GrantRoles(_) => "grantRoles",
+        SolDbIntructions::RevokeRoles(_) => "revokeRoles",
+        SolDbIntructions::SetValueAuthority(_) => "setValueAuthority",
+        SolDbIntructions::SetTokenGate(_) => "setTokenGate",
+        SolDbIntructions::Moderate(_) => "moderate",
+        SolDbIntructions::SetModerationDestination(_) => "setModerationDestination",
+    }
+}
+
+/// Whether `discriminant` tags a variant of `SolDbIntructions`, told apart
+/// from its missing fields by the error of the derived decoder.
+fn is_variant(discriminant: u8) -> bool {
+    match borsh::from_slice::<SolDbIntructions>(&[discriminant]) {
+        Ok(_) => true,
+        Err(err) => !err.to_string().starts_with("Unexpected variant tag"),
+    }
+}
+
+/// Builds an instruction with the exported builder matching an IDL name.
+fn build(name: &str) -> Instruction {
+    let program_id = program_id();
+    let owner = Pubkey::new_unique();
+    let table = Pubkey::new_unique();
+    let message = |kind| RelayedMessage {
+        kind,
+        table,
+        key: b"k".to_vec(),
+        payload: b"v".to_vec(),
+        nonce: 0,
+        expiry: 0,
+    };
+
+    match name {
+        "initTable" => instructions::init_table(&program_id, &owner, "t"),
+        "insert" => instructions::insert(&program_id, &table, &owner, b"k", b"v", 0),
+        "put" => instructions::put(&program_id, &owner, "t", b"k", b"v", 0),
+        "delete" => instructions::delete(&program_id, &owner, "t", b"k", 0, 1),
+        "patch" => instructions::patch(&program_id, &owner, "t", b"k", 0, b"v", 0),
+        "append" => instructions::append(&program_id, &owner, "t", b"k", b"v", 0),
+        "truncate" => instructions::truncate(&program_id, &owner, "t", b"k", 0, 0),
+        "openUpload" => instructions::open_upload(&program_id, &owner, "t", b"k", 1, [0; 32], 0),
+        "writeChunk" => instructions::write_chunk(&program_id, &owner, "t", b"k", 0, b"v"),
+        "commitUpload" => instructions::commit_upload(&program_id, &owner, "t", b"k", 0),
+        "upsert" => instructions::upsert(&program_id, &owner, "t", b"k", b"v", 0, 0),
+        "migrate" => instructions::migrate(&program_id, &owner, "t", b"k", 1, 0),
+        "verifyProof" => {
+            instructions::verify_proof(&program_id, &table, &owner, b"k", [0; 32], 0, vec![])
+        }
+        "compressedPut" => instructions::compressed_put(
+            &program_id,
+            &owner,
+            "t",
+            b"k",
+            b"v",
+            None,
+            [0; 32],
+            0,
+            vec![],
+        ),
+        "compressedDelete" => instructions::compressed_delete(
+            &program_id,
+            &owner,
+            "t",
+            b"k",
+            [0; 32],
+            [0; 32],
+            0,
+            vec![],
+        ),
+        "initBloom" => instructions::init_bloom(&program_id, &owner, "t", 64, 3),
+        "maybeContains" => instructions::maybe_contains(&program_id, &table, b"k"),
+        "rebuildBloom" => instructions::rebuild_bloom(&program_id, &owner, "t", true, &[]),
+        "setForeignKey" => instructions::set_foreign_key(
+            &program_id,
+            &owner,
+            "t",
+            ForeignKey {
+                parent: table,
+                parent_owner: owner,
+                offset: 0,
+                len: 1,
+                restrict: true,
+            },
+        ),
+        "setHook" => instructions::set_hook(&program_id, &owner, "t", None),
+        "relayedInsert" => instructions::relayed_insert(
+            &program_id,
+            &table,
+            &owner,
+            "t",
+            &message(MutationKind::Insert),
+            0,
+        ),
+        "relayedPut" => instructions::relayed_put(
+            &program_id,
+            &table,
+            &owner,
+            "t",
+            &message(MutationKind::Put),
+            0,
+        ),
+        "relayedDelete" => instructions::relayed_delete(
+            &program_id,
+            &table,
+            &owner,
+            "t",
+            &message(MutationKind::Delete),
+            0,
+            1,
+        ),
+        "createMultisig" => instructions::create_multisig(&program_id, &owner, "m", 1, &[owner]),
+        "setMultisigSigners" => {
+            instructions::set_multisig_signers(&program_id, &table, 1, &[owner], &[])
+        }
+        "delegate" => instructions::delegate(&program_id, &owner, "t", &table, b"k", 1, 0),
+        "revoke" => instructions::revoke(&program_id, &owner, "t", &table),
+        "grantRoles" => instructions::grant_roles(&program_id, &owner, &owner, "t", &table, 2),
+        "revokeRoles" => instructions::revoke_roles(&program_id, &owner, &owner, "t", &table, 2),
+        "setValueAuthority" => {
+            instructions::set_value_authority(&program_id, &owner, &owner, "t", b"k", Some(table))
+        }
+        "setTokenGate" => instructions::set_token_gate(
+            &program_id,
+            &owner,
+            "t",
+            Some(TokenGate {
+                mint: table,
+                min_amount: 1,
+            }),
+        ),
+        "moderate" => {
+            instructions::moderate(&program_id, &table, &owner, &owner, "t", b"k", 0, 1, &owner)
+        }
+        "setModerationDestination" => {
+            instructions::set_moderation_destination(&program_id, &owner, "t", Some(table))
+        }
+        _ => panic!("no builder for IDL instruction {name}"),
+    }
+}
+
+fn find_type<'a>(idl: &'a Value, name: &str) -> &'a Value {
+    ["types", "accounts"]
+        .iter()
+        .flat_map(|section| idl[section].as_array().unwrap())
+        .find(|ty| ty["name"] == name)
+        .unwrap_or_else(|| panic!("type {name} missing from IDL"))
+}
+
+/// Borsh-encodes a deterministic sample of an IDL type.
+fn encode_sample(idl: &Value, ty: &Value, out: &mut Vec<u8>) {
+    match ty {
+        Value::String(name) => match name.as_str() {
+            "bool" => out.push(1),
+            "u8" => out.push(7),
+            "u16" => out.extend(7u16.to_le_bytes()),
+            "u32" => out.extend(7u32.to_le_bytes()),
+            "u64" => out.extend(7u64.to_le_bytes()),
+            "i64" => out.extend(7i64.to_le_bytes()),
+            "string" => {
+                out.extend(1u32.to_le_bytes());
+                out.push(b'a');
+            }
+            "bytes" => {
+                out.extend(2u32.to_le_bytes());
+                out.extend([1, 2]);
+            }
+            "publicKey" => out.extend([5; 32]),
+            other => panic!("unsupported IDL type {other}"),
+        },
+        Value::Object(obj) => {
+            if let Some(defined) = obj.get("defined") {
+                encode_defined(idl, find_type(idl, defined.as_str().unwrap()), out);
+            } else if let Some(array) = obj.get("array") {
+                for _ in 0..array[1].as_u64().unwrap() {
+                    encode_sample(idl, &array[0], out);
+                }
+            } else if let Some(inner) = obj.get("option") {
+                out.push(1);
+                encode_sample(idl, inner, out);
+            } else if let Some(inner) = obj.get("vec") {
+                out.extend(1u32.to_le_bytes());
+                encode_sample(idl, inner, out);
+            } else {
+                panic!("unsupported IDL type {ty}");
+            }
+        }
+        _ => panic!("unsupported IDL type {ty}"),
+    }
+}
+
+fn encode_defined(idl: &Value, def: &Value, out: &mut Vec<u8>) {
+    let ty = &def["type"];
+    match ty["kind"].as_str().unwrap() {
+        "struct" => {
+            for field in ty["fields"].as_array().unwrap() {
+                encode_sample(idl, &field["type"], out);
+            }
+        }
+        "enum" => {
+            // The last variant is sampled, as it usually carries the most data.
+            let variants = ty["variants"].as_array().unwrap();
+            let variant = variants.last().unwrap();
+            out.push((variants.len() - 1) as u8);
+            if let Some(fields) = variant["fields"].as_array() {
+                for field in fields {
+                    encode_sample(idl, &field["type"], out);
+                }
+            }
+        }
+        kind => panic!("unsupported IDL kind {kind}"),
+    }
+}
+
+fn snake_case(name: &str) -> String {
+    name.chars()
+        .flat_map(|c| {
+            if c.is_ascii_uppercase() {
+                vec!['_', c.to_ascii_lowercase()]
+            } else {
+                vec![c]
+            }
+        })
+        .collect()
+}
+
+fn pascal_case(name: &str) -> String {
+    let mut chars = name.chars();
+    let first = chars.next().unwrap().to_ascii_uppercase();
+    std::iter::once(first).chain(chars).collect()
+}
+
+/// Field names of the outermost struct in a `{:?}` rendering.
+fn debug_field_names(debug: &str) -> Vec<String> {
+    let start = debug.find('{').unwrap() + 1;
+    let end = debug.rfind('}').unwrap();
+
+    let mut names = Vec::new();
+    let mut depth = 0;
+    let mut field = String::new();
+    for c in debug[start..end].chars().chain(std::iter::once(',')) {
+        match c {
+            '{' | '[' | '(' => depth += 1,
+            '}' | ']' | ')' => depth -= 1,
+            ',' if depth == 0 => {
+                let name = field.split(':').next().unwrap().trim();
+                if !name.is_empty() {
+                    names.push(name.to_string());
+                }
+                field.clear();
+                continue;
+            }
+            _ => {}
+        }
+        field.push(c);
+    }
+    names
+}
+
+fn idl_field_names(def: &Value) -> Vec<String> {
+    def["type"]["fields"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|field| snake_case(field["name"].as_str().unwrap()))
+        .collect()
+}
+
+#[test]
+fn test_idl_address() {
+    let idl = idl();
+    assert_eq!(idl["metadata"]["address"], program_id().to_string());
+}
+
+#[test]
+fn test_idl_instructions_decode() {
+    let idl = idl();
+    let instructions = idl["instructions"].as_array().unwrap();
+    let variants = (0..=u8::MAX).filter(|&d| is_variant(d)).count();
+    assert_eq!(instructions.len(), variants, "IDL misses instructions");
+
+    for (i, ix) in instructions.iter().enumerate() {
+        let name = ix["name"].as_str().unwrap();
+        let discriminant = ix["discriminant"]["value"].as_u64().unwrap();
+        assert_eq!(discriminant, i as u64, "{name} discriminant out of order");
+
+        let mut data = vec![discriminant as u8];
+        for arg in ix["args"].as_array().unwrap() {
+            encode_sample(&idl, &arg["type"], &mut data);
+        }
+
+        let decoded = SolDbIntructions::unpack(&data)
+            .unwrap_or_else(|e| panic!("{name} args do not match the program: {e:?}"));
+        assert_eq!(idl_name(&decoded), name);
+        assert_eq!(
+            borsh::to_vec(&decoded).unwrap(),
+            data,
+            "{name} does not round-trip"
+        );
+
+        let arg_type = ix["args"][0]["type"]["defined"].as_str().unwrap();
+        let debug = format!("{decoded:?}");
+        assert!(debug.starts_with(&format!("{}(", pascal_case(name))));
+        assert_eq!(
+            debug_field_names(&debug),
+            idl_field_names(find_type(&idl, arg_type)),
+            "{name} field names differ"
+        );
+    }
+}
+
+#[test]
+fn test_idl_instruction_accounts() {
+    let idl = idl();
+
+    for ix in idl["instructions"].as_array().unwrap() {
+        let name = ix["name"].as_str().unwrap();
+        // Optional accounts depend on the table, and builders leave them to
+        // the caller.
+        let expected: Vec<(bool, bool)> = ix["accounts"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|a| a["isOptional"].as_bool() != Some(true))
+            .map(|a| {
+                (
+                    a["isMut"].as_bool().unwrap(),
+                    a["isSigner"].as_bool().unwrap(),
+                )
+            })
+            .collect();
+        let built = build(name);
+        let decoded = SolDbIntructions::unpack(&built.data).unwrap();
+        assert_eq!(
+            idl_name(&decoded),
+            name,
+            "{name} builds another instruction"
+        );
+
+        let actual: Vec<(bool, bool)> = built
+            .accounts
+            .iter()
+            .map(|meta| (meta.is_writable, meta.is_signer))
+            .collect();
+
+        assert_eq!(actual, expected, "{name} accounts differ from its builder");
+    }
+}
+
+#[test]
+fn test_idl_account_layouts() {
+    let idl = idl();
+
+    for account in idl["accounts"].as_array().unwrap() {
+        let name = account["name"].as_str().unwrap();
+        let mut data = Vec::new();
+        encode_defined(&idl, account, &mut data);
+
+        let debug = match name {
+            "SolTable" => format!("{:?}", borsh::from_slice::<SolTable>(&data).unwrap()),
+            "SolValue" => format!("{:?}", bytemuck::pod_read_unaligned::<SolValue>(&data)),
+            "SolUpload" => format!("{:?}", borsh::from_slice::<SolUpload>(&data).unwrap()),
+            "SolSlab" => format!("{:?}", bytemuck::pod_read_unaligned::<SolSlab>(&data)),
+            "SolDirectory" => format!("{:?}", bytemuck::pod_read_unaligned::<SolDirectory>(&data)),
+            "SolTree" => format!("{:?}", bytemuck::pod_read_unaligned::<SolTree>(&data)),
+            "SolBloom" => format!("{:?}", bytemuck::pod_read_unaligned::<SolBloom>(&data)),
+            "SolNonce" => format!("{:?}", bytemuck::pod_read_unaligned::<SolNonce>(&data)),
+            "SolMultisig" => format!("{:?}", bytemuck::pod_read_unaligned::<SolMultisig>(&data)),
+            "SolAcl" => format!("{:?}", bytemuck::pod_read_unaligned::<SolAcl>(&data)),
+            "SolDelegation" => {
+                format!("{:?}", bytemuck::pod_read_unaligned::<SolDelegation>(&data))
+            }
+            "SolValueAuthority" => {
+                format!(
+                    "{:?}",
+                    bytemuck::pod_read_unaligned::<SolValueAuthority>(&data)
+                )
+            }
+            _ => panic!("unknown IDL account {name}"),
+        };
+        assert_eq!(debug_field_names(&debug), idl_field_names(account));
+    }
+}
+
+#[test]
+fn test_idl_errors() {
+    let idl = idl();
+    let errors = idl["errors"].as_array().unwrap();
+
+    for error in errors {
+        let code = error["code"].as_u64().unwrap() as u32;
+        let decoded = SolDbError::try_from(code).unwrap();
+        assert_eq!(format!("{decoded:?}"), error["name"].as_str().unwrap());
+        assert_eq!(decoded.message(), error["msg"].as_str().unwrap());
+    }
+
+    assert!(
+        SolDbError::try_from(errors.len() as u32).is_err(),
+        "every SolDbError must be listed in the IDL"
+    );
+}
@@ -1,6 +1,6 @@
 mod utils;
 
-use borsh::{BorshDeserialize, BorshSerialize};
+use borsh::BorshSerialize;
 use solana_program_test::*;
 use solana_sdk::{
     instruction::{AccountMeta, Instruction},
@@ -11,7 +11,11 @@ use solana_sdk::{
 };
 use solana_system_interface::program;
 
-use soldb_program::{accounts::SolTable, instructions::SolDbIntructions};
+use soldb_program::{
+    accounts::{SolTable, TableMode},
+    error::SolDbError,
+    instructions::SolDbIntructions,
+};
 
 #[tokio::test]
 async fn test_init_table_success() -> Result<(), TransportError> {
@@ -20,9 +24,9 @@ async fn test_init_table_success() -> Result<(), TransportError> {
     let program_id = soldb_program::id();
 
     let name = "Test".to_string();
-    let table = SolTable { name: name.clone() };
-    let (pda_pubkey, _bump) =
-        utils::init_table(&banks_client, &payer, last_blockhash, &table).await?;
+    let (pda_pubkey, bump) =
+        utils::init_table(&banks_client, &payer, last_blockhash, &name).await?;
+    let table = SolTable::new(bump, TableMode::Dedicated, name.clone());
 
     let mut serialized = Vec::new();
     table.serialize(&mut serialized)?;
@@ -54,8 +58,9 @@ async fn test_init_table_success() -> Result<(), TransportError> {
         "PDA account data length mismatch"
     );
 
-    let sol_table = SolTable::deserialize(&mut account.data.as_slice()).unwrap();
+    let sol_table = SolTable::load(&account.data).unwrap();
     assert_eq!(sol_table.name, name);
+    assert_eq!(sol_table.bump, bump);
 
     Ok(())
 }
@@ -69,19 +74,13 @@ async fn test_init_table_pda_table_mismatch() -> Result<(), TransportError> {
     let name = "Test".to_string();
     let wrong_name = "Wrong".to_string();
 
-    let table = SolTable { name: name.clone() };
-    let wrong_table = SolTable {
-        name: wrong_name.clone(),
-    };
-
-    let (pda_pubkey, bump) = Pubkey::find_program_address(
-        &[table.name.as_bytes(), payer.pubkey().as_ref()],
-        &program_id,
-    );
+    let (pda_pubkey, bump) =
+        Pubkey::find_program_address(&[name.as_bytes(), payer.pubkey().as_ref()], &program_id);
 
     let instr = SolDbIntructions::InitTable(soldb_program::instructions::InitTable {
-        name: wrong_table.name.clone(),
+        name: wrong_name.clone(),
         bump,
+        mode: TableMode::Dedicated,
     });
     let mut ix_data = Vec::new();
     instr.serialize(&mut ix_data).unwrap();
@@ -112,10 +111,10 @@ async fn test_init_table_pda_table_mismatch() -> Result<(), TransportError> {
         solana_sdk::transaction::TransactionError::InstructionError(_, instr_err) => {
             assert_eq!(
                 instr_err,
-                solana_sdk::instruction::InstructionError::InvalidSeeds
+                solana_sdk::instruction::InstructionError::Custom(SolDbError::PdaMismatch.code())
             );
         }
-        _ => panic!("expected InvalidSeeds, got {:?}", err),
+        _ => panic!("expected PdaMismatch, got {:?}", err),
     }
     Ok(())
 }
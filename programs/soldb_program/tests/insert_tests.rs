@@ -1,30 +1,39 @@
 mod utils;
 
-use borsh::{BorshDeserialize, BorshSerialize};
+use borsh::BorshSerialize;
 use solana_program_test::*;
-use solana_sdk::transport::TransportError;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signer::Signer,
+    transaction::Transaction,
+    transport::TransportError,
+};
+use solana_system_interface::program;
 
-use soldb_program::accounts::{SolTable, SolValue};
+use soldb_program::{
+    accounts::{MAX_KEY_LEN, SolValue},
+    error::SolDbError,
+    id as program_id,
+    instructions::{Insert, SolDbIntructions},
+};
 
 #[tokio::test]
 async fn test_insert() -> Result<(), TransportError> {
     let (banks_client, payer, last_blockhash) = utils::setup().await?;
-    let program_id = soldb_program::id();
+    let program_id = program_id();
 
-    let name = "Test".to_string();
-    let table = SolTable { name };
     let (pda_table_pubkey, _bump) =
-        utils::init_table(&banks_client, &payer, last_blockhash, &table).await?;
+        utils::init_table(&banks_client, &payer, last_blockhash, "Test").await?;
 
     let value: Vec<u8> = "v-0".into();
-    let sol_value = SolValue { val: value.clone() };
     let (pda_val_pubkey, _bump) = utils::insert(
         &banks_client,
         &payer,
         last_blockhash,
         &pda_table_pubkey,
         "k-0".into(),
-        &sol_value,
+        &value,
     )
     .await?;
 
@@ -32,12 +41,9 @@ async fn test_insert() -> Result<(), TransportError> {
     assert!(maybe_account.is_some(), "PDA account was not created");
 
     let account = maybe_account.unwrap();
-    let sol_value = SolValue::deserialize(&mut account.data.as_slice()).unwrap();
-    assert_eq!(sol_value.val, value);
+    assert_eq!(SolValue::payload(&account.data).unwrap(), value);
 
-    let mut serialized = Vec::new();
-    sol_value.serialize(&mut serialized)?;
-    let expected_space = serialized.len();
+    let expected_space = SolValue::space(value.len());
     let rent = banks_client
         .get_rent()
         .await
@@ -62,3 +68,75 @@ async fn test_insert() -> Result<(), TransportError> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_insert_existing_key() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = utils::setup().await?;
+
+    let (pda_table_pubkey, _bump) =
+        utils::init_table(&banks_client, &payer, last_blockhash, "Test").await?;
+
+    utils::insert(
+        &banks_client,
+        &payer,
+        last_blockhash,
+        &pda_table_pubkey,
+        "k-0".into(),
+        b"v-0",
+    )
+    .await?;
+
+    let err = utils::insert(
+        &banks_client,
+        &payer,
+        last_blockhash,
+        &pda_table_pubkey,
+        "k-0".into(),
+        b"v-1",
+    )
+    .await
+    .unwrap_err();
+
+    assert_eq!(
+        utils::custom_error(err),
+        Some(SolDbError::AccountAlreadyExists)
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_insert_key_too_long() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = utils::setup().await?;
+
+    let (pda_table_pubkey, _bump) =
+        utils::init_table(&banks_client, &payer, last_blockhash, "Test").await?;
+
+    let instr = SolDbIntructions::Insert(Insert {
+        key: vec![7; MAX_KEY_LEN + 1],
+        payload: "v-0".into(),
+        bump: 0,
+        capacity: 0,
+    });
+    let mut ix_data = Vec::new();
+    instr.serialize(&mut ix_data).unwrap();
+
+    let ix = Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(pda_table_pubkey, false),
+            AccountMeta::new(Pubkey::new_unique(), false),
+            AccountMeta::new_readonly(program::ID, false),
+            AccountMeta::new(Pubkey::new_unique(), false),
+        ],
+        data: ix_data,
+    };
+    let txn =
+        Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[&payer], last_blockhash);
+    let err = banks_client.process_transaction(txn).await.unwrap_err();
+
+    assert_eq!(utils::custom_error(err), Some(SolDbError::KeyTooLong));
+
+    Ok(())
+}
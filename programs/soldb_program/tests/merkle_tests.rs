@@ -0,0 +1,156 @@
+mod utils;
+
+use solana_program_test::*;
+use solana_sdk::{
+    hash::hash, instruction::Instruction, pubkey::Pubkey, signer::Signer, transport::TransportError,
+};
+use soldb_program::{
+    accounts::{SolDirectory, SolTable},
+    error::SolDbError,
+    id as program_id, instructions, merkle,
+    pda::{find_directory_address, find_table_address},
+};
+use utils::{process, setup};
+
+fn key(i: usize) -> Vec<u8> {
+    format!("k-{i}").into_bytes()
+}
+
+async fn table_state(
+    banks_client: &BanksClient,
+    table: &Pubkey,
+) -> Result<SolTable, TransportError> {
+    let account = banks_client.get_account(*table).await?.unwrap();
+    Ok(SolTable::load(&account.data).unwrap())
+}
+
+/// Builds the `VerifyProof` instruction for the key at directory position
+/// `index` of the first page, from the accounts as they are now.
+async fn verify(
+    banks_client: &BanksClient,
+    table: &Pubkey,
+    index: usize,
+    value: &[u8],
+) -> Result<Instruction, TransportError> {
+    let (directory, _) = find_directory_address(&program_id(), table, 0);
+    let account = banks_client.get_account(directory).await?.unwrap();
+    let entry = SolDirectory::entry(&account.data, index);
+    let sol_table = table_state(banks_client, table).await?;
+
+    let mut proof = merkle::proof(&SolDirectory::leaves(&account.data), index);
+    proof.extend(merkle::proof(&sol_table.page_roots, 0));
+
+    Ok(instructions::verify_proof(
+        &program_id(),
+        table,
+        &entry.writer,
+        entry.key(),
+        hash(value).to_bytes(),
+        index as u32,
+        proof,
+    ))
+}
+
+#[tokio::test]
+async fn test_verify_proof() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let (table, _) = utils::init_table(&banks_client, &payer, last_blockhash, "Test").await?;
+    assert_eq!(
+        table_state(&banks_client, &table).await?.root,
+        merkle::EMPTY
+    );
+    for i in 0..3 {
+        utils::insert(
+            &banks_client,
+            &payer,
+            last_blockhash,
+            &table,
+            key(i),
+            b"v-0",
+        )
+        .await?;
+    }
+
+    let ix = verify(&banks_client, &table, 1, b"v-0").await?;
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let ix = verify(&banks_client, &table, 1, b"v-2").await?;
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::InvalidProof));
+
+    // The leaf commits to the writer of the value, which follows the
+    // instruction tag.
+    let mut ix = verify(&banks_client, &table, 1, b"v-0").await?;
+    ix.data[1..33].copy_from_slice(Pubkey::new_unique().as_ref());
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::InvalidProof));
+
+    // Overwriting a value moves the root, which leaves the proofs of the
+    // other keys stale.
+    let stale = verify(&banks_client, &table, 0, b"v-0").await?;
+    let root = table_state(&banks_client, &table).await?.root;
+    let ix = instructions::put(&program_id, &owner, "Test", &key(1), b"v-1", 0);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    assert_ne!(table_state(&banks_client, &table).await?.root, root);
+
+    let err = process(&banks_client, &payer, last_blockhash, stale)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::InvalidProof));
+    let ix = verify(&banks_client, &table, 1, b"v-1").await?;
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    // The last key moves into the entry of a deleted one.
+    let ix = instructions::delete(&program_id, &owner, "Test", &key(0), 0, 3);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let sol_table = table_state(&banks_client, &table).await?;
+    assert_eq!(sol_table.keys, 2);
+    assert_eq!(sol_table.root, merkle::root(&sol_table.page_roots));
+    let ix = verify(&banks_client, &table, 0, b"v-0").await?;
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_packed_tables_cannot_be_proven() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let ix = instructions::init_packed_table(&program_id, &owner, "Test", 1);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    let ix = instructions::packed_insert(&program_id, &owner, "Test", &key(0), b"v-0", 1);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    // Slab entries are never committed, so the root stays empty.
+    let (table, _) = find_table_address(&program_id, &owner, "Test");
+    assert_eq!(
+        table_state(&banks_client, &table).await?.root,
+        merkle::EMPTY
+    );
+
+    let ix = instructions::verify_proof(
+        &program_id,
+        &table,
+        &owner,
+        &key(0),
+        hash(b"v-0").to_bytes(),
+        0,
+        vec![merkle::EMPTY; merkle::PAGE_DEPTH],
+    );
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::WrongTableMode));
+
+    Ok(())
+}
@@ -0,0 +1,248 @@
+mod utils;
+
+use solana_program_test::*;
+use solana_sdk::{
+    hash::Hash, instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer,
+    transport::TransportError,
+};
+use solana_system_interface::instruction::transfer;
+use soldb_program::{
+    accounts::SolAcl,
+    error::SolDbError,
+    id as program_id, instructions,
+    pda::{
+        find_global_value_address, find_table_address, find_value_address,
+        find_value_authority_address,
+    },
+};
+use utils::{process, setup};
+
+/// Creates a collaborator holding enough lamports to pay for its writes.
+async fn collaborator(
+    banks_client: &BanksClient,
+    payer: &Keypair,
+    last_blockhash: Hash,
+) -> Result<Keypair, TransportError> {
+    let collaborator = Keypair::new();
+    let fund = transfer(&payer.pubkey(), &collaborator.pubkey(), 1_000_000_000);
+    process(banks_client, payer, last_blockhash, fund).await?;
+    Ok(collaborator)
+}
+
+/// Grants `roles` on the table `Test` of the payer to `principal`.
+async fn grant(
+    banks_client: &BanksClient,
+    payer: &Keypair,
+    last_blockhash: Hash,
+    principal: &Pubkey,
+    roles: u8,
+) -> Result<(), TransportError> {
+    let owner = payer.pubkey();
+    let ix = instructions::grant_roles(&program_id(), &owner, &owner, "Test", principal, roles);
+    process(banks_client, payer, last_blockhash, ix).await
+}
+
+/// Adapts the `Insert` instruction `ix` to be signed by `writer`, holding the
+/// inserter role.
+fn insert_as(writer: &Keypair, ix: Instruction) -> Instruction {
+    instructions::with_role(&program_id(), ix, &writer.pubkey())
+}
+
+#[tokio::test]
+async fn test_moderator_closes_claimed_values() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let (table, _) = utils::init_table(&banks_client, &payer, last_blockhash, "Test").await?;
+    let writer = collaborator(&banks_client, &payer, last_blockhash).await?;
+    let moderator = collaborator(&banks_client, &payer, last_blockhash).await?;
+    grant(
+        &banks_client,
+        &payer,
+        last_blockhash,
+        &writer.pubkey(),
+        SolAcl::INSERTER,
+    )
+    .await?;
+    grant(
+        &banks_client,
+        &payer,
+        last_blockhash,
+        &moderator.pubkey(),
+        SolAcl::MODERATOR,
+    )
+    .await?;
+
+    let ix = instructions::insert(&program_id, &table, &owner, b"k-0", b"spam", 0);
+    process(
+        &banks_client,
+        &writer,
+        last_blockhash,
+        insert_as(&writer, ix),
+    )
+    .await?;
+    let ix = instructions::set_value_authority(
+        &program_id,
+        &owner,
+        &owner,
+        "Test",
+        b"k-0",
+        Some(writer.pubkey()),
+    );
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let treasury = Pubkey::new_unique();
+    let ix = instructions::set_moderation_destination(&program_id, &owner, "Test", Some(treasury));
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    // The lamports only go to the configured destination.
+    let ix = instructions::moderate(
+        &program_id,
+        &moderator.pubkey(),
+        &owner,
+        &owner,
+        "Test",
+        b"k-0",
+        0,
+        1,
+        &moderator.pubkey(),
+    );
+    let err = process(&banks_client, &moderator, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        utils::custom_error(err),
+        Some(SolDbError::InvalidDestination)
+    );
+
+    let ix = instructions::moderate(
+        &program_id,
+        &writer.pubkey(),
+        &owner,
+        &owner,
+        "Test",
+        b"k-0",
+        0,
+        1,
+        &treasury,
+    );
+    let err = process(&banks_client, &writer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::MissingRole));
+
+    let (value, _) = find_value_address(&program_id, &table, &owner, b"k-0");
+    let (record, _) = find_value_authority_address(&program_id, &value);
+    let rent = banks_client.get_account(value).await?.unwrap().lamports;
+
+    let ix = instructions::moderate(
+        &program_id,
+        &moderator.pubkey(),
+        &owner,
+        &owner,
+        "Test",
+        b"k-0",
+        0,
+        1,
+        &treasury,
+    );
+    process(&banks_client, &moderator, last_blockhash, ix).await?;
+
+    assert!(banks_client.get_account(value).await?.is_none());
+    assert!(banks_client.get_account(record).await?.is_none());
+    assert_eq!(banks_client.get_balance(treasury).await?, rent);
+    assert_eq!(utils::table_keys(&banks_client, &table).await?, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_owner_moderates_global_tables() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let ix = instructions::init_global_table(&program_id, &owner, "Test");
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    let writer = collaborator(&banks_client, &payer, last_blockhash).await?;
+    grant(
+        &banks_client,
+        &payer,
+        last_blockhash,
+        &writer.pubkey(),
+        SolAcl::INSERTER,
+    )
+    .await?;
+
+    let (table, _) = find_table_address(&program_id, &owner, "Test");
+    let ix = instructions::global_insert(&program_id, &table, &owner, b"k-0", b"spam", 0);
+    process(
+        &banks_client,
+        &writer,
+        last_blockhash,
+        insert_as(&writer, ix),
+    )
+    .await?;
+
+    // Without a destination, the owner receives the lamports.
+    let ix =
+        instructions::global_moderate(&program_id, &owner, &owner, "Test", b"k-0", 0, 1, &owner);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let (value, _) = find_global_value_address(&program_id, &table, b"k-0");
+    assert!(banks_client.get_account(value).await?.is_none());
+    assert_eq!(utils::table_keys(&banks_client, &table).await?, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_owner_moderates_values_of_other_writers() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let (table, _) = utils::init_table(&banks_client, &payer, last_blockhash, "Test").await?;
+    let spammer = collaborator(&banks_client, &payer, last_blockhash).await?;
+
+    // Any signer may insert under its own keys, out of reach of `Delete`.
+    let ix = instructions::insert(&program_id, &table, &spammer.pubkey(), b"k-0", b"spam", 0);
+    process(&banks_client, &spammer, last_blockhash, ix).await?;
+
+    // The value is derived from its writer, not from the owner.
+    let ix = instructions::moderate(
+        &program_id,
+        &owner,
+        &owner,
+        &owner,
+        "Test",
+        b"k-0",
+        0,
+        1,
+        &owner,
+    );
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::PdaMismatch));
+
+    let ix = instructions::moderate(
+        &program_id,
+        &owner,
+        &owner,
+        &spammer.pubkey(),
+        "Test",
+        b"k-0",
+        0,
+        1,
+        &owner,
+    );
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let (value, _) = find_value_address(&program_id, &table, &spammer.pubkey(), b"k-0");
+    assert!(banks_client.get_account(value).await?.is_none());
+    assert_eq!(utils::table_keys(&banks_client, &table).await?, 0);
+
+    Ok(())
+}
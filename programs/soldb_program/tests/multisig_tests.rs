@@ -0,0 +1,185 @@
+mod utils;
+
+use solana_program_test::*;
+use solana_sdk::{
+    hash::Hash, instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer,
+    transaction::Transaction, transport::TransportError,
+};
+use solana_system_interface::instruction::transfer;
+use soldb_program::{
+    accounts::{SolMultisig, SolValue},
+    error::SolDbError,
+    id as program_id, instructions,
+    pda::{find_multisig_address, find_table_address, find_value_address},
+};
+use utils::{process, setup};
+
+/// Processes `ix` signed by `payer` and the `approvers` of the multisig.
+async fn submit(
+    banks_client: &BanksClient,
+    payer: &Keypair,
+    last_blockhash: Hash,
+    approvers: &[&Keypair],
+    ix: Instruction,
+) -> Result<(), TransportError> {
+    let mut signers = vec![payer];
+    signers.extend_from_slice(approvers);
+    let txn =
+        Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &signers, last_blockhash);
+    banks_client.process_transaction(txn).await?;
+
+    Ok(())
+}
+
+/// Processes `ix`, built with the multisig as owner, approved by
+/// `approvers`.
+async fn approve(
+    banks_client: &BanksClient,
+    payer: &Keypair,
+    last_blockhash: Hash,
+    approvers: &[&Keypair],
+    ix: Instruction,
+) -> Result<(), TransportError> {
+    let keys: Vec<Pubkey> = approvers.iter().map(|approver| approver.pubkey()).collect();
+    let ix = instructions::with_multisig(ix, &keys);
+    submit(banks_client, payer, last_blockhash, approvers, ix).await
+}
+
+/// Creates the 2-of-3 multisig `Config` funded for a few accounts, and its
+/// table `Test`.
+async fn setup_multisig(
+    banks_client: &BanksClient,
+    payer: &Keypair,
+    last_blockhash: Hash,
+    signers: &[Keypair; 3],
+) -> Result<(Pubkey, Pubkey), TransportError> {
+    let program_id = program_id();
+    let keys: Vec<Pubkey> = signers.iter().map(|signer| signer.pubkey()).collect();
+
+    let ix = instructions::create_multisig(&program_id, &payer.pubkey(), "Config", 2, &keys);
+    process(banks_client, payer, last_blockhash, ix).await?;
+    let (multisig, _) = find_multisig_address(&program_id, &payer.pubkey(), "Config");
+
+    let fund = transfer(&payer.pubkey(), &multisig, 1_000_000_000);
+    process(banks_client, payer, last_blockhash, fund).await?;
+
+    let ix = instructions::init_table(&program_id, &multisig, "Test");
+    approve(
+        banks_client,
+        payer,
+        last_blockhash,
+        &[&signers[0], &signers[1]],
+        ix,
+    )
+    .await?;
+    let (table, _) = find_table_address(&program_id, &multisig, "Test");
+
+    Ok((multisig, table))
+}
+
+#[tokio::test]
+async fn test_multisig_owns_table() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let signers = [Keypair::new(), Keypair::new(), Keypair::new()];
+
+    let (multisig, table) = setup_multisig(&banks_client, &payer, last_blockhash, &signers).await?;
+    let (value, _) = find_value_address(&program_id, &table, &multisig, b"k-0");
+
+    let ix = instructions::insert(&program_id, &table, &multisig, b"k-0", b"v-0", 0);
+    approve(
+        &banks_client,
+        &payer,
+        last_blockhash,
+        &[&signers[1], &signers[2]],
+        ix,
+    )
+    .await?;
+
+    let account = banks_client.get_account(value).await?.unwrap();
+    assert_eq!(SolValue::payload(&account.data).unwrap(), b"v-0");
+
+    // One signer alone is below the threshold.
+    let ix = instructions::put(&program_id, &multisig, "Test", b"k-0", b"v-1", 0);
+    let err = approve(&banks_client, &payer, last_blockhash, &[&signers[0]], ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::MissingSigner));
+
+    // Keys that are not listed do not count.
+    let outsider = Keypair::new();
+    let ix = instructions::put(&program_id, &multisig, "Test", b"k-0", b"v-2", 0);
+    let err = approve(
+        &banks_client,
+        &payer,
+        last_blockhash,
+        &[&signers[0], &outsider],
+        ix,
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::MissingSigner));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_set_multisig_signers() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let signers = [Keypair::new(), Keypair::new(), Keypair::new()];
+    let (multisig, table) = setup_multisig(&banks_client, &payer, last_blockhash, &signers).await?;
+
+    let replacement = Keypair::new();
+    let ix = instructions::set_multisig_signers(
+        &program_id,
+        &multisig,
+        1,
+        &[replacement.pubkey()],
+        &[signers[0].pubkey(), signers[2].pubkey()],
+    );
+    submit(
+        &banks_client,
+        &payer,
+        last_blockhash,
+        &[&signers[0], &signers[2]],
+        ix,
+    )
+    .await?;
+
+    let account = banks_client.get_account(multisig).await?.unwrap();
+    let stored = SolMultisig::load(&account.data).unwrap();
+    assert_eq!(stored.threshold, 1);
+    assert_eq!(stored.signers(), [replacement.pubkey()]);
+
+    // The former signers lost control of the table.
+    let ix = instructions::insert(&program_id, &table, &multisig, b"k-0", b"v-0", 0);
+    let err = approve(
+        &banks_client,
+        &payer,
+        last_blockhash,
+        &[&signers[0], &signers[1]],
+        ix,
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::MissingSigner));
+
+    let ix = instructions::insert(&program_id, &table, &multisig, b"k-0", b"v-1", 0);
+    approve(&banks_client, &payer, last_blockhash, &[&replacement], ix).await?;
+
+    // A threshold above the number of signers is rejected.
+    let ix = instructions::set_multisig_signers(
+        &program_id,
+        &multisig,
+        2,
+        &[replacement.pubkey()],
+        &[replacement.pubkey()],
+    );
+    let err = submit(&banks_client, &payer, last_blockhash, &[&replacement], ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::InvalidMultisig));
+
+    Ok(())
+}
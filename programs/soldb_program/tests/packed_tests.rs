@@ -0,0 +1,145 @@
+mod utils;
+
+use solana_program_test::*;
+use solana_sdk::{pubkey::Pubkey, signer::Signer, transport::TransportError};
+use soldb_program::{
+    accounts::{EntryState, MAX_ENTRY_LEN, SlabEntry, SolSlab, SolValue},
+    error::SolDbError,
+    id as program_id, instructions,
+    pda::{find_slab_address, find_table_address, find_value_address},
+};
+use utils::{process, setup};
+
+const SLABS: u8 = 4;
+
+async fn slab_entry(
+    banks_client: &BanksClient,
+    table: &Pubkey,
+    key: &[u8],
+) -> Result<Option<SlabEntry>, TransportError> {
+    let index = SolSlab::index_of(key, SLABS);
+    let (slab, _) = find_slab_address(&program_id(), table, index);
+    let account = banks_client.get_account(slab).await?.unwrap();
+
+    Ok(SolSlab::find(&account.data, key).map(|slot| SolSlab::entry(&account.data, slot)))
+}
+
+#[tokio::test]
+async fn test_packed_insert_put_delete() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let ix = instructions::init_packed_table(&program_id, &owner, "Test", SLABS);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    let (table, _) = find_table_address(&program_id, &owner, "Test");
+
+    for i in 0..8u8 {
+        let key = [b'k', i];
+        let ix = instructions::packed_insert(&program_id, &owner, "Test", &key, &[i], SLABS);
+        process(&banks_client, &payer, last_blockhash, ix).await?;
+    }
+
+    let ix = instructions::packed_put(&program_id, &owner, "Test", b"k\x03", b"v-3", SLABS);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let entry = slab_entry(&banks_client, &table, b"k\x03").await?.unwrap();
+    assert_eq!(entry.value(), b"v-3");
+
+    let ix = instructions::packed_delete(&program_id, &owner, "Test", b"k\x03", SLABS);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    assert!(slab_entry(&banks_client, &table, b"k\x03").await?.is_none());
+    for i in (0..8u8).filter(|i| *i != 3) {
+        let entry = slab_entry(&banks_client, &table, &[b'k', i])
+            .await?
+            .unwrap();
+        assert_eq!(entry.value(), [i]);
+    }
+
+    let ix = instructions::packed_put(&program_id, &owner, "Test", b"k\x03", b"v", SLABS);
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::KeyNotFound));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_packed_table_rejects_dedicated_values() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let ix = instructions::init_packed_table(&program_id, &owner, "Test", SLABS);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let ix = instructions::upsert(&program_id, &owner, "Test", b"k-0", b"v-0", 0, 0);
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::WrongTableMode));
+
+    let ix = instructions::packed_insert(
+        &program_id,
+        &owner,
+        "Test",
+        b"k-0",
+        &[0; MAX_ENTRY_LEN + 1],
+        SLABS,
+    );
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::EntryTooLarge));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_migrate_entry() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let ix = instructions::init_packed_table(&program_id, &owner, "Test", SLABS);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    let (table, _) = find_table_address(&program_id, &owner, "Test");
+    let (value, _) = find_value_address(&program_id, &table, &owner, b"k-0");
+
+    let ix = instructions::packed_insert(&program_id, &owner, "Test", b"k-0", b"v-0", SLABS);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let ix = instructions::migrate(&program_id, &owner, "Test", b"k-0", SLABS, 0);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let entry = slab_entry(&banks_client, &table, b"k-0").await?.unwrap();
+    assert_eq!(entry.state(), EntryState::Migrated);
+    let account = banks_client.get_account(value).await?.unwrap();
+    assert_eq!(SolValue::payload(&account.data).unwrap(), b"v-0");
+
+    let ix = instructions::packed_put(&program_id, &owner, "Test", b"k-0", b"v-1", SLABS);
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::EntryMigrated));
+
+    let payload = vec![7; 4 * MAX_ENTRY_LEN];
+    let ix = instructions::put(&program_id, &owner, "Test", b"k-0", &payload, 0);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let account = banks_client.get_account(value).await?.unwrap();
+    assert_eq!(SolValue::payload(&account.data).unwrap(), payload);
+
+    let ix = instructions::delete_migrated(&program_id, &owner, "Test", b"k-0", SLABS);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    assert!(banks_client.get_account(value).await?.is_none());
+    assert!(slab_entry(&banks_client, &table, b"k-0").await?.is_none());
+
+    let ix = instructions::packed_insert(&program_id, &owner, "Test", b"k-0", b"v-2", SLABS);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    Ok(())
+}
@@ -0,0 +1,145 @@
+mod utils;
+
+use solana_program_test::*;
+use solana_sdk::{
+    hash::Hash, instruction::Instruction, pubkey::Pubkey, rent::Rent, signature::Keypair,
+    signer::Signer, transport::TransportError,
+};
+use soldb_program::{accounts::SolValue, error::SolDbError, id as program_id, instructions};
+use utils::setup;
+
+struct Fixture {
+    banks_client: BanksClient,
+    payer: Keypair,
+    last_blockhash: Hash,
+    value: Pubkey,
+}
+
+async fn fixture(initial: &[u8]) -> Result<Fixture, TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+
+    let (table_pubkey, _table_bump) =
+        utils::init_table(&banks_client, &payer, last_blockhash, "Test").await?;
+
+    let (value_pubkey, _value_bump) = utils::insert(
+        &banks_client,
+        &payer,
+        last_blockhash,
+        &table_pubkey,
+        "k-0".into(),
+        initial,
+    )
+    .await?;
+
+    Ok(Fixture {
+        banks_client,
+        payer,
+        last_blockhash,
+        value: value_pubkey,
+    })
+}
+
+async fn send(fx: &Fixture, ix: Instruction) -> Result<(), TransportError> {
+    utils::process(&fx.banks_client, &fx.payer, fx.last_blockhash, ix).await
+}
+
+async fn stored(fx: &Fixture) -> (Vec<u8>, u64, usize) {
+    let account = fx
+        .banks_client
+        .get_account(fx.value)
+        .await
+        .unwrap()
+        .unwrap();
+    let payload = SolValue::payload(&account.data).unwrap().to_vec();
+    (payload, account.lamports, account.data.len())
+}
+
+#[tokio::test]
+async fn test_patch() -> Result<(), TransportError> {
+    let fx = fixture(b"hello world").await?;
+
+    send(
+        &fx,
+        instructions::patch(
+            &program_id(),
+            &fx.payer.pubkey(),
+            "Test",
+            b"k-0",
+            6,
+            b"solana!",
+            0,
+        ),
+    )
+    .await?;
+
+    let (val, lamports, len) = stored(&fx).await;
+    assert_eq!(val, b"hello solana!");
+    assert_eq!(lamports, Rent::default().minimum_balance(len));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_patch_out_of_bounds() -> Result<(), TransportError> {
+    let fx = fixture(b"hello").await?;
+
+    let err = send(
+        &fx,
+        instructions::patch(
+            &program_id(),
+            &fx.payer.pubkey(),
+            "Test",
+            b"k-0",
+            6,
+            b"x",
+            0,
+        ),
+    )
+    .await
+    .unwrap_err();
+
+    assert_eq!(
+        utils::custom_error(err),
+        Some(SolDbError::OffsetOutOfBounds)
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_append_and_truncate() -> Result<(), TransportError> {
+    let fx = fixture(b"abc").await?;
+
+    send(
+        &fx,
+        instructions::append(
+            &program_id(),
+            &fx.payer.pubkey(),
+            "Test",
+            b"k-0",
+            b"defgh",
+            0,
+        ),
+    )
+    .await?;
+
+    let (val, lamports, len) = stored(&fx).await;
+    assert_eq!(val, b"abcdefgh");
+    assert_eq!(lamports, Rent::default().minimum_balance(len));
+
+    send(
+        &fx,
+        instructions::truncate(&program_id(), &fx.payer.pubkey(), "Test", b"k-0", 2, 0),
+    )
+    .await?;
+
+    let (val, lamports, len) = stored(&fx).await;
+    assert_eq!(val, b"ab");
+    assert_eq!(
+        lamports,
+        Rent::default().minimum_balance(len),
+        "freed rent should be refunded to the owner"
+    );
+
+    Ok(())
+}
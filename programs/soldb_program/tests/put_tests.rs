@@ -1,6 +1,6 @@
 mod utils;
 
-use borsh::{BorshDeserialize, BorshSerialize};
+use borsh::BorshSerialize;
 use solana_program_test::*;
 use solana_sdk::{
     instruction::{AccountMeta, Instruction},
@@ -11,9 +11,10 @@ use solana_sdk::{
 };
 use solana_system_interface::program;
 use soldb_program::{
-    accounts::{SolTable, SolValue},
+    accounts::SolValue,
     id as program_id,
     instructions::{Put, SolDbIntructions},
+    pda::find_directory_address,
 };
 use utils::setup;
 
@@ -21,21 +22,18 @@ use utils::setup;
 async fn test_put() -> Result<(), TransportError> {
     let (banks_client, payer, last_blockhash) = setup().await?;
 
-    let name = "Test".to_string();
-    let table = SolTable { name };
     let (pda_table_pubkey, table_bump) =
-        utils::init_table(&banks_client, &payer, last_blockhash, &table).await?;
+        utils::init_table(&banks_client, &payer, last_blockhash, "Test").await?;
 
     let key: Vec<u8> = "k-0".into();
     let value: Vec<u8> = "v-0".into();
-    let sol_value = SolValue { val: value.clone() };
     let (pda_val_pubkey, value_bump) = utils::insert(
         &banks_client,
         &payer,
         last_blockhash,
         &pda_table_pubkey,
         key.into(),
-        &sol_value,
+        &value,
     )
     .await?;
 
@@ -57,6 +55,10 @@ async fn test_put() -> Result<(), TransportError> {
             AccountMeta::new(pda_table_pubkey, false),
             AccountMeta::new(pda_val_pubkey, false),
             AccountMeta::new_readonly(program::ID, false),
+            AccountMeta::new(
+                find_directory_address(&program_id(), &pda_table_pubkey, 0).0,
+                false,
+            ),
         ],
         data: ix_data,
     };
@@ -96,8 +98,8 @@ async fn test_put() -> Result<(), TransportError> {
         .await
         .unwrap()
         .unwrap();
-    let stored: SolValue = BorshDeserialize::try_from_slice(&val_after_acc.data).unwrap();
-    assert_eq!(stored.val, new_payload, "payload must be updated");
+    let stored = SolValue::payload(&val_after_acc.data).unwrap();
+    assert_eq!(stored, new_payload, "payload must be updated");
 
     let new_len = val_after_acc.data.len();
     assert!(new_len >= old_len, "should have grown or stayed same");
@@ -0,0 +1,225 @@
+mod utils;
+
+use solana_program_test::*;
+use solana_sdk::{
+    hash::Hash, instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer,
+    transaction::Transaction, transport::TransportError,
+};
+use solana_system_interface::instruction::transfer;
+use soldb_program::{
+    accounts::{SolNonce, SolValue},
+    error::SolDbError,
+    hook::MutationKind,
+    id as program_id, instructions,
+    pda::{find_directory_address, find_nonce_address, find_value_address},
+    relay::{self, RelayedMessage},
+};
+use utils::{process, setup};
+
+fn message(table: Pubkey, kind: MutationKind, payload: &[u8], nonce: u64) -> RelayedMessage {
+    RelayedMessage {
+        kind,
+        table,
+        key: b"k-0".to_vec(),
+        payload: payload.to_vec(),
+        nonce,
+        expiry: i64::MAX,
+    }
+}
+
+/// Creates a relayer holding enough lamports to pay for the writes.
+async fn relayer(
+    banks_client: &BanksClient,
+    payer: &Keypair,
+    last_blockhash: Hash,
+) -> Result<Keypair, TransportError> {
+    let relayer = Keypair::new();
+    let fund = transfer(&payer.pubkey(), &relayer.pubkey(), 1_000_000_000);
+    process(banks_client, payer, last_blockhash, fund).await?;
+    Ok(relayer)
+}
+
+/// Submits `ix` signed by `relayer` alone, after the ed25519 instruction
+/// verifying the signature of `message` by `signer`.
+async fn relay(
+    banks_client: &BanksClient,
+    relayer: &Keypair,
+    last_blockhash: Hash,
+    signer: &Keypair,
+    message: &RelayedMessage,
+    ix: Instruction,
+) -> Result<(), TransportError> {
+    let bytes = message.to_bytes().unwrap();
+    let signature = signer.sign_message(&bytes);
+    let verify = relay::signature_instruction(&signer.pubkey(), &signature.into(), &bytes);
+
+    let txn = Transaction::new_signed_with_payer(
+        &[verify, ix],
+        Some(&relayer.pubkey()),
+        &[relayer],
+        last_blockhash,
+    );
+    banks_client.process_transaction(txn).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_relayed_insert_put_delete() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let (table, _) = utils::init_table(&banks_client, &payer, last_blockhash, "Test").await?;
+    let relayer = relayer(&banks_client, &payer, last_blockhash).await?;
+    let (value, _) = find_value_address(&program_id, &table, &owner, b"k-0");
+    let balance = banks_client.get_balance(owner).await?;
+
+    let insert = message(table, MutationKind::Insert, b"v-0", 0);
+    let ix =
+        instructions::relayed_insert(&program_id, &relayer.pubkey(), &owner, "Test", &insert, 0);
+    relay(&banks_client, &relayer, last_blockhash, &payer, &insert, ix).await?;
+
+    let put = message(table, MutationKind::Put, b"v-1", 1);
+    let ix = instructions::relayed_put(&program_id, &relayer.pubkey(), &owner, "Test", &put, 0);
+    relay(&banks_client, &relayer, last_blockhash, &payer, &put, ix).await?;
+
+    let account = banks_client.get_account(value).await?.unwrap();
+    assert_eq!(SolValue::payload(&account.data).unwrap(), b"v-1");
+    assert_eq!(utils::table_keys(&banks_client, &table).await?, 1);
+
+    // The owner receives the lamports of the closed value and page, which
+    // the relayer paid for.
+    let (directory, _) = find_directory_address(&program_id, &table, 0);
+    let refund = account.lamports + banks_client.get_balance(directory).await?;
+
+    let delete = message(table, MutationKind::Delete, b"", 2);
+    let ix = instructions::relayed_delete(
+        &program_id,
+        &relayer.pubkey(),
+        &owner,
+        "Test",
+        &delete,
+        0,
+        1,
+    );
+    relay(&banks_client, &relayer, last_blockhash, &payer, &delete, ix).await?;
+
+    assert!(banks_client.get_account(value).await?.is_none());
+    assert_eq!(utils::table_keys(&banks_client, &table).await?, 0);
+
+    // The owner only signed messages, the relayer paid for everything.
+    assert_eq!(banks_client.get_balance(owner).await?, balance + refund);
+
+    let (nonce, _) = find_nonce_address(&program_id, &owner);
+    let account = banks_client.get_account(nonce).await?.unwrap();
+    assert_eq!(SolNonce::load(&account.data).unwrap().nonce, 3);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_relayed_write_cannot_be_replayed() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let (table, _) = utils::init_table(&banks_client, &payer, last_blockhash, "Test").await?;
+    utils::insert(
+        &banks_client,
+        &payer,
+        last_blockhash,
+        &table,
+        b"k-0".to_vec(),
+        b"v",
+    )
+    .await?;
+    let relayer = relayer(&banks_client, &payer, last_blockhash).await?;
+
+    let put = message(table, MutationKind::Put, b"v-0", 0);
+    let ix = instructions::relayed_put(&program_id, &relayer.pubkey(), &owner, "Test", &put, 0);
+    relay(&banks_client, &relayer, last_blockhash, &payer, &put, ix).await?;
+
+    // Another relayer submits the same signed message again.
+    let other = self::relayer(&banks_client, &payer, last_blockhash).await?;
+    let ix = instructions::relayed_put(&program_id, &other.pubkey(), &owner, "Test", &put, 0);
+    let err = relay(&banks_client, &other, last_blockhash, &payer, &put, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::NonceMismatch));
+
+    // Nonces cannot be skipped either.
+    let put = message(table, MutationKind::Put, b"v-2", 2);
+    let ix = instructions::relayed_put(&program_id, &relayer.pubkey(), &owner, "Test", &put, 0);
+    let err = relay(&banks_client, &relayer, last_blockhash, &payer, &put, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::NonceMismatch));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_relayed_write_requires_owner_signature() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let (table, _) = utils::init_table(&banks_client, &payer, last_blockhash, "Test").await?;
+    let relayer = relayer(&banks_client, &payer, last_blockhash).await?;
+
+    // Signed by the relayer instead of the owner.
+    let insert = message(table, MutationKind::Insert, b"v-0", 0);
+    let ix =
+        instructions::relayed_insert(&program_id, &relayer.pubkey(), &owner, "Test", &insert, 0);
+    let err = relay(
+        &banks_client,
+        &relayer,
+        last_blockhash,
+        &relayer,
+        &insert,
+        ix,
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::InvalidSignature));
+
+    // The instruction writes another payload than the one signed.
+    let signed = message(table, MutationKind::Insert, b"v-1", 0);
+    let forged = message(table, MutationKind::Insert, b"forged", 0);
+    let ix =
+        instructions::relayed_insert(&program_id, &relayer.pubkey(), &owner, "Test", &forged, 0);
+    let err = relay(&banks_client, &relayer, last_blockhash, &payer, &signed, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::InvalidSignature));
+
+    // No ed25519 instruction at all.
+    let insert = message(table, MutationKind::Insert, b"v-2", 0);
+    let ix =
+        instructions::relayed_insert(&program_id, &relayer.pubkey(), &owner, "Test", &insert, 0);
+    let err = process(&banks_client, &relayer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::InvalidSignature));
+
+    let expired = RelayedMessage {
+        expiry: 0,
+        ..message(table, MutationKind::Insert, b"v-3", 0)
+    };
+    let ix =
+        instructions::relayed_insert(&program_id, &relayer.pubkey(), &owner, "Test", &expired, 0);
+    let err = relay(
+        &banks_client,
+        &relayer,
+        last_blockhash,
+        &payer,
+        &expired,
+        ix,
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::SignatureExpired));
+
+    Ok(())
+}
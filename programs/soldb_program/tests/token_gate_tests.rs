@@ -0,0 +1,235 @@
+mod utils;
+
+use solana_program_test::*;
+use solana_sdk::{
+    account::Account, hash::Hash, instruction::Instruction, pubkey::Pubkey, signature::Keypair,
+    signer::Signer, transport::TransportError,
+};
+use soldb_program::{
+    accounts::{SolValue, TokenGate},
+    error::SolDbError,
+    id as program_id, instructions,
+    pda::{find_table_address, find_value_address},
+};
+use spl_generic_token::{token, token_2022};
+use utils::process;
+
+/// Length of an SPL Token account without extensions.
+const TOKEN_ACCOUNT_LEN: usize = 165;
+
+/// Offset of the state of a token account, initialized at `1`.
+const TOKEN_STATE_OFFSET: usize = 108;
+
+/// Account of `token_program` holding `amount` of `mint` for `owner`.
+fn token_account(token_program: Pubkey, mint: &Pubkey, owner: &Pubkey, amount: u64) -> Account {
+    let mut data = vec![0; TOKEN_ACCOUNT_LEN];
+    data[..32].copy_from_slice(mint.as_ref());
+    data[32..64].copy_from_slice(owner.as_ref());
+    data[64..72].copy_from_slice(&amount.to_le_bytes());
+    data[TOKEN_STATE_OFFSET] = 1;
+
+    Account {
+        lamports: 1_000_000_000,
+        data,
+        owner: token_program,
+        ..Account::default()
+    }
+}
+
+/// Processes `ix`, built for the owner, signed by `holder` with its token
+/// account `token`.
+async fn as_holder(
+    banks_client: &BanksClient,
+    holder: &Keypair,
+    last_blockhash: Hash,
+    token: &Pubkey,
+    ix: Instruction,
+) -> Result<(), TransportError> {
+    let ix = instructions::with_token_account(ix, &holder.pubkey(), token);
+    process(banks_client, holder, last_blockhash, ix).await
+}
+
+#[tokio::test]
+async fn test_token_holders_insert_and_put() -> Result<(), TransportError> {
+    let program_id = program_id();
+    let mint = Pubkey::new_unique();
+    let holder = Keypair::new();
+    let (rich, rich_2022, poor, foreign) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+
+    let pid = Pubkey::new_from_array(program_id.to_bytes());
+    let mut program_test = ProgramTest::new("soldb_program", pid, None);
+    program_test.add_account(
+        holder.pubkey(),
+        Account {
+            lamports: 1_000_000_000,
+            ..Account::default()
+        },
+    );
+    let accounts = [
+        (rich, token::id(), mint, 10),
+        (rich_2022, token_2022::id(), mint, 25),
+        (poor, token::id(), mint, 9),
+        (foreign, token::id(), Pubkey::new_unique(), 10),
+    ];
+    for (address, token_program, mint, amount) in accounts {
+        let account = token_account(token_program, &mint, &holder.pubkey(), amount);
+        program_test.add_account(address, account);
+    }
+    let (banks_client, payer, last_blockhash) = program_test.start().await;
+    let owner = payer.pubkey();
+
+    let (table, _) = utils::init_table(&banks_client, &payer, last_blockhash, "Test").await?;
+    let gate = TokenGate {
+        mint,
+        min_amount: 10,
+    };
+    let ix = instructions::set_token_gate(&program_id, &owner, "Test", Some(gate));
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let ix = instructions::insert(&program_id, &table, &owner, b"k-0", b"v-0", 0);
+    as_holder(&banks_client, &holder, last_blockhash, &rich, ix).await?;
+
+    // Token-2022 accounts pass the gate as well.
+    let ix = instructions::put(&program_id, &owner, "Test", b"k-0", b"v-1", 0);
+    as_holder(&banks_client, &holder, last_blockhash, &rich_2022, ix).await?;
+
+    let (value, _) = find_value_address(&program_id, &table, &owner, b"k-0");
+    let account = banks_client.get_account(value).await?.unwrap();
+    assert_eq!(SolValue::payload(&account.data).unwrap(), b"v-1");
+
+    // Below the minimum balance, or holding another mint.
+    for (token, payload) in [(&poor, b"v-2"), (&foreign, b"v-3")] {
+        let ix = instructions::insert(&program_id, &table, &owner, b"k-1", payload, 0);
+        let err = as_holder(&banks_client, &holder, last_blockhash, token, ix)
+            .await
+            .unwrap_err();
+        assert_eq!(utils::custom_error(err), Some(SolDbError::TokenGateFailed));
+    }
+
+    // Holders may not delete.
+    let ix = instructions::delete(&program_id, &owner, "Test", b"k-0", 0, 1);
+    let err = as_holder(&banks_client, &holder, last_blockhash, &rich, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::MissingRole));
+
+    // Nor may another signer use the token account of the holder.
+    let ix = instructions::put(&program_id, &owner, "Test", b"k-0", b"v-4", 0);
+    let ix = instructions::with_token_account(ix, &owner, &rich);
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::TokenGateFailed));
+
+    let ix = instructions::set_token_gate(&program_id, &owner, "Test", None);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let ix = instructions::put(&program_id, &owner, "Test", b"k-0", b"v-5", 0);
+    let err = as_holder(&banks_client, &holder, last_blockhash, &rich, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::TokenGateFailed));
+    assert_eq!(utils::table_keys(&banks_client, &table).await?, 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_holders_overwrite_each_other() -> Result<(), TransportError> {
+    let program_id = program_id();
+    let mint = Pubkey::new_unique();
+    let (alice, bob) = (Keypair::new(), Keypair::new());
+    let (alice_token, bob_token) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+    let pid = Pubkey::new_from_array(program_id.to_bytes());
+    let mut program_test = ProgramTest::new("soldb_program", pid, None);
+    for (holder, address) in [(&alice, alice_token), (&bob, bob_token)] {
+        program_test.add_account(
+            holder.pubkey(),
+            Account {
+                lamports: 1_000_000_000,
+                ..Account::default()
+            },
+        );
+        let account = token_account(token::id(), &mint, &holder.pubkey(), 1);
+        program_test.add_account(address, account);
+    }
+    let (banks_client, payer, last_blockhash) = program_test.start().await;
+    let owner = payer.pubkey();
+
+    let (table, _) = utils::init_table(&banks_client, &payer, last_blockhash, "Test").await?;
+    let gate = TokenGate {
+        mint,
+        min_amount: 1,
+    };
+    let ix = instructions::set_token_gate(&program_id, &owner, "Test", Some(gate));
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let ix = instructions::insert(&program_id, &table, &owner, b"k-0", b"alice", 0);
+    as_holder(&banks_client, &alice, last_blockhash, &alice_token, ix).await?;
+
+    // The key is the owner's, not Alice's, so Bob may put over it.
+    let ix = instructions::put(&program_id, &owner, "Test", b"k-0", b"bob", 0);
+    as_holder(&banks_client, &bob, last_blockhash, &bob_token, ix).await?;
+
+    let (value, _) = find_value_address(&program_id, &table, &owner, b"k-0");
+    let account = banks_client.get_account(value).await?.unwrap();
+    assert_eq!(SolValue::payload(&account.data).unwrap(), b"bob");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_plain_inserts_skip_the_gate() -> Result<(), TransportError> {
+    let program_id = program_id();
+    let mint = Pubkey::new_unique();
+    let stranger = Keypair::new();
+
+    let pid = Pubkey::new_from_array(program_id.to_bytes());
+    let mut program_test = ProgramTest::new("soldb_program", pid, None);
+    program_test.add_account(
+        stranger.pubkey(),
+        Account {
+            lamports: 1_000_000_000,
+            ..Account::default()
+        },
+    );
+    let (banks_client, payer, last_blockhash) = program_test.start().await;
+    let owner = payer.pubkey();
+
+    let gate = TokenGate {
+        mint,
+        min_amount: 1,
+    };
+    let ix = instructions::init_global_table(&program_id, &owner, "Global");
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+    let (table, _) = utils::init_table(&banks_client, &payer, last_blockhash, "Test").await?;
+    for name in ["Global", "Test"] {
+        let ix = instructions::set_token_gate(&program_id, &owner, name, Some(gate));
+        process(&banks_client, &payer, last_blockhash, ix).await?;
+    }
+
+    // Without tokens, a plain insert cannot take a key of a global table.
+    let (global, _) = find_table_address(&program_id, &owner, "Global");
+    let ix = instructions::global_insert(&program_id, &global, &stranger.pubkey(), b"k-0", b"v", 0);
+    let err = process(&banks_client, &stranger, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::MissingRole));
+    assert_eq!(utils::table_keys(&banks_client, &global).await?, 0);
+
+    // Nor one of a dedicated table: the value is the stranger's own, which
+    // the table does not list.
+    let ix = instructions::insert(&program_id, &table, &stranger.pubkey(), b"k-0", b"v", 0);
+    process(&banks_client, &stranger, last_blockhash, ix).await?;
+    let (value, _) = find_value_address(&program_id, &table, &owner, b"k-0");
+    assert!(banks_client.get_account(value).await?.is_none());
+    assert_eq!(utils::table_keys(&banks_client, &table).await?, 0);
+
+    Ok(())
+}
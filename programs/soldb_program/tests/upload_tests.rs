@@ -0,0 +1,116 @@
+mod utils;
+
+use solana_program_test::*;
+use solana_sdk::{
+    hash::Hash, instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer,
+    transport::TransportError,
+};
+use soldb_program::{
+    accounts::{SolUpload, SolValue},
+    error::SolDbError,
+    id as program_id, instructions,
+    pda::{find_upload_address, find_value_address},
+};
+use utils::setup;
+
+const CHUNK: usize = 900;
+
+struct Fixture {
+    banks_client: BanksClient,
+    payer: Keypair,
+    last_blockhash: Hash,
+    value: Pubkey,
+    buffer: Pubkey,
+}
+
+async fn fixture() -> Result<Fixture, TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+
+    let (table_pubkey, _bump) =
+        utils::init_table(&banks_client, &payer, last_blockhash, "Test").await?;
+
+    let (value, _) = find_value_address(&program_id(), &table_pubkey, &payer.pubkey(), b"k-0");
+    let (buffer, _) = find_upload_address(&program_id(), &value);
+
+    Ok(Fixture {
+        banks_client,
+        payer,
+        last_blockhash,
+        value,
+        buffer,
+    })
+}
+
+async fn send(fx: &Fixture, ix: Instruction) -> Result<(), TransportError> {
+    utils::process(&fx.banks_client, &fx.payer, fx.last_blockhash, ix).await
+}
+
+async fn upload(fx: &Fixture, payload: &[u8], digest: [u8; 32]) -> Result<(), TransportError> {
+    let owner = fx.payer.pubkey();
+
+    send(
+        fx,
+        instructions::open_upload(
+            &program_id(),
+            &owner,
+            "Test",
+            b"k-0",
+            payload.len() as u32,
+            digest,
+            0,
+        ),
+    )
+    .await?;
+
+    for (i, chunk) in payload.chunks(CHUNK).enumerate() {
+        let offset = (i * CHUNK) as u32;
+        send(
+            fx,
+            instructions::write_chunk(&program_id(), &owner, "Test", b"k-0", offset, chunk),
+        )
+        .await?;
+    }
+
+    send(
+        fx,
+        instructions::commit_upload(&program_id(), &owner, "Test", b"k-0", 0),
+    )
+    .await
+}
+
+fn digest_of(payload: &[u8]) -> [u8; 32] {
+    payload
+        .chunks(CHUNK)
+        .enumerate()
+        .fold([0; 32], |digest, (i, chunk)| {
+            SolUpload::chain(&digest, (i * CHUNK) as u32, chunk)
+        })
+}
+
+#[tokio::test]
+async fn test_upload_larger_than_realloc_limit() -> Result<(), TransportError> {
+    let fx = fixture().await?;
+
+    let payload: Vec<u8> = (0..25 * 1024).map(|i| (i % 251) as u8).collect();
+    upload(&fx, &payload, digest_of(&payload)).await?;
+
+    let account = fx.banks_client.get_account(fx.value).await?.unwrap();
+    assert_eq!(SolValue::payload(&account.data).unwrap(), payload);
+
+    let buffer = fx.banks_client.get_account(fx.buffer).await?;
+    assert!(buffer.is_none(), "buffer should be closed after commit");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_upload_digest_mismatch() -> Result<(), TransportError> {
+    let fx = fixture().await?;
+
+    let payload: Vec<u8> = vec![42; 2 * CHUNK];
+    let err = upload(&fx, &payload, [9; 32]).await.unwrap_err();
+
+    assert_eq!(utils::custom_error(err), Some(SolDbError::DigestMismatch));
+
+    Ok(())
+}
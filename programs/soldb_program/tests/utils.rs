@@ -1,21 +1,20 @@
 #![allow(dead_code)]
 
-use borsh::BorshSerialize;
 use solana_program_test::{BanksClient, ProgramTest};
 use solana_sdk::{
     hash::Hash,
-    instruction::{AccountMeta, Instruction},
+    instruction::{Instruction, InstructionError},
     pubkey::Pubkey,
     signature::Keypair,
     signer::Signer,
-    transaction::Transaction,
+    transaction::{Transaction, TransactionError},
     transport::TransportError,
 };
-use solana_system_interface::program;
 use soldb_program::{
-    accounts::{SolTable, SolValue},
-    id as program_id,
-    instructions::SolDbIntructions,
+    accounts::{SolDirectory, SolTable},
+    error::SolDbError,
+    id as program_id, instructions,
+    pda::{find_table_address, find_value_address},
 };
 
 pub async fn setup() -> Result<(BanksClient, Keypair, Hash), TransportError> {
@@ -25,42 +24,28 @@ pub async fn setup() -> Result<(BanksClient, Keypair, Hash), TransportError> {
     Ok(program_test.start().await)
 }
 
+/// Extracts the program's typed error from a failed transaction.
+pub fn custom_error(err: impl Into<TransportError>) -> Option<SolDbError> {
+    match err.into() {
+        TransportError::TransactionError(TransactionError::InstructionError(
+            _,
+            InstructionError::Custom(code),
+        )) => SolDbError::try_from(code).ok(),
+        _ => None,
+    }
+}
+
 pub async fn init_table(
     banks_client: &BanksClient,
     payer: &Keypair,
     last_blockhash: Hash,
-    table: &SolTable,
+    name: &str,
 ) -> Result<(Pubkey, u8), TransportError> {
-    let program_id = soldb_program::id();
-
-    let (pda_pubkey, bump) = Pubkey::find_program_address(
-        &[table.name.as_bytes(), payer.pubkey().as_ref()],
-        &program_id,
-    );
-
-    let instr = SolDbIntructions::InitTable(soldb_program::instructions::InitTable {
-        name: table.name.clone(),
-        bump,
-    });
-    let mut ix_data = Vec::new();
-    instr.serialize(&mut ix_data).unwrap();
-
-    let accounts = vec![
-        AccountMeta::new(payer.pubkey(), true),
-        AccountMeta::new(pda_pubkey, false),
-        AccountMeta::new_readonly(program::ID, false),
-    ];
-
-    let ix = Instruction {
-        program_id: soldb_program::id(),
-        accounts,
-        data: ix_data,
-    };
-
-    let txn =
-        Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[&payer], last_blockhash);
+    let program_id = program_id();
+    let (pda_pubkey, bump) = find_table_address(&program_id, &payer.pubkey(), name);
 
-    banks_client.process_transaction_with_metadata(txn).await?;
+    let ix = instructions::init_table(&program_id, &payer.pubkey(), name);
+    process(banks_client, payer, last_blockhash, ix).await?;
 
     Ok((pda_pubkey, bump))
 }
@@ -71,40 +56,35 @@ pub async fn insert(
     last_blockhash: Hash,
     table: &Pubkey,
     key: Vec<u8>,
-    sol_value: &SolValue,
+    payload: &[u8],
 ) -> Result<(Pubkey, u8), TransportError> {
-    let program_id = soldb_program::id();
+    let program_id = program_id();
+    let (pda_pubkey, bump) = find_value_address(&program_id, table, &payer.pubkey(), &key);
 
-    let (pda_pubkey, bump) = Pubkey::find_program_address(
-        &[&key, &table.to_bytes(), payer.pubkey().as_ref()],
-        &program_id,
-    );
+    let page = SolDirectory::page_of(table_keys(banks_client, table).await?);
+    let ix = instructions::insert(&program_id, table, &payer.pubkey(), &key, payload, page);
+    process(banks_client, payer, last_blockhash, ix).await?;
 
-    let instr = SolDbIntructions::Insert(soldb_program::instructions::Insert {
-        key,
-        payload: sol_value.val.clone(),
-        bump,
-    });
-    let mut ix_data = Vec::new();
-    instr.serialize(&mut ix_data).unwrap();
-
-    let accounts = vec![
-        AccountMeta::new_readonly(table.clone(), false),
-        AccountMeta::new(pda_pubkey, false),
-        AccountMeta::new(payer.pubkey(), true),
-        AccountMeta::new_readonly(program::id(), false),
-    ];
+    Ok((pda_pubkey, bump))
+}
 
-    let ix = Instruction {
-        program_id: soldb_program::id(),
-        accounts,
-        data: ix_data,
-    };
+/// Number of keys listed in the directory of `table`.
+pub async fn table_keys(banks_client: &BanksClient, table: &Pubkey) -> Result<u32, TransportError> {
+    let account = banks_client.get_account(*table).await?.unwrap();
+    Ok(SolTable::load(&account.data).unwrap().keys)
+}
 
+/// Signs `ix` with `payer` and processes it, failing on any transaction error.
+pub async fn process(
+    banks_client: &BanksClient,
+    payer: &Keypair,
+    last_blockhash: Hash,
+    ix: Instruction,
+) -> Result<(), TransportError> {
     let txn =
         Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[&payer], last_blockhash);
 
-    banks_client.process_transaction_with_metadata(txn).await?;
+    banks_client.process_transaction(txn).await?;
 
-    Ok((pda_pubkey, bump))
+    Ok(())
 }
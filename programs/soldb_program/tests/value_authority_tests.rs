@@ -0,0 +1,187 @@
+mod utils;
+
+use solana_program_test::*;
+use solana_sdk::{
+    hash::Hash, instruction::Instruction, signature::Keypair, signer::Signer,
+    transport::TransportError,
+};
+use solana_system_interface::instruction::transfer;
+use soldb_program::{
+    accounts::{SolValue, SolValueAuthority},
+    error::SolDbError,
+    id as program_id, instructions,
+    pda::{find_value_address, find_value_authority_address},
+};
+use utils::{process, setup};
+
+/// Creates a wallet holding enough lamports to pay for its writes.
+async fn holder(
+    banks_client: &BanksClient,
+    payer: &Keypair,
+    last_blockhash: Hash,
+) -> Result<Keypair, TransportError> {
+    let holder = Keypair::new();
+    let fund = transfer(&payer.pubkey(), &holder.pubkey(), 1_000_000_000);
+    process(banks_client, payer, last_blockhash, fund).await?;
+    Ok(holder)
+}
+
+/// Processes `ix`, built for the owner, signed by `authority` alone.
+async fn as_authority(
+    banks_client: &BanksClient,
+    authority: &Keypair,
+    last_blockhash: Hash,
+    ix: Instruction,
+) -> Result<(), TransportError> {
+    let ix = instructions::with_value_authority(&program_id(), ix, &authority.pubkey());
+    process(banks_client, authority, last_blockhash, ix).await
+}
+
+#[tokio::test]
+async fn test_value_authority_is_transferable() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let (table, _) = utils::init_table(&banks_client, &payer, last_blockhash, "Test").await?;
+    utils::insert(
+        &banks_client,
+        &payer,
+        last_blockhash,
+        &table,
+        b"k-0".to_vec(),
+        b"v-0",
+    )
+    .await?;
+    let first = holder(&banks_client, &payer, last_blockhash).await?;
+    let second = holder(&banks_client, &payer, last_blockhash).await?;
+
+    let ix = instructions::set_value_authority(
+        &program_id,
+        &owner,
+        &owner,
+        "Test",
+        b"k-0",
+        Some(first.pubkey()),
+    );
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let (value, _) = find_value_address(&program_id, &table, &owner, b"k-0");
+    let (record, _) = find_value_authority_address(&program_id, &value);
+    let account = banks_client.get_account(record).await?.unwrap();
+    assert_eq!(
+        SolValueAuthority::load(&account.data).unwrap().authority,
+        first.pubkey()
+    );
+
+    // The owner no longer writes the value.
+    let ix = instructions::put(&program_id, &owner, "Test", b"k-0", b"v-1", 0);
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::ValueClaimed));
+
+    let ix = instructions::put(&program_id, &owner, "Test", b"k-0", b"v-2", 0);
+    as_authority(&banks_client, &first, last_blockhash, ix).await?;
+
+    let account = banks_client.get_account(value).await?.unwrap();
+    assert_eq!(SolValue::payload(&account.data).unwrap(), b"v-2");
+
+    // Only the current authority hands the value over.
+    let ix =
+        instructions::set_value_authority(&program_id, &owner, &owner, "Test", b"k-0", Some(owner));
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::MissingSigner));
+
+    let ix = instructions::set_value_authority(
+        &program_id,
+        &first.pubkey(),
+        &owner,
+        "Test",
+        b"k-0",
+        Some(second.pubkey()),
+    );
+    process(&banks_client, &first, last_blockhash, ix).await?;
+
+    let ix = instructions::put(&program_id, &owner, "Test", b"k-0", b"v-3", 0);
+    let err = as_authority(&banks_client, &first, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::MissingSigner));
+
+    let ix = instructions::put(&program_id, &owner, "Test", b"k-0", b"v-4", 0);
+    as_authority(&banks_client, &second, last_blockhash, ix).await?;
+
+    // Releasing the value hands it back to the owner.
+    let ix = instructions::set_value_authority(
+        &program_id,
+        &second.pubkey(),
+        &owner,
+        "Test",
+        b"k-0",
+        None,
+    );
+    process(&banks_client, &second, last_blockhash, ix).await?;
+    assert!(banks_client.get_account(record).await?.is_none());
+
+    let ix = instructions::put(&program_id, &owner, "Test", b"k-0", b"v-5", 0);
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let account = banks_client.get_account(value).await?.unwrap();
+    assert_eq!(SolValue::payload(&account.data).unwrap(), b"v-5");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_value_authority_only_puts() -> Result<(), TransportError> {
+    let (banks_client, payer, last_blockhash) = setup().await?;
+    let program_id = program_id();
+    let owner = payer.pubkey();
+
+    let (table, _) = utils::init_table(&banks_client, &payer, last_blockhash, "Test").await?;
+    utils::insert(
+        &banks_client,
+        &payer,
+        last_blockhash,
+        &table,
+        b"k-0".to_vec(),
+        b"v-0",
+    )
+    .await?;
+    let authority = holder(&banks_client, &payer, last_blockhash).await?;
+
+    let ix = instructions::set_value_authority(
+        &program_id,
+        &owner,
+        &owner,
+        "Test",
+        b"k-0",
+        Some(authority.pubkey()),
+    );
+    process(&banks_client, &payer, last_blockhash, ix).await?;
+
+    let ix = instructions::delete(&program_id, &owner, "Test", b"k-0", 0, 1);
+    let err = as_authority(&banks_client, &authority, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::MissingRole));
+
+    // Neither does the owner delete a value it no longer controls.
+    let ix = instructions::delete(&program_id, &owner, "Test", b"k-0", 0, 1);
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::ValueClaimed));
+
+    let ix = instructions::patch(&program_id, &owner, "Test", b"k-0", 0, b"x", 0);
+    let err = process(&banks_client, &payer, last_blockhash, ix)
+        .await
+        .unwrap_err();
+    assert_eq!(utils::custom_error(err), Some(SolDbError::ValueClaimed));
+    assert_eq!(utils::table_keys(&banks_client, &table).await?, 1);
+
+    Ok(())
+}
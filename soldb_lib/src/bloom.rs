@@ -0,0 +1,75 @@
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+use soldb_program::{
+    accounts::{SolBloom, SolTable},
+    instructions,
+    pda::{find_bloom_address, find_table_address},
+};
+
+use crate::{
+    read::{fetch, key_accounts},
+    storage::Result,
+};
+
+/// Most directory pages or slabs a single `RebuildBloom` reads, keeping the
+/// transaction within its account limit.
+const SOURCES_PER_REBUILD: usize = 16;
+
+/// Checks the Bloom filter of the table `name` of `owner` for `key`, as
+/// `MaybeContains` does on chain.
+///
+/// `false` is exact: the key is not in the table. `true` means it may be,
+/// and is also the answer for a table without a filter. Returns `false`
+/// when the table does not exist.
+pub fn maybe_contains(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    key: &[u8],
+) -> Result<bool> {
+    let (table, _) = find_table_address(program_id, owner, name);
+    let Some(table_account) = fetch(rpc, &table)? else {
+        return Ok(false);
+    };
+    if !SolTable::load(&table_account.data)?.bloom {
+        return Ok(true);
+    }
+
+    let (bloom, _) = find_bloom_address(program_id, &table);
+    match fetch(rpc, &bloom)? {
+        Some(account) => Ok(SolBloom::contains(&account.data, key)?),
+        None => Ok(true),
+    }
+}
+
+/// Builds the `RebuildBloom` instructions that refill the filter of the
+/// table `name` of `owner` from its current keys, to run after many
+/// deletes. The first one resets the filter, so they must land in order.
+pub fn rebuild(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+) -> Result<Vec<Instruction>> {
+    let (table, _) = find_table_address(program_id, owner, name);
+    let Some(table_account) = fetch(rpc, &table)? else {
+        return Ok(Vec::new());
+    };
+    let sources = key_accounts(program_id, &table, &SolTable::load(&table_account.data)?)?;
+
+    if sources.is_empty() {
+        return Ok(vec![instructions::rebuild_bloom(
+            program_id,
+            owner,
+            name,
+            true,
+            &[],
+        )]);
+    }
+    Ok(sources
+        .chunks(SOURCES_PER_REBUILD)
+        .enumerate()
+        .map(|(i, chunk)| instructions::rebuild_bloom(program_id, owner, name, i == 0, chunk))
+        .collect())
+}
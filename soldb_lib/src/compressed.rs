@@ -0,0 +1,343 @@
+use std::collections::BTreeMap;
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use borsh::BorshDeserialize;
+use solana_client::{rpc_client::GetConfirmedSignaturesForAddress2Config, rpc_client::RpcClient};
+use solana_sdk::{hash::hash, instruction::Instruction, pubkey::Pubkey, signature::Signature};
+use solana_transaction_status_client_types::UiTransactionEncoding;
+use soldb_program::{
+    accounts::{SolTable, SolTree, TableMode},
+    error::SolDbError,
+    events::{CompressedEvent, EVENT_TAG},
+    instructions, merkle,
+    pda::{find_table_address, find_tree_address},
+};
+
+use crate::{
+    read::fetch,
+    storage::{Error, Result},
+};
+
+/// Key and value stored in a leaf of a compressed table.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Entry {
+    pub key: Vec<u8>,
+    pub value: Vec<u8>,
+}
+
+/// Contents of a compressed table, rebuilt from the events of its writes.
+///
+/// Besides reading the table, it builds the proofs that `CompressedPut` and
+/// `CompressedDelete` need against its current root.
+#[derive(Clone, Debug)]
+pub struct CompressedTable {
+    pub table: Pubkey,
+    pub depth: u8,
+    /// Tree sequence number of the last event applied.
+    pub sequence: u64,
+    leaves: BTreeMap<u32, Entry>,
+}
+
+impl CompressedTable {
+    /// An empty table, as created by `InitTable`.
+    pub fn new(table: Pubkey, depth: u8) -> Self {
+        Self {
+            table,
+            depth,
+            sequence: 0,
+            leaves: BTreeMap::new(),
+        }
+    }
+
+    /// Applies the next event of the table. Events of other tables are
+    /// ignored, and events must come in sequence order without gaps.
+    pub fn apply(&mut self, event: &CompressedEvent) -> Result<()> {
+        let (CompressedEvent::Put { table, .. } | CompressedEvent::Delete { table, .. }) = event;
+        if *table != self.table {
+            return Ok(());
+        }
+        let sequence = sequence(event);
+        if sequence != self.sequence + 1 {
+            return Err(Error::MissingEvent(self.sequence + 1));
+        }
+
+        match event {
+            CompressedEvent::Put {
+                index,
+                key,
+                payload,
+                ..
+            } => {
+                self.leaves.insert(
+                    *index,
+                    Entry {
+                        key: key.clone(),
+                        value: payload.clone(),
+                    },
+                );
+            }
+            CompressedEvent::Delete { index, .. } => {
+                self.leaves.remove(index);
+            }
+        }
+        self.sequence = sequence;
+
+        Ok(())
+    }
+
+    /// Value of `key`, if the table holds it.
+    pub fn get(&self, key: &[u8]) -> Option<&[u8]> {
+        self.index_of(key)
+            .map(|index| self.leaves[&index].value.as_slice())
+    }
+
+    /// Entries of the table by leaf index.
+    pub fn entries(&self) -> impl Iterator<Item = (u32, &Entry)> {
+        self.leaves.iter().map(|(index, entry)| (*index, entry))
+    }
+
+    /// Leaf index holding `key`.
+    pub fn index_of(&self, key: &[u8]) -> Option<u32> {
+        self.leaves
+            .iter()
+            .find(|(_, entry)| entry.key == key)
+            .map(|(index, _)| *index)
+    }
+
+    /// Lowest free leaf index, if the tree is not full.
+    pub fn free_index(&self) -> Option<u32> {
+        (0..1u64 << self.depth)
+            .map(|index| index as u32)
+            .find(|index| !self.leaves.contains_key(index))
+    }
+
+    /// Nodes of every level of the tree that are not the root of an empty
+    /// subtree, from the leaves up to the root.
+    fn levels(&self) -> Vec<BTreeMap<u32, [u8; 32]>> {
+        let mut level: BTreeMap<u32, [u8; 32]> = self
+            .leaves
+            .iter()
+            .map(|(index, entry)| (*index, merkle::leaf(&entry.key, &entry.value)))
+            .collect();
+
+        let mut levels = Vec::with_capacity(self.depth as usize + 1);
+        for height in 0..self.depth as usize {
+            let empty = merkle::empty_subtree(height);
+            let parents = level
+                .keys()
+                .map(|index| index / 2)
+                .map(|parent| {
+                    let left = level.get(&(2 * parent)).unwrap_or(&empty);
+                    let right = level.get(&(2 * parent + 1)).unwrap_or(&empty);
+                    (parent, merkle::node(left, right))
+                })
+                .collect();
+            levels.push(std::mem::replace(&mut level, parents));
+        }
+        levels.push(level);
+        levels
+    }
+
+    /// Root of the tree, which matches the tree account once every event
+    /// was applied.
+    pub fn root(&self) -> [u8; 32] {
+        let levels = self.levels();
+        levels[self.depth as usize]
+            .get(&0)
+            .copied()
+            .unwrap_or_else(|| merkle::empty_subtree(self.depth as usize))
+    }
+
+    /// Proof of the leaf at `index` against the current root.
+    pub fn proof(&self, index: u32) -> Vec<[u8; 32]> {
+        let levels = self.levels();
+        (0..self.depth as usize)
+            .map(|height| {
+                let sibling = (index >> height) ^ 1;
+                levels[height]
+                    .get(&sibling)
+                    .copied()
+                    .unwrap_or_else(|| merkle::empty_subtree(height))
+            })
+            .collect()
+    }
+
+    /// Builds the `CompressedPut` instruction writing `payload` under `key`,
+    /// in the leaf already holding the key or else in the lowest free one.
+    /// Returns `None` when the key is new and the tree is full.
+    pub fn put(
+        &self,
+        program_id: &Pubkey,
+        owner: &Pubkey,
+        name: &str,
+        key: &[u8],
+        payload: &[u8],
+    ) -> Option<Instruction> {
+        let (index, previous_hash) = match self.index_of(key) {
+            Some(index) => (index, Some(hash(&self.leaves[&index].value).to_bytes())),
+            None => (self.free_index()?, None),
+        };
+
+        Some(instructions::compressed_put(
+            program_id,
+            owner,
+            name,
+            key,
+            payload,
+            previous_hash,
+            self.root(),
+            index,
+            self.proof(index),
+        ))
+    }
+
+    /// Builds the `CompressedDelete` instruction removing `key`, or returns
+    /// `None` when the table does not hold it.
+    pub fn delete(
+        &self,
+        program_id: &Pubkey,
+        owner: &Pubkey,
+        name: &str,
+        key: &[u8],
+    ) -> Option<Instruction> {
+        let index = self.index_of(key)?;
+
+        Some(instructions::compressed_delete(
+            program_id,
+            owner,
+            name,
+            key,
+            hash(&self.leaves[&index].value).to_bytes(),
+            self.root(),
+            index,
+            self.proof(index),
+        ))
+    }
+}
+
+/// Events logged by `program_id` in the log messages of one transaction, in
+/// order.
+///
+/// Only data logged while the program itself is executing counts, so that
+/// another program of the transaction cannot forge events.
+pub fn events(program_id: &Pubkey, logs: &[String]) -> Vec<CompressedEvent> {
+    let program = program_id.to_string();
+    let mut stack: Vec<&str> = Vec::new();
+    let mut events = Vec::new();
+
+    for line in logs {
+        let Some(rest) = line.strip_prefix("Program ") else {
+            continue;
+        };
+        if let Some(data) = rest.strip_prefix("data: ") {
+            if stack.last() == Some(&program.as_str()) {
+                events.extend(decode_event(data));
+            }
+        } else if let Some((id, status)) = rest.split_once(' ') {
+            if status.starts_with("invoke [") {
+                stack.push(id);
+            } else if status == "success" || status.starts_with("failed") {
+                stack.pop();
+            }
+        }
+    }
+
+    events
+}
+
+/// Decodes the fields of a `Program data:` line into an event, if it is one.
+fn decode_event(data: &str) -> Option<CompressedEvent> {
+    let mut fields = data.split(' ').map(|field| STANDARD.decode(field).ok());
+    if fields.next()?? != EVENT_TAG {
+        return None;
+    }
+    let event = fields.next()??;
+    if fields.next().is_some() {
+        return None;
+    }
+    CompressedEvent::try_from_slice(&event).ok()
+}
+
+/// Rebuilds the compressed table `name` of `owner` by replaying the logs of
+/// every successful transaction that wrote to its tree.
+///
+/// The result is checked against the root of the tree account, so that a
+/// node serving incomplete history cannot go unnoticed; a write landing
+/// during the replay also fails the check, and the replay can be retried.
+/// Returns `None` when the table does not exist.
+pub fn replay(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+) -> Result<Option<CompressedTable>> {
+    let (table, _) = find_table_address(program_id, owner, name);
+    let Some(table_account) = fetch(rpc, &table)? else {
+        return Ok(None);
+    };
+    let TableMode::Compressed { depth } = SolTable::load(&table_account.data)?.mode else {
+        return Err(SolDbError::WrongTableMode.into());
+    };
+
+    let (tree, _) = find_tree_address(program_id, &table);
+    let mut signatures = Vec::new();
+    let mut before = None;
+    loop {
+        let config = GetConfirmedSignaturesForAddress2Config {
+            before,
+            until: None,
+            limit: None,
+            commitment: Some(rpc.commitment()),
+        };
+        let page = rpc.get_signatures_for_address_with_config(&tree, config)?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        before = last.signature.parse::<Signature>().ok();
+        signatures.extend(
+            page.into_iter()
+                .filter(|status| status.err.is_none())
+                .filter_map(|status| status.signature.parse::<Signature>().ok()),
+        );
+        if before.is_none() {
+            break;
+        }
+    }
+
+    let mut events = Vec::new();
+    for signature in &signatures {
+        let transaction = rpc.get_transaction(signature, UiTransactionEncoding::Json)?;
+        let logs: Option<Vec<String>> = transaction
+            .transaction
+            .meta
+            .and_then(|meta| meta.log_messages.into());
+        events.extend(self::events(program_id, &logs.unwrap_or_default()));
+    }
+    events.sort_by_key(sequence);
+
+    let mut compressed = CompressedTable::new(table, depth);
+    for event in &events {
+        compressed.apply(event)?;
+    }
+
+    let Some(tree_account) = fetch(rpc, &tree)? else {
+        return Err(SolDbError::NotTree.into());
+    };
+    let tree_data = &tree_account.data;
+    if SolTree::load(tree_data)?.sequence != compressed.sequence {
+        return Err(Error::MissingEvent(compressed.sequence + 1));
+    }
+    if SolTree::root(tree_data)? != compressed.root() {
+        return Err(SolDbError::InvalidProof.into());
+    }
+
+    Ok(Some(compressed))
+}
+
+fn sequence(event: &CompressedEvent) -> u64 {
+    match event {
+        CompressedEvent::Put { sequence, .. } | CompressedEvent::Delete { sequence, .. } => {
+            *sequence
+        }
+    }
+}
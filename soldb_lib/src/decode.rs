@@ -0,0 +1,45 @@
+use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+use soldb_program::error::SolDbError;
+
+/// A `SolDbError` recovered from a failed transaction, together with the
+/// index of the instruction that raised it.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct DecodedError {
+    pub instruction_index: u8,
+    pub error: SolDbError,
+}
+
+impl std::fmt::Display for DecodedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "instruction {} failed with code {}: {}",
+            self.instruction_index,
+            self.error.code(),
+            self.error.message()
+        )
+    }
+}
+
+/// Turns `InstructionError::Custom(n)` back into the program's typed error.
+///
+/// Returns `None` for built-in instruction errors and for codes the program
+/// does not define.
+pub fn decode_instruction_error(err: &InstructionError) -> Option<SolDbError> {
+    match err {
+        InstructionError::Custom(code) => SolDbError::try_from(*code).ok(),
+        _ => None,
+    }
+}
+
+/// Extracts the typed program error from a failed transaction, if any.
+pub fn decode_transaction_error(err: &TransactionError) -> Option<DecodedError> {
+    match err {
+        TransactionError::InstructionError(index, instr_err) => decode_instruction_error(instr_err)
+            .map(|error| DecodedError {
+                instruction_index: *index,
+                error,
+            }),
+        _ => None,
+    }
+}
@@ -1 +1,6 @@
+pub mod bloom;
+pub mod compressed;
+pub mod decode;
+pub mod proof;
+pub mod read;
 pub mod storage;
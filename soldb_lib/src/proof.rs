@@ -0,0 +1,122 @@
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{hash::hash, instruction::Instruction, pubkey::Pubkey};
+use soldb_program::{
+    accounts::{SolDirectory, SolTable, SolValue},
+    error::SolDbError,
+    instructions, merkle,
+    pda::{find_directory_address, find_table_address},
+};
+
+use crate::{
+    read::{fetch, fetch_many, value_address},
+    storage::Result,
+};
+
+/// Merkle proof that a key of a dedicated table holds a value, which the
+/// program checks with `VerifyProof`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Proof {
+    /// Signer the value PDA is derived from, or the owner of a global table.
+    pub writer: Pubkey,
+    /// Directory position of the key.
+    pub index: u32,
+    pub value_hash: [u8; 32],
+    /// Siblings of the leaf from the bottom of the tree up to the root.
+    pub siblings: Vec<[u8; 32]>,
+}
+
+impl Proof {
+    /// Checks the proof of `key` against a table `root` off-chain.
+    pub fn verify(&self, key: &[u8], root: &[u8; 32]) -> bool {
+        let leaf = merkle::entry_leaf_from_hash(&self.writer, key, &self.value_hash);
+        merkle::fold(leaf, self.index, &self.siblings) == *root
+    }
+
+    /// Builds the `VerifyProof` instruction checking the proof of `key` in
+    /// `table` on-chain.
+    pub fn instruction(&self, program_id: &Pubkey, table: &Pubkey, key: &[u8]) -> Instruction {
+        instructions::verify_proof(
+            program_id,
+            table,
+            &self.writer,
+            key,
+            self.value_hash,
+            self.index,
+            self.siblings.clone(),
+        )
+    }
+}
+
+/// Builds the proof of `key` of `writer` holding a value hashing to
+/// `value_hash` from the data of every directory page of its table, in page
+/// order.
+///
+/// Returns `None` when the key is not listed in the directory.
+pub fn build_proof(
+    pages: &[Vec<u8>],
+    writer: &Pubkey,
+    key: &[u8],
+    value_hash: [u8; 32],
+) -> Result<Option<Proof>> {
+    let mut found = None;
+    let mut page_roots = Vec::with_capacity(pages.len());
+    for (page, data) in pages.iter().enumerate() {
+        if let Some(slot) = SolDirectory::find(data, writer, key)? {
+            found = Some((page, slot));
+        }
+        page_roots.push(merkle::root(&SolDirectory::leaves(data)));
+    }
+
+    let Some((page, slot)) = found else {
+        return Ok(None);
+    };
+    let leaves = SolDirectory::leaves(&pages[page]);
+
+    let mut siblings = merkle::proof(&leaves, slot);
+    siblings.extend(merkle::proof(&page_roots, page));
+
+    Ok(Some(Proof {
+        writer: *writer,
+        index: (page * SolDirectory::PAGE_KEYS + slot) as u32,
+        value_hash,
+        siblings,
+    }))
+}
+
+/// Reads the value of `key` in the dedicated table `name` of `owner` along
+/// with the proof that the table root commits to it.
+///
+/// Returns `None` when the table or the key does not exist.
+pub fn prove(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    key: &[u8],
+) -> Result<Option<(Vec<u8>, Proof)>> {
+    let (table, _) = find_table_address(program_id, owner, name);
+    let Some(table_account) = fetch(rpc, &table)? else {
+        return Ok(None);
+    };
+    let sol_table = SolTable::load(&table_account.data)?;
+    if !sol_table.mode.is_dedicated() {
+        return Err(SolDbError::WrongTableMode.into());
+    }
+
+    let value = value_address(program_id, &table, sol_table.mode, owner, key);
+    let Some(value_account) = fetch(rpc, &value)? else {
+        return Ok(None);
+    };
+    let payload = SolValue::payload(&value_account.data)?.to_vec();
+
+    let addresses: Vec<Pubkey> = (0..sol_table.page_roots.len() as u32)
+        .map(|page| find_directory_address(program_id, &table, page).0)
+        .collect();
+    let pages = fetch_many(rpc, &addresses)?
+        .into_iter()
+        .map(|account| account.map(|account| account.data).unwrap_or_default())
+        .collect::<Vec<_>>();
+
+    let proof = build_proof(&pages, owner, key, hash(&payload).to_bytes())?;
+    Ok(proof.map(|proof| (payload, proof)))
+}
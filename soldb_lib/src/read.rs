@@ -0,0 +1,289 @@
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{account::Account, pubkey::Pubkey};
+use soldb_program::{
+    accounts::{
+        EntryState, ForeignKey, SlabEntry, SolAcl, SolDelegation, SolDirectory, SolNonce, SolSlab,
+        SolTable, SolValue, SolValueAuthority, TableMode, TokenGate,
+    },
+    error::SolDbError,
+    pda::{
+        find_acl_address, find_delegation_address, find_directory_address,
+        find_global_value_address, find_nonce_address, find_slab_address, find_table_address,
+        find_value_address, find_value_authority_address,
+    },
+};
+
+use crate::storage::Result;
+
+/// Most accounts a single `getMultipleAccounts` request may ask for.
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// Reads the value of `key` in the table `name` of `owner`, wherever the
+/// table keeps it: in its own PDA, inline in a slab, or in the PDA it was
+/// migrated to. Compressed tables keep their values in transaction logs and
+/// are read through [`crate::compressed::replay`] instead.
+///
+/// Returns `None` when the table or the key does not exist.
+pub fn get(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    key: &[u8],
+) -> Result<Option<Vec<u8>>> {
+    let (table, _) = find_table_address(program_id, owner, name);
+    let Some(table_account) = fetch(rpc, &table)? else {
+        return Ok(None);
+    };
+
+    let mode = SolTable::load(&table_account.data)?.mode;
+    match mode {
+        TableMode::Dedicated | TableMode::Global => {}
+        TableMode::Packed { slabs } => {
+            let (slab, _) = find_slab_address(program_id, &table, SolSlab::index_of(key, slabs));
+            let Some(slab_account) = fetch(rpc, &slab)? else {
+                return Ok(None);
+            };
+
+            match slab_entry(&slab_account.data, key)? {
+                Some(entry) if entry.state() == EntryState::Migrated => {}
+                Some(entry) => return Ok(Some(entry.value().to_vec())),
+                None => return Ok(None),
+            }
+        }
+        TableMode::Compressed { .. } => return Err(SolDbError::WrongTableMode.into()),
+    }
+
+    let value = value_address(program_id, &table, mode, owner, key);
+    match fetch(rpc, &value)? {
+        Some(account) => Ok(Some(SolValue::payload(&account.data)?.to_vec())),
+        None => Ok(None),
+    }
+}
+
+/// Lists the keys of the table `name` of `owner` by reading its directory
+/// pages, or its slabs for a packed table, so that no `getProgramAccounts`
+/// call is needed.
+///
+/// Keys of a dedicated table come in directory order: the key at index `i`
+/// is listed in page `SolDirectory::page_of(i)`, which is what `Delete`
+/// expects. Returns `None` when the table does not exist, and fails with
+/// `WrongTableMode` for a compressed table.
+pub fn keys(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+) -> Result<Option<Vec<Vec<u8>>>> {
+    let (table, _) = find_table_address(program_id, owner, name);
+    let Some(table_account) = fetch(rpc, &table)? else {
+        return Ok(None);
+    };
+    let sol_table = SolTable::load(&table_account.data)?;
+    let addresses = key_accounts(program_id, &table, &sol_table)?;
+
+    let mut keys = Vec::new();
+    for account in fetch_many(rpc, &addresses)?.into_iter().flatten() {
+        if sol_table.mode.is_dedicated() {
+            keys.extend(directory_keys(&account.data)?);
+        } else {
+            keys.extend(slab_keys(&account.data)?);
+        }
+    }
+
+    Ok(Some(keys))
+}
+
+/// Foreign key declared by the table `name` of `owner`, from which writes to
+/// the table build the meta of their parent value with
+/// `instructions::parent_meta`.
+///
+/// Returns `None` when the table does not exist or declares no foreign key.
+pub fn foreign_key(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+) -> Result<Option<ForeignKey>> {
+    let (table, _) = find_table_address(program_id, owner, name);
+    match fetch(rpc, &table)? {
+        Some(account) => Ok(SolTable::load(&account.data)?.foreign_key),
+        None => Ok(None),
+    }
+}
+
+/// Token gating the table `name` of `owner`, whose holders write it by
+/// passing a token account of the mint with `instructions::with_token_account`.
+///
+/// Returns `None` when the table does not exist or is not gated.
+pub fn token_gate(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+) -> Result<Option<TokenGate>> {
+    let (table, _) = find_table_address(program_id, owner, name);
+    match fetch(rpc, &table)? {
+        Some(account) => Ok(SolTable::load(&account.data)?.token_gate),
+        None => Ok(None),
+    }
+}
+
+/// Account receiving the lamports of the values closed by `Moderate` in the
+/// table `name` of `owner`: its moderation destination, or the owner when it
+/// set none.
+///
+/// Returns `None` when the table does not exist.
+pub fn moderation_destination(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+) -> Result<Option<Pubkey>> {
+    let (table, _) = find_table_address(program_id, owner, name);
+    match fetch(rpc, &table)? {
+        Some(account) => Ok(Some(
+            SolTable::load(&account.data)?
+                .moderation_destination
+                .unwrap_or(*owner),
+        )),
+        None => Ok(None),
+    }
+}
+
+/// Nonce the next relayed write of `signer` must be signed with, `0` until
+/// the first one created its nonce account.
+pub fn relay_nonce(rpc: &RpcClient, program_id: &Pubkey, signer: &Pubkey) -> Result<u64> {
+    let (nonce, _) = find_nonce_address(program_id, signer);
+    match fetch(rpc, &nonce)? {
+        Some(account) => Ok(SolNonce::load(&account.data)?.nonce),
+        None => Ok(0),
+    }
+}
+
+/// Delegation of `delegate` on the table `name` of `owner`, telling the
+/// prefix, writes and expiry slot left to the session key.
+///
+/// Returns `None` when the owner granted no delegation or revoked it.
+pub fn delegation(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    delegate: &Pubkey,
+) -> Result<Option<SolDelegation>> {
+    let (table, _) = find_table_address(program_id, owner, name);
+    let (delegation, _) = find_delegation_address(program_id, &table, delegate);
+    match fetch(rpc, &delegation)? {
+        Some(account) => Ok(Some(SolDelegation::load(&account.data)?)),
+        None => Ok(None),
+    }
+}
+
+/// Role flags of `SolAcl` that `principal` holds on the table `name` of
+/// `owner`, `0` when it holds none.
+pub fn roles(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    principal: &Pubkey,
+) -> Result<u8> {
+    let (table, _) = find_table_address(program_id, owner, name);
+    let (acl, _) = find_acl_address(program_id, &table, principal);
+    match fetch(rpc, &acl)? {
+        Some(account) => Ok(SolAcl::load(&account.data)?.roles),
+        None => Ok(0),
+    }
+}
+
+/// Authority controlling the value of `key` in the table `name` of `owner`,
+/// which alone may put it.
+///
+/// Returns `None` when the value has no authority and belongs to the owner.
+pub fn value_authority(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name: &str,
+    key: &[u8],
+) -> Result<Option<Pubkey>> {
+    let (table, _) = find_table_address(program_id, owner, name);
+    let Some(table_account) = fetch(rpc, &table)? else {
+        return Ok(None);
+    };
+    let mode = SolTable::load(&table_account.data)?.mode;
+
+    let value = value_address(program_id, &table, mode, owner, key);
+    let (record, _) = find_value_authority_address(program_id, &value);
+    match fetch(rpc, &record)? {
+        Some(account) => Ok(Some(SolValueAuthority::load(&account.data)?.authority)),
+        None => Ok(None),
+    }
+}
+
+/// Keys listed in the data of a directory page.
+pub fn directory_keys(data: &[u8]) -> Result<Vec<Vec<u8>>> {
+    Ok(SolDirectory::keys(data)?)
+}
+
+/// Keys stored in the data of a slab account, inline or migrated.
+pub fn slab_keys(data: &[u8]) -> Result<Vec<Vec<u8>>> {
+    Ok(SolSlab::keys(data)?)
+}
+
+/// Accounts listing the keys of `table`: its directory pages, or its slabs
+/// for a packed table.
+pub(crate) fn key_accounts(
+    program_id: &Pubkey,
+    table: &Pubkey,
+    sol_table: &SolTable,
+) -> Result<Vec<Pubkey>> {
+    Ok(match sol_table.mode {
+        TableMode::Dedicated | TableMode::Global if sol_table.keys == 0 => Vec::new(),
+        TableMode::Dedicated | TableMode::Global => (0..=SolDirectory::page_of(sol_table.keys - 1))
+            .map(|page| find_directory_address(program_id, table, page).0)
+            .collect(),
+        TableMode::Packed { slabs } => (0..slabs)
+            .map(|index| find_slab_address(program_id, table, index).0)
+            .collect(),
+        TableMode::Compressed { .. } => return Err(SolDbError::WrongTableMode.into()),
+    })
+}
+
+/// Value PDA of `key` in `table`, in the namespace of `owner` unless the
+/// table is global.
+pub(crate) fn value_address(
+    program_id: &Pubkey,
+    table: &Pubkey,
+    mode: TableMode,
+    owner: &Pubkey,
+    key: &[u8],
+) -> Pubkey {
+    if mode == TableMode::Global {
+        find_global_value_address(program_id, table, key).0
+    } else {
+        find_value_address(program_id, table, owner, key).0
+    }
+}
+
+/// Looks `key` up in the data of a slab account.
+pub fn slab_entry(data: &[u8], key: &[u8]) -> Result<Option<SlabEntry>> {
+    SolSlab::load(data)?;
+    Ok(SolSlab::find(data, key).map(|slot| SolSlab::entry(data, slot)))
+}
+
+pub(crate) fn fetch(rpc: &RpcClient, address: &Pubkey) -> Result<Option<Account>> {
+    Ok(rpc
+        .get_account_with_commitment(address, rpc.commitment())?
+        .value)
+}
+
+/// Fetches `addresses` in as few requests as possible, in order.
+pub(crate) fn fetch_many(rpc: &RpcClient, addresses: &[Pubkey]) -> Result<Vec<Option<Account>>> {
+    let mut accounts = Vec::with_capacity(addresses.len());
+    for chunk in addresses.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        accounts.extend(rpc.get_multiple_accounts(chunk)?);
+    }
+    Ok(accounts)
+}
@@ -1,3 +1,6 @@
+use solana_client::client_error::ClientError;
+use solana_sdk::program_error::ProgramError as AccountError;
+use soldb_program::error::SolDbError;
 use thiserror::Error as ThisError;
 
 pub mod memory;
@@ -6,6 +9,20 @@ pub mod memory;
 pub enum Error {
     #[error("IO Error")]
     IoError(#[from] std::io::Error),
+    #[error("Program Error: {0}")]
+    ProgramError(#[from] SolDbError),
+    #[error("Invalid Account: {0}")]
+    AccountError(#[from] AccountError),
+    #[error("RPC Error: {0}")]
+    RpcError(#[from] Box<ClientError>),
+    #[error("Missing event {0} of a compressed table")]
+    MissingEvent(u64),
+}
+
+impl From<ClientError> for Error {
+    fn from(err: ClientError) -> Self {
+        Self::RpcError(Box::new(err))
+    }
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
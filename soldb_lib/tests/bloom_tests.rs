@@ -0,0 +1,54 @@
+use soldb_program::{accounts::SolBloom, error::SolDbError};
+
+const BITS: u32 = 1024;
+
+fn empty_bloom() -> Vec<u8> {
+    let mut data = vec![0; SolBloom::space(BITS)];
+    SolBloom::new(BITS, 4, 255).store(&mut data);
+    data
+}
+
+fn key(i: usize) -> Vec<u8> {
+    format!("k-{i}").into_bytes()
+}
+
+#[test]
+fn test_bloom_has_no_false_negatives() {
+    let mut data = empty_bloom();
+
+    for i in 0..64 {
+        SolBloom::insert(&mut data, &key(i)).unwrap();
+    }
+    for i in 0..64 {
+        assert!(SolBloom::contains(&data, &key(i)).unwrap());
+    }
+    assert_eq!(SolBloom::load(&data).unwrap().count, 64);
+
+    // With 4 hashes over 1024 bits and 64 keys, false positives are rare.
+    let false_positives = (64..1064)
+        .filter(|i| SolBloom::contains(&data, &key(*i)).unwrap())
+        .count();
+    assert!(false_positives < 20, "{false_positives} false positives");
+}
+
+#[test]
+fn test_bloom_clear() {
+    let mut data = empty_bloom();
+    SolBloom::insert(&mut data, b"k").unwrap();
+
+    SolBloom::clear(&mut data).unwrap();
+
+    assert!(!SolBloom::contains(&data, b"k").unwrap());
+    assert_eq!(SolBloom::load(&data).unwrap().count, 0);
+}
+
+#[test]
+fn test_bloom_rejects_other_accounts() {
+    let mut data = empty_bloom();
+    data.pop();
+
+    assert_eq!(
+        SolBloom::contains(&data, b"k"),
+        Err(SolDbError::NotBloom.into())
+    );
+}
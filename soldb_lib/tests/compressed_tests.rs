@@ -0,0 +1,204 @@
+use base64::{Engine, engine::general_purpose::STANDARD};
+use borsh::to_vec;
+use solana_sdk::{hash::hash, program_error::ProgramError, pubkey::Pubkey};
+use soldb_lib::{
+    compressed::{CompressedTable, events},
+    storage::Error,
+};
+use soldb_program::{
+    accounts::SolTree,
+    error::SolDbError,
+    events::{CompressedEvent, EVENT_TAG},
+    merkle,
+};
+
+const DEPTH: u8 = 4;
+
+fn empty_tree() -> Vec<u8> {
+    let mut data = vec![0; SolTree::SPACE];
+    SolTree::init(&mut data, DEPTH, 255);
+    data
+}
+
+/// Writes `value` under `key` in both the tree account and the replica, the
+/// way `CompressedPut` does and its event is replayed.
+fn put(data: &mut [u8], replica: &mut CompressedTable, key: &[u8], value: &[u8]) {
+    let (index, previous_leaf) = match replica.index_of(key) {
+        Some(index) => (index, merkle::leaf(key, replica.get(key).unwrap())),
+        None => (replica.free_index().unwrap(), merkle::EMPTY),
+    };
+    SolTree::replace_leaf(
+        data,
+        replica.root(),
+        previous_leaf,
+        merkle::leaf(key, value),
+        index,
+        &replica.proof(index),
+    )
+    .unwrap();
+
+    let event = CompressedEvent::Put {
+        table: replica.table,
+        sequence: SolTree::load(data).unwrap().sequence,
+        index,
+        key: key.to_vec(),
+        payload: value.to_vec(),
+    };
+    replica.apply(&event).unwrap();
+}
+
+#[test]
+fn test_replica_follows_tree() {
+    let mut data = empty_tree();
+    let mut replica = CompressedTable::new(Pubkey::new_unique(), DEPTH);
+    assert_eq!(SolTree::root(&data).unwrap(), replica.root());
+
+    for i in 0..5u8 {
+        put(&mut data, &mut replica, &[b'k', i], &[i; 3]);
+        assert_eq!(SolTree::root(&data).unwrap(), replica.root());
+    }
+    put(&mut data, &mut replica, b"k\x02", b"updated");
+    assert_eq!(replica.index_of(b"k\x02"), Some(2));
+    assert_eq!(replica.get(b"k\x02"), Some(&b"updated"[..]));
+    assert_eq!(SolTree::root(&data).unwrap(), replica.root());
+
+    let index = replica.index_of(b"k\x01").unwrap();
+    SolTree::replace_leaf(
+        &mut data,
+        replica.root(),
+        merkle::leaf_from_hash(b"k\x01", &hash(&[1; 3]).to_bytes()),
+        merkle::EMPTY,
+        index,
+        &replica.proof(index),
+    )
+    .unwrap();
+    let event = CompressedEvent::Delete {
+        table: replica.table,
+        sequence: SolTree::load(&data).unwrap().sequence,
+        index,
+        key: b"k\x01".to_vec(),
+    };
+    replica.apply(&event).unwrap();
+
+    assert_eq!(SolTree::root(&data).unwrap(), replica.root());
+    assert_eq!(replica.get(b"k\x01"), None);
+    assert_eq!(replica.free_index(), Some(1));
+}
+
+#[test]
+fn test_concurrent_writes_fast_forward() {
+    let mut data = empty_tree();
+    let replica = CompressedTable::new(Pubkey::new_unique(), DEPTH);
+    let root = replica.root();
+
+    // Both writes are built from the empty tree, the second one landing
+    // after the first changed the root.
+    for (index, key) in [(0, b"a"), (5, b"b")] {
+        SolTree::replace_leaf(
+            &mut data,
+            root,
+            merkle::EMPTY,
+            merkle::leaf(key, b"v"),
+            index,
+            &replica.proof(index),
+        )
+        .unwrap();
+    }
+
+    let mut expected = CompressedTable::new(replica.table, DEPTH);
+    for (sequence, (index, key)) in [(0, b"a"), (5, b"b")].into_iter().enumerate() {
+        let event = CompressedEvent::Put {
+            table: replica.table,
+            sequence: sequence as u64 + 1,
+            index,
+            key: key.to_vec(),
+            payload: b"v".to_vec(),
+        };
+        expected.apply(&event).unwrap();
+    }
+    assert_eq!(SolTree::root(&data).unwrap(), expected.root());
+
+    // A leaf changed since the proof was built cannot be replaced from it.
+    let err = SolTree::replace_leaf(
+        &mut data,
+        root,
+        merkle::EMPTY,
+        merkle::leaf(b"c", b"v"),
+        0,
+        &replica.proof(0),
+    )
+    .unwrap_err();
+    assert_eq!(err, ProgramError::from(SolDbError::InvalidProof));
+}
+
+#[test]
+fn test_stale_root_is_rejected() {
+    let mut data = empty_tree();
+    let mut replica = CompressedTable::new(Pubkey::new_unique(), DEPTH);
+    let root = replica.root();
+
+    for i in 0..SolTree::MAX_BUFFER as u8 {
+        put(&mut data, &mut replica, &[b'k', i], b"v");
+    }
+
+    let index = replica.free_index().unwrap();
+    let err = SolTree::replace_leaf(
+        &mut data,
+        root,
+        merkle::EMPTY,
+        merkle::leaf(b"late", b"v"),
+        index,
+        &CompressedTable::new(replica.table, DEPTH).proof(index),
+    )
+    .unwrap_err();
+    assert_eq!(err, ProgramError::from(SolDbError::InvalidProof));
+}
+
+#[test]
+fn test_events_only_from_program() {
+    let program_id = Pubkey::new_unique();
+    let other = Pubkey::new_unique();
+    let event = CompressedEvent::Put {
+        table: Pubkey::new_unique(),
+        sequence: 1,
+        index: 0,
+        key: b"k".to_vec(),
+        payload: b"v".to_vec(),
+    };
+    let data = format!(
+        "Program data: {} {}",
+        STANDARD.encode(EVENT_TAG),
+        STANDARD.encode(to_vec(&event).unwrap())
+    );
+
+    let logs = vec![
+        format!("Program {other} invoke [1]"),
+        data.clone(),
+        format!("Program {program_id} invoke [2]"),
+        data.clone(),
+        "Program log: unrelated".to_string(),
+        format!("Program {program_id} consumed 1000 of 200000 compute units"),
+        format!("Program {program_id} success"),
+        data,
+        format!("Program {other} success"),
+    ];
+
+    assert_eq!(events(&program_id, &logs), vec![event]);
+}
+
+#[test]
+fn test_events_must_be_contiguous() {
+    let mut replica = CompressedTable::new(Pubkey::new_unique(), DEPTH);
+    let event = CompressedEvent::Delete {
+        table: replica.table,
+        sequence: 2,
+        index: 0,
+        key: b"k".to_vec(),
+    };
+
+    let err = replica.apply(&event).unwrap_err();
+    assert!(
+        matches!(err, Error::MissingEvent(1)),
+        "unexpected error {err:?}"
+    );
+}
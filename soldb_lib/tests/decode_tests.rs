@@ -0,0 +1,47 @@
+use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+use soldb_lib::decode::{DecodedError, decode_instruction_error, decode_transaction_error};
+use soldb_program::error::SolDbError;
+
+#[test]
+fn test_decode_every_code() {
+    let mut code = 0;
+    while let Ok(expected) = SolDbError::try_from(code) {
+        assert_eq!(expected.code(), code, "code must be stable");
+        assert_eq!(SolDbError::try_from(expected.code()), Ok(expected));
+        assert!(!expected.message().is_empty());
+
+        let err = TransactionError::InstructionError(1, InstructionError::Custom(code));
+        let decoded = decode_transaction_error(&err).expect("custom code should decode");
+        assert_eq!(
+            decoded,
+            DecodedError {
+                instruction_index: 1,
+                error: expected,
+            }
+        );
+        assert!(decoded.to_string().contains(expected.message()));
+
+        code += 1;
+    }
+    assert!(code > 0, "codes must start at 0");
+
+    // The first code past the last error is left to other programs.
+    let err = InstructionError::Custom(code);
+    assert_eq!(decode_instruction_error(&err), None);
+}
+
+#[test]
+fn test_decode_unknown_code() {
+    let err = InstructionError::Custom(u32::MAX);
+    assert_eq!(decode_instruction_error(&err), None);
+}
+
+#[test]
+fn test_decode_builtin_error() {
+    let err = TransactionError::InstructionError(0, InstructionError::InvalidSeeds);
+    assert_eq!(decode_transaction_error(&err), None);
+    assert_eq!(
+        decode_transaction_error(&TransactionError::AccountInUse),
+        None
+    );
+}
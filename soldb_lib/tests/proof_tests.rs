@@ -0,0 +1,103 @@
+use solana_sdk::{hash::hash, pubkey::Pubkey};
+use soldb_lib::proof::build_proof;
+use soldb_program::{
+    accounts::{DirectoryEntry, SolDirectory},
+    merkle,
+};
+
+fn key(i: usize) -> Vec<u8> {
+    format!("k-{i}").into_bytes()
+}
+
+fn value(i: usize) -> Vec<u8> {
+    format!("v-{i}").into_bytes()
+}
+
+/// Writer of every key of the pages.
+const OWNER: Pubkey = Pubkey::new_from_array([1; 32]);
+
+/// Directory pages listing `count` keys, the way the program fills them.
+fn pages(count: usize) -> Vec<Vec<u8>> {
+    let mut pages = Vec::new();
+    for i in 0..count {
+        let slot = i % SolDirectory::PAGE_KEYS;
+        if slot == 0 {
+            let mut data = vec![0; SolDirectory::SPACE];
+            SolDirectory::new(pages.len() as u32, 255).store(&mut data);
+            pages.push(data);
+        }
+        let data = pages.last_mut().unwrap();
+        let mut directory = SolDirectory::load(data).unwrap();
+        SolDirectory::set_entry(
+            data,
+            slot,
+            &DirectoryEntry::new(
+                &OWNER,
+                &key(i),
+                merkle::entry_leaf(&OWNER, &key(i), &value(i)),
+            ),
+        );
+        directory.count += 1;
+        directory.store(data);
+    }
+    pages
+}
+
+fn table_root(pages: &[Vec<u8>]) -> [u8; 32] {
+    let page_roots: Vec<_> = pages
+        .iter()
+        .map(|data| merkle::root(&SolDirectory::leaves(data)))
+        .collect();
+    merkle::root(&page_roots)
+}
+
+#[test]
+fn test_proofs_fold_to_root() {
+    let count = 2 * SolDirectory::PAGE_KEYS + 3;
+    let pages = pages(count);
+    let root = table_root(&pages);
+
+    for i in [0, 5, SolDirectory::PAGE_KEYS, count - 1] {
+        let proof = build_proof(&pages, &OWNER, &key(i), hash(&value(i)).to_bytes())
+            .unwrap()
+            .unwrap();
+        assert_eq!(proof.index, i as u32);
+        assert_eq!(
+            proof.siblings.len(),
+            merkle::PAGE_DEPTH + merkle::depth(pages.len())
+        );
+        assert!(proof.verify(&key(i), &root), "proof of key {i}");
+        assert!(!proof.verify(&key(i + 1), &root));
+    }
+}
+
+#[test]
+fn test_proof_of_wrong_value_or_writer_fails() {
+    let pages = pages(3);
+    let root = table_root(&pages);
+
+    let proof = build_proof(&pages, &OWNER, &key(1), hash(b"other").to_bytes())
+        .unwrap()
+        .unwrap();
+    assert!(!proof.verify(&key(1), &root));
+
+    // The leaf commits to the writer, so the proof is only valid for it.
+    let mut proof = build_proof(&pages, &OWNER, &key(1), hash(&value(1)).to_bytes())
+        .unwrap()
+        .unwrap();
+    assert!(proof.verify(&key(1), &root));
+    proof.writer = Pubkey::new_unique();
+    assert!(!proof.verify(&key(1), &root));
+
+    assert!(
+        build_proof(&pages, &OWNER, &key(3), [0; 32])
+            .unwrap()
+            .is_none()
+    );
+    let other = Pubkey::new_unique();
+    assert!(
+        build_proof(&pages, &other, &key(1), [0; 32])
+            .unwrap()
+            .is_none()
+    );
+}
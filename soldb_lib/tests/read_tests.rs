@@ -0,0 +1,132 @@
+use solana_sdk::{program_error::ProgramError, pubkey::Pubkey};
+use soldb_lib::{
+    read::{directory_keys, slab_entry, slab_keys},
+    storage::Error,
+};
+use soldb_program::{
+    accounts::{DirectoryEntry, EntryState, SlabEntry, SolDirectory, SolSlab},
+    error::SolDbError,
+    merkle,
+};
+
+fn empty_slab() -> Vec<u8> {
+    let mut data = vec![0; SolSlab::SPACE];
+    SolSlab::new(0, 255).store(&mut data);
+    data
+}
+
+fn insert(data: &mut [u8], key: &[u8], value: &[u8]) -> Result<(), ProgramError> {
+    let slot = SolSlab::vacant(data, key)?;
+    SolSlab::set_entry(data, slot, &SlabEntry::new(key, value));
+    Ok(())
+}
+
+fn remove(data: &mut [u8], key: &[u8]) {
+    let slot = SolSlab::find(data, key).unwrap();
+    let mut entry = SolSlab::entry(data, slot);
+    entry.state = EntryState::Tombstone as u8;
+    SolSlab::set_entry(data, slot, &entry);
+}
+
+fn key(i: usize) -> Vec<u8> {
+    format!("k-{i}").into_bytes()
+}
+
+#[test]
+fn test_slab_fills_every_slot() {
+    let mut data = empty_slab();
+
+    for i in 0..SolSlab::SLOTS {
+        insert(&mut data, &key(i), &i.to_le_bytes()).unwrap();
+    }
+    for i in 0..SolSlab::SLOTS {
+        let entry = slab_entry(&data, &key(i)).unwrap().unwrap();
+        assert_eq!(entry.value(), i.to_le_bytes());
+    }
+
+    assert_eq!(
+        insert(&mut data, b"one more", b"v"),
+        Err(SolDbError::SlabFull.into())
+    );
+    assert_eq!(
+        insert(&mut data, &key(3), b"v"),
+        Err(SolDbError::AccountAlreadyExists.into())
+    );
+}
+
+#[test]
+fn test_slab_probes_past_tombstones() {
+    let mut data = empty_slab();
+
+    for i in 0..SolSlab::SLOTS {
+        insert(&mut data, &key(i), b"v").unwrap();
+    }
+    for i in (0..SolSlab::SLOTS).step_by(2) {
+        remove(&mut data, &key(i));
+    }
+
+    for i in 0..SolSlab::SLOTS {
+        let found = slab_entry(&data, &key(i)).unwrap().is_some();
+        assert_eq!(found, i % 2 == 1, "lookup of key {i}");
+    }
+
+    // Freed slots are reused, but a key can still only be stored once.
+    insert(&mut data, b"new", b"v").unwrap();
+    assert_eq!(
+        insert(&mut data, &key(1), b"v"),
+        Err(SolDbError::AccountAlreadyExists.into())
+    );
+}
+
+#[test]
+fn test_slab_entry_rejects_other_accounts() {
+    let data = vec![0; SolSlab::SPACE];
+    let err = slab_entry(&data, b"k").unwrap_err();
+    assert!(
+        matches!(&err, Error::AccountError(e) if *e == SolDbError::NotSlab.into()),
+        "unexpected error {err:?}"
+    );
+}
+
+#[test]
+fn test_slab_keys_skip_free_entries() {
+    let mut data = empty_slab();
+
+    for i in 0..4 {
+        insert(&mut data, &key(i), b"v").unwrap();
+    }
+    remove(&mut data, &key(2));
+
+    let mut keys = slab_keys(&data).unwrap();
+    keys.sort();
+    assert_eq!(keys, vec![key(0), key(1), key(3)]);
+}
+
+#[test]
+fn test_directory_keys_in_order() {
+    let (owner, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut data = vec![0; SolDirectory::SPACE];
+    let mut directory = SolDirectory::new(0, 255);
+    let entries = [(owner, key(0)), (other, key(1)), (owner, key(1))];
+    for (slot, (writer, key)) in entries.iter().enumerate() {
+        SolDirectory::set_entry(
+            &mut data,
+            slot,
+            &DirectoryEntry::new(writer, key, merkle::entry_leaf(writer, key, b"v")),
+        );
+        directory.count += 1;
+    }
+    directory.store(&mut data);
+
+    assert_eq!(directory_keys(&data).unwrap(), vec![key(0), key(1), key(1)]);
+    // Keys are found by writer, so that equal keys stay apart.
+    assert_eq!(SolDirectory::find(&data, &owner, &key(1)).unwrap(), Some(2));
+    assert_eq!(SolDirectory::find(&data, &other, &key(1)).unwrap(), Some(1));
+    assert_eq!(SolDirectory::find(&data, &other, &key(0)).unwrap(), None);
+
+    let err = directory_keys(&empty_slab()).unwrap_err();
+    assert!(
+        matches!(&err, Error::AccountError(e) if *e == SolDbError::NotDirectory.into()),
+        "unexpected error {err:?}"
+    );
+}